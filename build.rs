@@ -0,0 +1,39 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+/// Compiles the translations in po/ to catalogs in the build dir, they are used when
+/// `LOCALE_DIR` is not set, e.g. with `cargo run`. Packages install them to `LOCALE_DIR`:
+/// `msgfmt po/nl.po -o $LOCALE_DIR/nl/LC_MESSAGES/pipewire-config-gnome.mo`
+fn main() {
+    println!("cargo::rerun-if-changed=po");
+
+    let locale_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("locale");
+    let linguas = fs::read_to_string("po/LINGUAS").unwrap_or_default();
+    let languages = linguas
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    for language in languages {
+        let dir = locale_dir.join(language).join("LC_MESSAGES");
+        fs::create_dir_all(&dir).unwrap();
+
+        let status = Command::new("msgfmt")
+            .arg("--check")
+            .arg("--output-file")
+            .arg(dir.join("pipewire-config-gnome.mo"))
+            .arg(format!("po/{}.po", language))
+            .status();
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => panic!("msgfmt failed for po/{}.po with {}", language, status),
+            Err(error) => {
+                println!(
+                    "cargo::warning=Translations are not compiled, running msgfmt failed: {error}"
+                );
+                return;
+            }
+        }
+    }
+
+    println!("cargo::rustc-env=BUILD_LOCALE_DIR={}", locale_dir.display());
+}
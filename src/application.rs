@@ -1,30 +1,128 @@
 mod pages;
-mod pipewire;
+pub mod pipewire;
+pub mod preferences;
+pub mod service;
+mod watcher;
 mod window;
 
-use pages::{NavPage, Page, Pages};
-use pipewire::pipewire::Pipewire;
-use std::{cell::RefCell, rc::Rc};
-use window::ApplicationWindow;
+use crate::{config, i18n::gettext};
+use libadwaita::glib;
+use log::error;
+use pages::{Pages, registry};
+use pipewire::{host::PwHost, pipewire::Pipewire};
+use preferences::Preferences;
+use service::DbusService;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+use watcher::ConfigWatcher;
+use window::{ApplicationWindow, apply_dialog::ApplyDialog, error_page::ErrorPage};
 
 pub struct Application {
     pub window: ApplicationWindow,
     pub pipewire: Rc<Pipewire>,
     pub pages: Rc<RefCell<Pages>>,
+    pub service: Rc<DbusService>,
+    pub watcher: Rc<ConfigWatcher>,
+    pub preferences: Rc<Preferences>,
+    /// Set while the pages show values from the configs, the rows send no input then
+    pub is_updating: Cell<bool>,
 }
 impl Application {
-    pub fn new(adw_application: &libadwaita::Application) -> Rc<Self> {
+    const LOG_TARGET: &str = "application";
+
+    /// Starts the app, or shows why it can not.
+    pub fn start(adw_application: &libadwaita::Application, service: Rc<DbusService>) {
+        if let Err(error) = PwHost::check_permissions() {
+            error!(target: Self::LOG_TARGET, "{:?}", error);
+            ErrorPage::show(
+                adw_application,
+                &gettext("Missing Permission"),
+                &gettext(
+                    "The app needs write access to ~/.config/pipewire and ~/.config/wireplumber for its drop-in files. Grant it with this command or in Flatseal, then restart the app.",
+                ),
+                Some(&PwHost::get_override_command(config::APP_ID)),
+            );
+            return;
+        }
+
         let pipewire = match Pipewire::new() {
             Ok(pipewire) => pipewire,
-            Err(error) => todo!("Pipewire class error handling:\n{:?}", error),
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "{:?}", error);
+                Self::show_startup_error(adw_application, service, &[error], None);
+                return;
+            }
         };
-        let window = ApplicationWindow::new(adw_application);
-        let pages = Rc::new(RefCell::new(Pages::new()));
+        if !pipewire.errors.is_empty() {
+            let continue_application = adw_application.clone();
+            let continue_pipewire = pipewire.clone();
+            let continue_service = service.clone();
+            let on_continue = move || {
+                Self::start_with(
+                    &continue_application,
+                    continue_pipewire.clone(),
+                    continue_service.clone(),
+                )
+            };
+            Self::show_startup_error(
+                adw_application,
+                service,
+                &pipewire.errors,
+                Some(Box::new(on_continue)),
+            );
+            return;
+        }
+
+        Self::start_with(adw_application, pipewire, service);
+    }
+
+    /// Starts the app with the configs that could be read, their pages work.
+    fn start_with(
+        adw_application: &libadwaita::Application,
+        pipewire: Rc<Pipewire>,
+        service: Rc<DbusService>,
+    ) {
+        let application = Self::new(adw_application, pipewire, service);
+        Self::run_app(&application);
+        Self::connect_service(&application);
+        application.watcher.watch(&application);
+    }
+
+    fn show_startup_error(
+        adw_application: &libadwaita::Application,
+        service: Rc<DbusService>,
+        errors: &[anyhow::Error],
+        on_continue: Option<Box<dyn Fn()>>,
+    ) {
+        let retry_application = adw_application.clone();
+        ErrorPage::show_startup_error(
+            adw_application,
+            errors,
+            move || Self::start(&retry_application, service.clone()),
+            on_continue,
+        );
+    }
+
+    pub fn new(
+        adw_application: &libadwaita::Application,
+        pipewire: Rc<Pipewire>,
+        service: Rc<DbusService>,
+    ) -> Rc<Self> {
+        let preferences = Rc::new(Preferences::new());
+        let window = ApplicationWindow::new(adw_application, &preferences);
+        let pages = Rc::new(RefCell::new(Pages::new(&pipewire)));
+        let watcher = ConfigWatcher::new();
 
         return Rc::new(Self {
             window,
             pipewire,
             pages,
+            service,
+            watcher,
+            preferences,
+            is_updating: Cell::new(false),
         });
     }
 
@@ -32,17 +130,50 @@ impl Application {
         application.window.init(application);
 
         let sidebar = &application.window.view.sidebar;
-        {
+        let last_page = application.preferences.get_last_page();
+        let page_id = {
             // Scoped to drop borrow before continuing
             let pages = application.pages.borrow();
-            sidebar.add_nav_row(pages.main.get_title(), Page::Main);
-            sidebar.add_nav_row(pages.surround.get_title(), Page::Surround);
-        }
+            let descriptors = pages.get_descriptors();
+            for descriptor in &descriptors {
+                sidebar.add_nav_row(descriptor);
+            }
 
-        application.navigate(Page::Surround);
+            // The last page may be left out now, e.g. without WirePlumber
+            descriptors
+                .iter()
+                .find(|descriptor| last_page.as_deref() == Some(descriptor.id))
+                .map_or(registry::DEFAULT_PAGE, |descriptor| descriptor.id)
+        };
+
+        application.navigate(page_id);
+    }
+
+    /// Points the exported D-Bus interface at this app, pending values show up in the pages.
+    fn connect_service(application: &Rc<Application>) {
+        application
+            .service
+            .set_pipewire(application.pipewire.clone());
+        application.service.set_watcher(application.watcher.clone());
+
+        let pending_application = Rc::downgrade(application);
+        application.service.connect_pending(move || {
+            if let Some(application) = pending_application.upgrade() {
+                application
+                    .pages
+                    .borrow_mut()
+                    .show_pending(application.clone());
+            }
+        });
+        let applied_application = Rc::downgrade(application);
+        application.service.connect_applied(move |applied| {
+            if let Some(application) = applied_application.upgrade() {
+                glib::spawn_future_local(ApplyDialog::restart_affected(application, applied));
+            }
+        });
     }
 
-    fn navigate(&self, page: Page) {
-        self.window.view.navigate(page);
+    fn navigate(&self, page_id: &str) {
+        self.window.view.navigate(page_id);
     }
 }
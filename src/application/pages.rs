@@ -1,40 +1,388 @@
+mod clock_page;
+mod diagnostics_page;
+mod history_page;
 mod main_page;
+mod presets_page;
+pub mod registry;
+mod settings_page;
+mod speaker_layout;
+mod speaker_test;
 mod surround_page;
-
-use super::{Application, pipewire::config::PwConfig};
+mod value_rows;
+
+use super::{
+    Application,
+    pipewire::{
+        config::{PwConfig, PwOptions},
+        pipewire::Pipewire,
+        schema::{PwProperty, PwPropertyType, PwSchema},
+    },
+    window::apply_dialog::ApplyDialog,
+};
+use crate::i18n::{gettext, gettext_f};
+use clock_page::ClockPage;
 use convert_case::{Case, Casing};
+use diagnostics_page::DiagnosticsPage;
+use history_page::HistoryPage;
 use libadwaita::{
-    ComboRow, EntryRow, HeaderBar, NavigationPage, NavigationSplitView, PreferencesGroup,
-    PreferencesPage, PreferencesRow, SpinRow, SwitchRow, ToolbarView,
+    ActionRow, Banner, ComboRow, EntryRow, HeaderBar, NavigationPage, NavigationSplitView,
+    PreferencesGroup, PreferencesPage, PreferencesRow, SpinRow, SwitchRow, ToolbarView,
     gio::{ActionEntry, SimpleActionGroup},
-    glib::{VariantTy, object::Cast, variant::ToVariant},
+    glib::{self, VariantTy, object::Cast, variant::ToVariant},
     gtk::{
-        self, Adjustment, Orientation, StringList,
-        prelude::{EditableExt, WidgetExt},
+        self, Adjustment, Button, Label, MenuButton, Orientation, Popover, StringList,
+        prelude::{BoxExt, EditableExt, WidgetExt},
     },
-    prelude::{ComboRowExt, PreferencesGroupExt},
+    prelude::{ActionRowExt, ComboRowExt, EntryRowExt, PreferencesGroupExt},
 };
-use log::debug;
+use log::{debug, error, info};
 use main_page::MainPage;
+use presets_page::PresetsPage;
+use registry::{PageDescriptor, PageKind};
 use serde_json::json;
+use settings_page::SettingsPage;
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 use surround_page::SurroundPage;
+use value_rows::{ArrayRow, ObjectRow, ValueRows};
+
+/// The translated title of a key's row from the schema, or else made from the key.
+/// The section is already the group title.
+fn get_row_title(key: &str) -> String {
+    if let Some(title) = PwSchema::bundled()
+        .get(key)
+        .and_then(|property| property.title.as_deref())
+    {
+        return gettext(title);
+    }
+
+    let title = key
+        .split_once('.')
+        .map_or(key, |(_section, prop)| prop)
+        .replace('.', "-")
+        .to_lowercase()
+        .from_case(Case::Kebab)
+        .to_case(Case::Sentence);
+
+    gettext(&title)
+}
+
+/// A setting found by the search in the sidebar.
+pub struct SearchResult {
+    pub page_id: &'static str,
+    pub page_title: String,
+    pub key: String,
+    pub title: String,
+}
 
-#[repr(i32)]
-pub enum Page {
-    Main,
-    Surround,
+/// A page of the type its descriptor asks for.
+pub enum AnyPage {
+    Main(MainPage),
+    Settings(SettingsPage),
+    Surround(SurroundPage),
+    Clock(ClockPage),
+    Presets(PresetsPage),
+    History(HistoryPage),
+    Diagnostics(DiagnosticsPage),
+}
+
+/// Runs the same code for every page type, they all are a `NavPage`.
+macro_rules! with_page {
+    ($page:expr, $inner:ident => $body:expr) => {
+        match $page {
+            AnyPage::Main($inner) => $body,
+            AnyPage::Settings($inner) => $body,
+            AnyPage::Surround($inner) => $body,
+            AnyPage::Clock($inner) => $body,
+            AnyPage::Presets($inner) => $body,
+            AnyPage::History($inner) => $body,
+            AnyPage::Diagnostics($inner) => $body,
+        }
+    };
 }
 
+impl AnyPage {
+    fn new(descriptor: &'static PageDescriptor) -> Self {
+        match descriptor.kind {
+            PageKind::Main => Self::Main(MainPage::new(descriptor)),
+            PageKind::Settings => Self::Settings(SettingsPage::new(descriptor)),
+            PageKind::Surround => Self::Surround(SurroundPage::new(descriptor)),
+            PageKind::Clock => Self::Clock(ClockPage::new(descriptor)),
+            PageKind::Presets => Self::Presets(PresetsPage::new(descriptor)),
+            PageKind::History => Self::History(HistoryPage::new(descriptor)),
+            PageKind::Diagnostics => Self::Diagnostics(DiagnosticsPage::new(descriptor)),
+        }
+    }
+
+    fn get_title(&self) -> &str {
+        with_page!(self, page => page.get_title())
+    }
+
+    fn is_init(&self) -> bool {
+        with_page!(self, page => page.is_init())
+    }
+
+    fn init(&mut self, application: Rc<Application>) {
+        with_page!(self, page => page.init(application))
+    }
+
+    fn load_page(&mut self, application: Rc<Application>, view: &NavigationSplitView) {
+        with_page!(self, page => page.load_page(application, view))
+    }
+}
+
+/// The pages of the registry, built when the app starts.
 pub struct Pages {
-    pub main: MainPage,
-    pub surround: SurroundPage,
+    pages: Vec<(&'static PageDescriptor, AnyPage)>,
 }
 impl Pages {
-    pub fn new() -> Self {
-        Self {
-            main: MainPage::new(),
-            surround: SurroundPage::new(),
+    const LOG_TARGET: &str = "pages";
+
+    /// Leaves out pages whose config is unavailable, e.g. without WirePlumber settings.
+    pub fn new(pipewire: &Pipewire) -> Self {
+        let pages = registry::PAGES
+            .iter()
+            .filter(|descriptor| match descriptor.config_file {
+                Some(config_file) => {
+                    let is_available = pipewire.get_config(&config_file).is_some();
+                    if !is_available {
+                        info!(target: Self::LOG_TARGET, "Leaving out page {}, its config is unavailable", descriptor.id);
+                    }
+                    is_available
+                }
+                None => true,
+            })
+            .map(|descriptor| (descriptor, AnyPage::new(descriptor)))
+            .collect();
+
+        Self { pages }
+    }
+
+    pub fn get_descriptors(&self) -> Vec<&'static PageDescriptor> {
+        self.pages
+            .iter()
+            .map(|(descriptor, _page)| *descriptor)
+            .collect()
+    }
+
+    /// Shows the page with this id, `false` when there is none.
+    pub fn load_page(
+        &mut self,
+        page_id: &str,
+        application: Rc<Application>,
+        view: &NavigationSplitView,
+    ) -> bool {
+        let page = self
+            .pages
+            .iter_mut()
+            .find(|(descriptor, _page)| descriptor.id == page_id);
+        match page {
+            Some((_descriptor, page)) => {
+                page.load_page(application, view);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Shows the pending values of the configs in the rows of their pages.
+    pub fn show_pending(&mut self, application: Rc<Application>) {
+        let pipewire = application.pipewire.clone();
+
+        for (descriptor, page) in &mut self.pages {
+            let Some(pw_config) = descriptor
+                .config_file
+                .and_then(|config_file| pipewire.get_config(&config_file))
+            else {
+                continue;
+            };
+            if !page.is_init() {
+                page.init(application.clone());
+            }
+
+            // Cloned, the rows set pending values through their input actions
+            let pending = pw_config.borrow().new.clone();
+            match page {
+                AnyPage::Settings(page) => page.set_row_values(&pending),
+                AnyPage::Surround(page) => page.show_values(&pending),
+                AnyPage::Clock(page) => page.set_row_values(&pending),
+                _ => {}
+            }
+        }
+    }
+
+    /// Shows the current values with the pending ones on top, e.g. after the configs reloaded.
+    pub fn show_values(&mut self, application: Rc<Application>) {
+        let pipewire = application.pipewire.clone();
+
+        for (descriptor, page) in &mut self.pages {
+            let Some(pw_config) = descriptor
+                .config_file
+                .and_then(|config_file| pipewire.get_config(&config_file))
+            else {
+                continue;
+            };
+            if !page.is_init() {
+                continue;
+            }
+
+            let pending = pw_config.borrow().new.clone();
+            let mut values: serde_json::Map<String, serde_json::Value> = pw_config
+                .borrow()
+                .default
+                .iter()
+                .map(|(key, (value, _options))| (key.to_owned(), value.to_owned()))
+                .collect();
+            values.extend(pw_config.borrow().current.clone());
+            values.extend(pending);
+
+            // The rows send what they show through the input action, only what was pending is kept
+            application.is_updating.set(true);
+            match page {
+                AnyPage::Settings(page) => page.set_row_values(&values),
+                AnyPage::Surround(page) => page.show_values(&values),
+                AnyPage::Clock(page) => page.set_row_values(&values),
+                _ => {}
+            }
+            application.is_updating.set(false);
+        }
+    }
+
+    /// Turns the page switches on for the configs whose drop-in is active or that have
+    /// pending values, e.g. after the drop-in was written or removed elsewhere.
+    /// The switches activate their action, so this is called while the pages are not borrowed.
+    pub fn show_enabled(application: &Application) {
+        let mut switches = Vec::new();
+        for (descriptor, page) in &application.pages.borrow().pages {
+            let Some(pw_config) = descriptor
+                .config_file
+                .and_then(|config_file| application.pipewire.get_config(&config_file))
+            else {
+                continue;
+            };
+            let page_switch = match page {
+                AnyPage::Settings(page) => page.get_page_switch(),
+                AnyPage::Surround(page) => page.get_page_switch(),
+                AnyPage::Clock(page) => page.get_page_switch(),
+                _ => None,
+            };
+            // A page with pending values stays editable
+            let pw_config = pw_config.borrow();
+            let is_active = pw_config.is_dropin_active() || !pw_config.new.is_empty();
+            if let Some(page_switch) = page_switch {
+                switches.push((page_switch.clone(), is_active));
+            }
+        }
+
+        // Only shows the state, the drop-ins are not toggled
+        application.is_updating.set(true);
+        for (page_switch, is_active) in switches {
+            ValueRows::set_row_value(&page_switch, &serde_json::Value::Bool(is_active));
+        }
+        application.is_updating.set(false);
+    }
+
+    /// Settings of every page with all words of the query in their key, title or description.
+    /// Advanced settings are only found while they are shown.
+    pub fn search(
+        &self,
+        pipewire: &Pipewire,
+        query: &str,
+        show_advanced: bool,
+    ) -> Vec<SearchResult> {
+        let words: Vec<String> = query
+            .to_lowercase()
+            .split_whitespace()
+            .map(|word| word.to_string())
+            .collect();
+        let mut results = Vec::new();
+        if words.is_empty() {
+            return results;
+        }
+        let schema = PwSchema::bundled();
+
+        for (descriptor, page) in &self.pages {
+            let Some(pw_config) = descriptor
+                .config_file
+                .and_then(|config_file| pipewire.get_config(&config_file))
+            else {
+                continue;
+            };
+            let pw_config = pw_config.borrow();
+            let mut keys: Vec<&String> = pw_config
+                .default
+                .keys()
+                .filter(|key| descriptor.is_shown_key(key))
+                .filter(|key| {
+                    show_advanced || !schema.get(key).is_some_and(|property| property.advanced)
+                })
+                .collect();
+            keys.sort();
+
+            for key in keys {
+                let title = get_row_title(key);
+                let text = format!(
+                    "{} {} {}",
+                    key,
+                    title,
+                    pw_config
+                        .get_description(key)
+                        .map(gettext)
+                        .unwrap_or_default()
+                )
+                .to_lowercase();
+
+                if words.iter().all(|word| text.contains(word.as_str())) {
+                    results.push(SearchResult {
+                        page_id: descriptor.id,
+                        page_title: page.get_title().to_string(),
+                        key: key.to_owned(),
+                        title,
+                    });
+                }
+            }
+        }
+
+        results
+    }
+
+    /// Moves the focus to the row of a setting, its page should be shown.
+    pub fn show_setting(&self, page_id: &str, key: &str) {
+        for (descriptor, page) in &self.pages {
+            if descriptor.id != page_id {
+                continue;
+            }
+            match page {
+                AnyPage::Settings(page) => page.highlight_row(key),
+                AnyPage::Surround(page) => page.highlight_row(key),
+                AnyPage::Clock(page) => page.highlight_row(key),
+                _ => {}
+            }
+        }
+    }
+
+    /// Shows or hides the advanced settings on all pages, e.g. after the preference changed.
+    pub fn show_advanced(&self, is_shown: bool) {
+        for (_descriptor, page) in &self.pages {
+            match page {
+                AnyPage::Settings(page) => page.show_advanced(is_shown),
+                AnyPage::Surround(page) => page.show_advanced(is_shown),
+                AnyPage::Clock(page) => page.show_advanced(is_shown),
+                _ => {}
+            }
+        }
+    }
+
+    fn set_enabled(&mut self, page_id: &str, enabled: bool) {
+        for (descriptor, page) in &mut self.pages {
+            if descriptor.id != page_id {
+                continue;
+            }
+            match page {
+                AnyPage::Settings(page) => page.set_enabled(enabled),
+                AnyPage::Surround(page) => page.set_enabled(enabled),
+                AnyPage::Clock(page) => page.set_enabled(enabled),
+                _ => {}
+            }
         }
     }
 }
@@ -77,8 +425,6 @@ pub trait NavPage {
     const LABEL: &str;
     const LOG_TARGET: &str;
 
-    fn new() -> Self;
-
     fn init(&mut self, _application: Rc<Application>);
 
     fn is_init(&self) -> bool;
@@ -137,6 +483,8 @@ pub trait PrefPage: NavPage {
     const INPUT_PAGE_ACTION_LABEL: &str;
     const PAGE_ENABLE_ACTION_LABEL: &str;
     const PAGE_ENABLE_PAGE_ACTION_LABEL: &str;
+    const APPLY_ACTION_LABEL: &str;
+    const APPLY_PAGE_ACTION_LABEL: &str;
 
     fn build_pref_page(
         title: &str,
@@ -144,15 +492,18 @@ pub trait PrefPage: NavPage {
         NavigationPage,
         PreferencesPage,
         HeaderBar,
+        Banner,
         PageState,
         SimpleActionGroup,
     ) {
         let pref_page = PreferencesPage::new();
         let header = HeaderBar::new();
+        let banner = Banner::new("");
         let toolbar = ToolbarView::new();
         let action_group = SimpleActionGroup::new();
 
         toolbar.add_top_bar(&header);
+        toolbar.add_top_bar(&banner);
         toolbar.set_content(Some(&pref_page));
 
         pref_page.insert_action_group(Self::ACTION_LABEL, Some(&action_group));
@@ -165,11 +516,13 @@ pub trait PrefPage: NavPage {
 
         let state = PageState::new();
 
-        return (nav_page, pref_page, header, state, action_group);
+        return (nav_page, pref_page, header, banner, state, action_group);
     }
 
     fn get_pref_groups(&self) -> &PreferencesPageEntries;
 
+    fn get_page_switch(&self) -> Option<&PreferencesRow>;
+
     fn set_state_enabled(&mut self, enabled: bool);
 
     fn set_enabled(&mut self, enabled: bool) {
@@ -180,37 +533,64 @@ pub trait PrefPage: NavPage {
         self.set_state_enabled(enabled);
     }
 
-    fn build_page_switch(&self) -> PreferencesGroup {
+    /// Hides the rows of settings the schema marks as advanced, and groups left empty.
+    fn show_advanced(&self, is_shown: bool) {
+        let schema = PwSchema::bundled();
+        for (group, rows) in self.get_pref_groups() {
+            for row in rows {
+                let is_advanced = schema
+                    .get(&row.widget_name())
+                    .is_some_and(|property| property.advanced);
+                row.set_visible(is_shown || !is_advanced);
+            }
+            group.set_visible(rows.iter().any(|row| row.is_visible()));
+        }
+    }
+
+    fn build_apply_button(&self) -> Button {
+        Button::builder()
+            .label(gettext("Apply"))
+            .tooltip_text(gettext("Write the pending settings to a drop-in file"))
+            .css_classes(["suggested-action"])
+            .action_name(Self::APPLY_PAGE_ACTION_LABEL)
+            .build()
+    }
+
+    /// The switch that is on while the page's drop-in is active.
+    fn build_page_switch(&self, enabled: bool) -> (PreferencesGroup, PreferencesRow) {
         let key = Self::PAGE_ENABLE_ACTION_LABEL;
         let preferences_group = PreferencesGroup::builder().build();
         let enable_switch = self
             .build_input_row_for_pref_group(
                 key,
-                &serde_json::Value::Bool(false),
+                &serde_json::Value::Bool(enabled),
                 &None,
                 Some(Self::PAGE_ENABLE_PAGE_ACTION_LABEL.to_string()),
             )
             .unwrap();
+        enable_switch.set_tooltip_text(Some(&gettext(
+            "Off moves this page's drop-in aside, on restores its last values",
+        )));
         preferences_group.add(&enable_switch);
 
-        preferences_group
+        (preferences_group, enable_switch)
     }
 
-    fn build_sections_from_default(&self, pw_config: &PwConfig) -> PreferencesPageEntries {
+    fn build_sections_from_default(
+        &self,
+        pw_config: &PwConfig,
+        descriptor: &PageDescriptor,
+    ) -> PreferencesPageEntries {
         let default = &pw_config.default;
-        let mut map: HashMap<Option<&str>, Vec<(&str, &(serde_json::Value, Option<Vec<String>>))>> =
+        let mut map: HashMap<Option<&str>, Vec<(&str, &(serde_json::Value, Option<PwOptions>))>> =
             HashMap::new();
         let mut preferences_groups: PreferencesPageEntries = HashMap::new();
 
         for (key, value) in default {
-            let mut section = None;
-            let mut prop: &str = key;
-
-            if key.contains('.') {
-                let mut split = key.split('.');
-                section = split.next();
-                prop = split.next().unwrap();
+            if !descriptor.is_shown_key(key) {
+                continue;
             }
+            let section = key.split_once('.').map(|(section, _prop)| section);
 
             let mapped_section = match map.get_mut(&section) {
                 None => {
@@ -219,7 +599,7 @@ pub trait PrefPage: NavPage {
                 }
                 Some(value) => value,
             };
-            mapped_section.push((prop, value));
+            mapped_section.push((key, value));
         }
 
         for (section_name, mut values) in map {
@@ -227,7 +607,7 @@ pub trait PrefPage: NavPage {
 
             let section = match section_name {
                 None => String::new(),
-                Some(section) => section.to_case(Case::Title),
+                Some(section) => gettext(&section.to_case(Case::Title)),
             };
             let preferences_group = PreferencesGroup::builder().title(section).build();
             let mut input_rows: Vec<PreferencesRow> = Vec::new();
@@ -236,6 +616,12 @@ pub trait PrefPage: NavPage {
                 if let Some(input_row) =
                     self.build_input_row_for_pref_group(key, value, options, None)
                 {
+                    let description = pw_config.get_description(key).map(gettext);
+                    Self::add_info(&input_row, key, description.as_deref(), value);
+                    if let Some(reason) = pw_config.get_unsupported_reason(key) {
+                        input_row.set_sensitive(false);
+                        input_row.set_tooltip_text(Some(&reason));
+                    }
                     preferences_group.add(&input_row);
                     input_rows.push(input_row);
                 }
@@ -249,26 +635,96 @@ pub trait PrefPage: NavPage {
         preferences_groups
     }
 
+    /// Describes the setting in the row's subtitle and an info popover with its upstream default.
+    fn add_info(
+        row: &PreferencesRow,
+        key: &str,
+        description: Option<&str>,
+        default: &serde_json::Value,
+    ) {
+        let default = match default.as_str() {
+            Some(default) => default.to_string(),
+            None => default.to_string(),
+        };
+
+        let content_box = gtk::Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(6)
+            .margin_top(6)
+            .margin_bottom(6)
+            .margin_start(6)
+            .margin_end(6)
+            .build();
+        content_box.append(
+            &Label::builder()
+                .label(key)
+                .selectable(true)
+                .xalign(0.0)
+                .css_classes(["heading"])
+                .build(),
+        );
+        if let Some(description) = description {
+            content_box.append(
+                &Label::builder()
+                    .label(description)
+                    .wrap(true)
+                    .max_width_chars(40)
+                    .xalign(0.0)
+                    .build(),
+            );
+        }
+        content_box.append(
+            &Label::builder()
+                .label(gettext_f("Default: {}", &[&default]))
+                .xalign(0.0)
+                .css_classes(["dim-label"])
+                .build(),
+        );
+
+        let info_button = MenuButton::builder()
+            .icon_name("dialog-information-symbolic")
+            .tooltip_text(gettext("About this setting"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .popover(&Popover::builder().child(&content_box).build())
+            .build();
+
+        if let Some(action_row) = row.downcast_ref::<ActionRow>() {
+            if let Some(description) = description {
+                action_row.set_subtitle(&glib::markup_escape_text(description));
+            }
+            action_row.add_suffix(&info_button);
+        } else if let Some(entry_row) = row.downcast_ref::<EntryRow>() {
+            entry_row.add_suffix(&info_button);
+        }
+    }
+
     fn build_input_row_for_pref_group(
         &self,
         key: &str,
         value: &serde_json::Value,
-        options: &Option<Vec<String>>,
+        options: &Option<PwOptions>,
         action_label: Option<String>,
     ) -> Option<PreferencesRow> {
-        let title = key
-            .to_lowercase()
-            .from_case(Case::Kebab)
-            .to_case(Case::Sentence);
+        let title = get_row_title(key);
         let action_label = match action_label {
             Some(label) => label,
             None => Self::INPUT_PAGE_ACTION_LABEL.to_string(),
         };
 
+        let property = PwSchema::bundled().get(key);
+        // The bundled schema wins over the options in upstream comments
+        let options = options.as_ref();
+        let enum_values = match property.map(|property| &property.kind) {
+            Some(PwPropertyType::Enum(values)) => Some(values),
+            _ => options.and_then(|options| options.values.as_ref()),
+        };
+
         match value {
             serde_json::Value::Bool(value) => {
                 let key = key.to_owned();
                 let build = SwitchRow::builder()
+                    .name(&key)
                     .title(title)
                     .active(value.to_owned())
                     .build();
@@ -288,24 +744,56 @@ pub trait PrefPage: NavPage {
 
             // TODO: maybe slider? (ActionRow->Scale)
             serde_json::Value::Number(value) => {
+                let is_int = match property {
+                    Some(property) => property.kind == PwPropertyType::Int,
+                    None => value.is_i64() || value.is_u64(),
+                };
+                let step = property.map_or(1.0, |property| property.step);
+                let digits =
+                    property.map_or(if is_int { 0 } else { 1 }, |property| property.get_digits());
+                let min = property
+                    .and_then(|property| property.min)
+                    .or(options.and_then(|options| options.min));
+                let max = property
+                    .and_then(|property| property.max)
+                    .or(options.and_then(|options| options.max));
+                let unit = property
+                    .and_then(|property| property.unit.as_ref())
+                    .or(options.and_then(|options| options.unit.as_ref()));
+
                 let key = key.to_owned();
                 let build = SpinRow::builder()
+                    .name(&key)
                     .title(title)
                     .adjustment(
                         &Adjustment::builder()
-                            .lower(f64::MIN)
-                            .upper(f64::MAX)
-                            .page_increment(1.0)
-                            .step_increment(1.0)
+                            .lower(min.unwrap_or(f64::MIN))
+                            .upper(max.unwrap_or(f64::MAX))
+                            .page_increment(step * 10.0)
+                            .step_increment(step)
                             .build(),
                     )
-                    .digits(1)
+                    .digits(digits)
                     .editable(true)
                     .value(value.as_f64().unwrap())
                     .build();
 
+                if let Some(unit) = unit {
+                    build.add_suffix(
+                        &Label::builder()
+                            .label(unit)
+                            .css_classes(["dim-label"])
+                            .build(),
+                    );
+                }
+
                 build.connect_value_notify(move |spin_row| {
-                    let json_variant = json!({ &key: &spin_row.value() }).to_string().to_variant();
+                    let value = match is_int {
+                        true => json!(spin_row.value().round() as i64),
+                        false => json!(spin_row.value()),
+                    };
+                    Self::show_validity(spin_row.upcast_ref(), &key, &value);
+                    let json_variant = json!({ &key: value }).to_string().to_variant();
 
                     spin_row
                         .activate_action(&action_label, Some(&json_variant))
@@ -315,15 +803,24 @@ pub trait PrefPage: NavPage {
                 Some(build.upcast())
             }
 
-            serde_json::Value::String(value) => match options {
+            serde_json::Value::String(value) => match enum_values {
                 Some(options_value) => {
                     let list = StringList::new(&[]);
                     for option in options_value {
                         list.append(&option);
                     }
+                    let selected = options_value
+                        .iter()
+                        .position(|option| option == value)
+                        .unwrap_or(0);
 
                     let key = key.to_owned();
-                    let build = ComboRow::builder().title(title).model(&list).build();
+                    let build = ComboRow::builder()
+                        .name(&key)
+                        .title(title)
+                        .model(&list)
+                        .selected(selected as u32)
+                        .build();
 
                     build.connect_selected_item_notify(move |combo_row| {
                         let selected_string =
@@ -340,10 +837,15 @@ pub trait PrefPage: NavPage {
                 }
                 None => {
                     let key = key.to_owned();
-                    let build = EntryRow::builder().title(title).text(value).build();
+                    let build = EntryRow::builder()
+                        .name(&key)
+                        .title(title)
+                        .text(value)
+                        .build();
 
                     build.connect_text_notify(move |entry_row| {
                         let input_string = entry_row.text().to_string();
+                        Self::show_validity(entry_row.upcast_ref(), &key, &json!(input_string));
                         let json_variant = json!({ &key: &input_string }).to_string().to_variant();
 
                         entry_row
@@ -355,21 +857,107 @@ pub trait PrefPage: NavPage {
                 }
             },
 
-            // serde_json::Value::Array(_value) => None,
-            // serde_json::Value::Object(_value) => None,
+            serde_json::Value::Array(items) => {
+                let item_property = property
+                    .and_then(|property| property.get_item_property())
+                    .unwrap_or_else(|| {
+                        let kind = match items.first() {
+                            Some(serde_json::Value::Number(number)) if number.is_f64() => {
+                                PwPropertyType::Float
+                            }
+                            Some(serde_json::Value::Number(_number)) => PwPropertyType::Int,
+                            Some(serde_json::Value::Bool(_value)) => PwPropertyType::Bool,
+                            _ => PwPropertyType::String,
+                        };
+                        PwProperty::from_kind(kind)
+                    });
+
+                Some(ArrayRow::build(
+                    key,
+                    &title,
+                    items,
+                    item_property,
+                    action_label,
+                ))
+            }
+
+            serde_json::Value::Object(values) => Some(ObjectRow::build(
+                key,
+                &title,
+                values,
+                action_label,
+                |child_key, child_value, input_action_label| {
+                    self.build_input_row_for_pref_group(
+                        child_key,
+                        child_value,
+                        &None,
+                        Some(input_action_label),
+                    )
+                },
+            )),
+
             // serde_json::Value::Null => None,
             _ => None,
         }
     }
 
+    /// Shows values in the rows of the same keys, e.g. pending values loaded from a file.
+    fn set_row_values(&self, values: &serde_json::Map<String, serde_json::Value>) {
+        for rows in self.get_pref_groups().values() {
+            for row in rows {
+                let key = row.widget_name();
+                let Some(value) = values.get(key.as_str()) else {
+                    continue;
+                };
+                Self::show_validity(row, &key, value);
+                ValueRows::set_row_value(row, value);
+            }
+        }
+    }
+
+    /// Moves the focus to the row of a key and highlights it for a moment.
+    fn highlight_row(&self, key: &str) {
+        let row = self
+            .get_pref_groups()
+            .values()
+            .flatten()
+            .find(|row| row.widget_name() == key);
+        let Some(row) = row else {
+            return;
+        };
+
+        row.grab_focus();
+        row.add_css_class("accent");
+        let row = row.clone();
+        glib::timeout_add_seconds_local_once(2, move || row.remove_css_class("accent"));
+    }
+
+    /// Highlights a row whose value does not fit the schema, applying is blocked until fixed.
+    fn show_validity(row: &PreferencesRow, key: &str, value: &serde_json::Value) {
+        match PwSchema::bundled().validate(key, value) {
+            Ok(()) => {
+                row.remove_css_class("error");
+                row.set_tooltip_text(None);
+            }
+            Err(reason) => {
+                row.add_css_class("error");
+                row.set_tooltip_text(Some(&gettext_f("The value {}", &[&reason])));
+            }
+        }
+    }
+
     fn build_input_action(
         &self,
+        application: Rc<Application>,
         pw_config: &Rc<RefCell<PwConfig>>,
     ) -> ActionEntry<SimpleActionGroup> {
         let pw_config = pw_config.clone();
         let action = ActionEntry::builder(Self::INPUT_ACTION_LABEL)
             .parameter_type(Some(VariantTy::STRING))
             .activate(move |_group, _action, parameter| {
+                if application.is_updating.get() {
+                    return;
+                }
                 let mut pw_config = pw_config.borrow_mut();
                 let string_value = parameter.unwrap().try_get::<String>().unwrap();
                 let json_value: serde_json::Value = serde_json::from_str(&string_value).unwrap();
@@ -380,6 +968,10 @@ pub trait PrefPage: NavPage {
                 let key = json_object.keys().next().unwrap();
                 let value = json_object.values().next().unwrap();
 
+                // Rows also send when they show a value that is already pending
+                if pw_config.new.get(key) != Some(value) {
+                    application.service.emit_changed(key, value);
+                }
                 pw_config.new.insert(key.to_owned(), value.clone());
 
                 debug!(target: Self::LOG_TARGET, "Input action new config:\n{:#?}", pw_config.new);
@@ -389,18 +981,43 @@ pub trait PrefPage: NavPage {
         action
     }
 
+    fn build_apply_action(
+        &self,
+        application: Rc<Application>,
+        pw_config: &Rc<RefCell<PwConfig>>,
+    ) -> ActionEntry<SimpleActionGroup> {
+        let pw_config = pw_config.clone();
+
+        let action = ActionEntry::builder(Self::APPLY_ACTION_LABEL)
+            .activate(move |_group, _action, _parameter| {
+                if pw_config.borrow().new.is_empty() {
+                    debug!(target: Self::LOG_TARGET, "Apply action: nothing pending");
+                    return;
+                }
+
+                glib::spawn_future_local(ApplyDialog::apply(
+                    application.clone(),
+                    pw_config.clone(),
+                ));
+            })
+            .build();
+
+        action
+    }
+
+    /// Turning the switch off moves the page's drop-in aside, turning it on restores it.
+    /// The affected services are restarted like after an apply.
     fn build_page_switch_action(
         &self,
         application: Rc<Application>,
-        page: Page,
+        pw_config: &Rc<RefCell<PwConfig>>,
+        page_id: &'static str,
     ) -> ActionEntry<SimpleActionGroup> {
-        let application = application.clone();
-        let pages = application.pages.clone();
+        let pw_config = pw_config.clone();
 
         let action = ActionEntry::builder(Self::PAGE_ENABLE_ACTION_LABEL)
             .parameter_type(Some(VariantTy::STRING))
             .activate(move |_group, _action, parameter| {
-                let mut pages_mut = pages.borrow_mut();
                 let string_value = parameter.unwrap().try_get::<String>().unwrap();
                 let json_value: serde_json::Value = serde_json::from_str(&string_value).unwrap();
 
@@ -417,15 +1034,70 @@ pub trait PrefPage: NavPage {
 
                 debug!(target: Self::LOG_TARGET, "Page enable action:\n{:?}", is_enabled);
 
-                pages_mut.surround.set_enabled(is_enabled);
+                // Also sent when the switch only shows the state of the drop-in
+                if !application.is_updating.get()
+                    && is_enabled != pw_config.borrow().is_dropin_active()
+                {
+                    Self::toggle_dropin(application.clone(), &pw_config, is_enabled);
+                }
 
-                match page {
-                    Page::Main => {}
-                    Page::Surround => pages_mut.surround.set_enabled(is_enabled),
-                };
+                // Without a drop-in to restore the rows are editable until the first apply
+                application
+                    .pages
+                    .borrow_mut()
+                    .set_enabled(page_id, is_enabled);
             })
             .build();
 
         action
     }
+
+    fn toggle_dropin(
+        application: Rc<Application>,
+        pw_config: &Rc<RefCell<PwConfig>>,
+        enable: bool,
+    ) {
+        let history = &application.pipewire.history;
+        if let Err(error) = history.snapshot_initial() {
+            error!(target: Self::LOG_TARGET, "Initial snapshot failed:\n{:?}", error);
+        }
+
+        let toggled = {
+            let _pause = application.watcher.pause();
+            match enable {
+                true => pw_config.borrow_mut().enable(),
+                false => pw_config.borrow_mut().disable(),
+            }
+        };
+        match toggled {
+            Ok(Some(applied)) => {
+                let summary = match enable {
+                    true => applied.get_summary(),
+                    false => format!("Disabled {}", pw_config.borrow().get_name()),
+                };
+                if let Err(error) = history.snapshot(&summary) {
+                    error!(target: Self::LOG_TARGET, "Snapshot failed:\n{:?}", error);
+                }
+
+                let applied = vec![(pw_config.clone(), applied)];
+                glib::spawn_future_local(ApplyDialog::restart_affected(application, applied));
+            }
+            // Nothing was applied before, the page is on once something is applied
+            Ok(None) => {}
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Toggling drop-in failed:\n{:?}", error);
+
+                glib::spawn_future_local(async move {
+                    let window = application.window.window.clone();
+                    ApplyDialog::show_error(
+                        &window,
+                        &gettext("Could not change the drop-in"),
+                        &error,
+                    )
+                    .await;
+                    Pages::show_enabled(&application);
+                });
+            }
+        }
+    }
 }
@@ -0,0 +1,252 @@
+use super::{NavPage, PageState, PrefPage, PreferencesPageEntries, registry::PageDescriptor};
+use crate::{
+    application::{
+        Application,
+        pipewire::{
+            config::PwConfig,
+            metadata::{PwMetadata, PwTrial},
+        },
+        window::countdown::Countdown,
+    },
+    i18n::{gettext, gettext_f},
+};
+use anyhow::{Result, anyhow};
+use libadwaita::{
+    Banner, HeaderBar, NavigationPage, PreferencesPage, PreferencesRow,
+    gio::{self, ActionEntry, SimpleActionGroup, prelude::ActionMapExtManual},
+    glib,
+    gtk::Button,
+    prelude::PreferencesPageExt,
+};
+use log::error;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+pub struct ClockPage {
+    pub descriptor: &'static PageDescriptor,
+    pub nav_page: NavigationPage,
+    pref_page: PreferencesPage,
+    pref_groups: PreferencesPageEntries,
+    page_switch: Option<PreferencesRow>,
+    header: HeaderBar,
+    banner: Banner,
+    state: PageState,
+    title: String,
+    actions: SimpleActionGroup,
+    trial: Rc<RefCell<Option<(PwTrial, Countdown)>>>,
+}
+impl NavPage for ClockPage {
+    const LABEL: &str = "clock-page";
+    const LOG_TARGET: &str = Self::LABEL;
+
+    fn is_init(&self) -> bool {
+        self.state.get_init()
+    }
+
+    fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    fn get_state(&self) -> &PageState {
+        &self.state
+    }
+
+    fn init(&mut self, application: Rc<Application>) {
+        self.on_init(application);
+        self.state.set_init(true);
+    }
+
+    fn get_navpage(&self) -> &NavigationPage {
+        &self.nav_page
+    }
+}
+impl PrefPage for ClockPage {
+    const ACTION_LABEL: &str = "clock";
+    const INPUT_ACTION_LABEL: &str = "input";
+    const INPUT_PAGE_ACTION_LABEL: &str = "clock.input";
+    const PAGE_ENABLE_ACTION_LABEL: &str = "page-enable";
+    const PAGE_ENABLE_PAGE_ACTION_LABEL: &str = "clock.page-enable";
+    const APPLY_ACTION_LABEL: &str = "apply";
+    const APPLY_PAGE_ACTION_LABEL: &str = "clock.apply";
+
+    fn get_pref_groups(&self) -> &PreferencesPageEntries {
+        &self.pref_groups
+    }
+
+    fn get_page_switch(&self) -> Option<&PreferencesRow> {
+        self.page_switch.as_ref()
+    }
+
+    fn set_state_enabled(&mut self, enabled: bool) {
+        self.state.set_page_enabled(enabled);
+    }
+}
+impl ClockPage {
+    pub fn new(descriptor: &'static PageDescriptor) -> Self {
+        let title = descriptor.get_title();
+        let (nav_page, pref_page, header, banner, state, actions) = Self::build_pref_page(&title);
+
+        Self {
+            descriptor,
+            nav_page,
+            pref_page,
+            pref_groups: HashMap::with_capacity(0),
+            page_switch: None,
+            header,
+            banner,
+            state,
+            title,
+            actions,
+            trial: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    const TRY_NOW_ACTION_LABEL: &str = "try-now";
+    const TRY_NOW_PAGE_ACTION_LABEL: &str = "clock.try-now";
+    const TRY_NOW_SECONDS: u32 = 20;
+
+    fn on_init(&mut self, application: Rc<Application>) {
+        let pipewire = application.pipewire.clone();
+        let Some(pw_config) = self
+            .descriptor
+            .config_file
+            .and_then(|config_file| pipewire.get_config(&config_file))
+        else {
+            return;
+        };
+
+        let input_action = self.build_input_action(application.clone(), pw_config);
+        let apply_action = self.build_apply_action(application.clone(), pw_config);
+        let page_enabled_action =
+            self.build_page_switch_action(application.clone(), pw_config, self.descriptor.id);
+        let try_now_action = self.build_try_now_action(pw_config);
+        self.actions.add_action_entries([
+            input_action,
+            apply_action,
+            page_enabled_action,
+            try_now_action,
+        ]);
+
+        let try_now_button = Button::builder()
+            .label(gettext("Try now"))
+            .tooltip_text(gettext(
+                "Apply pending settings live until restart, reverting unless kept",
+            ))
+            .action_name(Self::TRY_NOW_PAGE_ACTION_LABEL)
+            .build();
+        self.header.pack_start(&try_now_button);
+        self.header.pack_end(&self.build_apply_button());
+        self.connect_banner();
+
+        let is_active = pw_config.borrow().is_dropin_active();
+        let (enable_pref_group, page_switch) = self.build_page_switch(is_active);
+        self.pref_page.add(&enable_pref_group);
+        self.page_switch = Some(page_switch);
+
+        self.pref_groups = self.build_sections_from_default(&pw_config.borrow(), self.descriptor);
+        for group in self.pref_groups.keys() {
+            self.pref_page.add(group);
+        }
+
+        self.show_advanced(application.preferences.get_show_advanced());
+        self.set_enabled(is_active);
+    }
+
+    fn build_try_now_action(
+        &self,
+        pw_config: &Rc<RefCell<PwConfig>>,
+    ) -> ActionEntry<SimpleActionGroup> {
+        let pw_config = pw_config.clone();
+        let banner = self.banner.clone();
+        let trial = self.trial.clone();
+
+        ActionEntry::builder(Self::TRY_NOW_ACTION_LABEL)
+            .activate(move |_group, action, _parameter| {
+                if trial.borrow().is_some() {
+                    return;
+                }
+
+                let pending = pw_config.borrow().new.clone();
+                let action = action.clone();
+                let banner = banner.clone();
+                let trial = trial.clone();
+                // Runs pw-metadata off the main loop, the action is off until it is done
+                action.set_enabled(false);
+                glib::spawn_future_local(async move {
+                    let pw_trial = gio::spawn_blocking(move || PwMetadata::try_settings(&pending))
+                        .await
+                        .unwrap_or_else(|_panic| Err(anyhow!("Trying settings live panicked")));
+                    action.set_enabled(true);
+                    Self::start_trial(pw_trial, &banner, &trial);
+                });
+            })
+            .build()
+    }
+
+    /// Shows a trial that was pushed and reverts it when the countdown ends.
+    fn start_trial(
+        pw_trial: Result<PwTrial>,
+        banner: &Banner,
+        trial: &Rc<RefCell<Option<(PwTrial, Countdown)>>>,
+    ) {
+        let pw_trial = match pw_trial {
+            Ok(pw_trial) => pw_trial,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Trying settings live failed:\n{:?}", error);
+                banner.set_title(&gettext("Could not try the settings live"));
+                banner.set_button_label(Some(&gettext("Dismiss")));
+                banner.set_revealed(true);
+                return;
+            }
+        };
+
+        if pw_trial.is_empty() {
+            banner.set_title(&gettext("None of the pending settings can be tried live"));
+            banner.set_button_label(Some(&gettext("Dismiss")));
+            banner.set_revealed(true);
+            return;
+        }
+
+        let banner_tick = banner.clone();
+        let banner_done = banner.clone();
+        let trial_done = trial.clone();
+        let countdown = Countdown::start(
+            Self::TRY_NOW_SECONDS,
+            move |remaining| {
+                banner_tick.set_title(&gettext_f(
+                    "Trying settings live, reverting in {} s",
+                    &[&remaining.to_string()],
+                ));
+            },
+            move || {
+                banner_done.set_revealed(false);
+                let Some((pw_trial, _countdown)) = trial_done.borrow_mut().take() else {
+                    return;
+                };
+                glib::spawn_future_local(async move {
+                    let reverted = gio::spawn_blocking(move || pw_trial.revert())
+                        .await
+                        .unwrap_or_else(|_panic| Err(anyhow!("Reverting live settings panicked")));
+                    if let Err(error) = reverted {
+                        error!(target: Self::LOG_TARGET, "Reverting live settings failed:\n{:?}", error);
+                    }
+                });
+            },
+        );
+
+        banner.set_button_label(Some(&gettext("Keep")));
+        banner.set_revealed(true);
+        trial.replace(Some((pw_trial, countdown)));
+    }
+
+    fn connect_banner(&self) {
+        let trial = self.trial.clone();
+
+        // Keeps a running trial, otherwise dismisses a message
+        self.banner.connect_button_clicked(move |banner| {
+            if let Some((_pw_trial, countdown)) = trial.borrow_mut().take() {
+                countdown.stop();
+            }
+            banner.set_revealed(false);
+        });
+    }
+}
@@ -0,0 +1,200 @@
+use super::{NavPage, PageState, registry::PageDescriptor};
+use crate::{
+    application::{
+        Application,
+        pipewire::{diagnostics::PwDiagnostics, services::PwServices},
+    },
+    i18n::gettext,
+};
+use libadwaita::{
+    ActionRow, HeaderBar, NavigationPage, NavigationSplitView, PreferencesGroup, PreferencesPage,
+    ToolbarView, gio, glib,
+    gtk::{
+        Button,
+        prelude::{ButtonExt, WidgetExt},
+    },
+    prelude::{PreferencesGroupExt, PreferencesPageExt},
+};
+use log::{error, warn};
+use std::{cell::RefCell, rc::Rc};
+
+pub struct DiagnosticsPage {
+    pub nav_page: NavigationPage,
+    header: HeaderBar,
+    report: Rc<Report>,
+    state: PageState,
+    title: String,
+}
+impl NavPage for DiagnosticsPage {
+    const LABEL: &str = "diagnostics-page";
+    const LOG_TARGET: &str = Self::LABEL;
+
+    fn init(&mut self, application: Rc<Application>) {
+        self.on_init(application);
+        self.state.set_init(true);
+    }
+
+    fn is_init(&self) -> bool {
+        self.state.get_init()
+    }
+
+    fn get_state(&self) -> &PageState {
+        &self.state
+    }
+
+    fn get_navpage(&self) -> &NavigationPage {
+        &self.nav_page
+    }
+
+    fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    /// Collects again every time, services may have restarted since.
+    fn load_page(&mut self, application: Rc<Application>, view: &NavigationSplitView) {
+        if !self.is_init() {
+            self.init(application.clone());
+        }
+        self.report.refresh(&application);
+
+        let nav_page = self.get_navpage();
+        if nav_page.parent().is_some() {
+            return;
+        };
+        view.set_content(Some(nav_page));
+    }
+}
+impl DiagnosticsPage {
+    pub fn new(descriptor: &'static PageDescriptor) -> Self {
+        let title = descriptor.get_title();
+        let pref_page = PreferencesPage::new();
+        let header = HeaderBar::new();
+        let toolbar = ToolbarView::new();
+        toolbar.add_top_bar(&header);
+        toolbar.set_content(Some(&pref_page));
+
+        let nav_page = NavigationPage::builder()
+            .title(&title)
+            .tag(&title)
+            .child(&toolbar)
+            .build();
+
+        let report = Rc::new(Report {
+            pref_page,
+            groups: RefCell::new(Vec::new()),
+            text: RefCell::new(String::new()),
+        });
+
+        Self {
+            nav_page,
+            header,
+            report,
+            state: PageState::new(),
+            title,
+        }
+    }
+
+    fn on_init(&mut self, application: Rc<Application>) {
+        let refresh_button = Button::builder()
+            .icon_name("view-refresh-symbolic")
+            .tooltip_text(gettext("Refresh"))
+            .build();
+        self.header.pack_end(&refresh_button);
+
+        let report = self.report.clone();
+        refresh_button.connect_clicked(move |_| report.refresh(&application));
+
+        let copy_button = Button::builder()
+            .label(gettext("Copy Report"))
+            .tooltip_text(gettext("Copy the report to paste it in a bug report"))
+            .build();
+        self.header.pack_start(&copy_button);
+
+        let report = self.report.clone();
+        copy_button
+            .connect_clicked(move |button| button.clipboard().set_text(&report.text.borrow()));
+    }
+}
+
+/// The collected diagnostics, as rows and as text to copy.
+struct Report {
+    pref_page: PreferencesPage,
+    groups: RefCell<Vec<PreferencesGroup>>,
+    text: RefCell<String>,
+}
+impl Report {
+    const LOG_TARGET: &str = DiagnosticsPage::LOG_TARGET;
+
+    fn refresh(self: &Rc<Self>, application: &Rc<Application>) {
+        let configs = application.pipewire.get_configs();
+        let configs: Vec<_> = configs.iter().map(|pw_config| pw_config.borrow()).collect();
+        let configs: Vec<_> = configs.iter().map(|pw_config| &**pw_config).collect();
+        let paths = PwDiagnostics::get_config_paths(&configs);
+
+        let report = self.clone();
+        glib::spawn_future_local(async move {
+            // Runs the programs off the main loop
+            let mut diagnostics =
+                match gio::spawn_blocking(move || PwDiagnostics::collect(paths)).await {
+                    Ok(diagnostics) => diagnostics,
+                    Err(_panic) => {
+                        error!(target: Self::LOG_TARGET, "Collecting diagnostics panicked");
+                        return;
+                    }
+                };
+            diagnostics.add_units(Self::get_unit_states().await);
+            report.show(&diagnostics);
+        });
+    }
+
+    async fn get_unit_states() -> Vec<(String, String)> {
+        let services = match PwServices::connect().await {
+            Ok(services) => services,
+            Err(error) => {
+                warn!(target: Self::LOG_TARGET, "Getting unit states failed:\n{:?}", error);
+                return Vec::new();
+            }
+        };
+
+        let mut states = Vec::new();
+        for unit in PwServices::UNITS {
+            let state = match services.get_unit_state(unit).await {
+                Ok(state) => state,
+                Err(error) => {
+                    warn!(target: Self::LOG_TARGET, "Getting state of {} failed:\n{:?}", unit, error);
+                    gettext("unknown")
+                }
+            };
+            states.push((unit.to_string(), state));
+        }
+
+        states
+    }
+
+    fn show(&self, diagnostics: &PwDiagnostics) {
+        for group in self.groups.take() {
+            self.pref_page.remove(&group);
+        }
+
+        let mut groups = self.groups.borrow_mut();
+        for section in &diagnostics.sections {
+            let group = PreferencesGroup::builder().title(&section.title).build();
+            if section.entries.is_empty() {
+                group.add(&ActionRow::builder().title(gettext("None")).build());
+            }
+            for (name, value) in &section.entries {
+                let row = ActionRow::builder()
+                    .use_markup(false)
+                    .title(name)
+                    .subtitle(value)
+                    .subtitle_selectable(true)
+                    .build();
+                group.add(&row);
+            }
+            self.pref_page.add(&group);
+            groups.push(group);
+        }
+
+        self.text.replace(diagnostics.to_report());
+    }
+}
@@ -0,0 +1,368 @@
+use super::{NavPage, PageState, registry::PageDescriptor};
+use crate::{
+    application::{
+        Application,
+        pipewire::{
+            history::{PwDiffLine, PwSnapshot},
+            services::PwServices,
+        },
+        window::apply_dialog::ApplyDialog,
+    },
+    i18n::{gettext, gettext_f},
+};
+use libadwaita::{
+    ActionRow, HeaderBar, MessageDialog, NavigationPage, NavigationSplitView, PreferencesGroup,
+    PreferencesPage, ResponseAppearance, ToolbarView,
+    glib::{self, DateTime},
+    gtk::{
+        self, Button, CheckButton, PolicyType, ScrolledWindow, TextBuffer, TextView, WrapMode,
+        prelude::{
+            ButtonExt, CheckButtonExt, GtkWindowExt, TextBufferExt, TextBufferExtManual, WidgetExt,
+        },
+    },
+    prelude::{
+        ActionRowExt, MessageDialogExt, MessageDialogExtManual, PreferencesGroupExt,
+        PreferencesPageExt,
+    },
+};
+use log::error;
+use std::{cell::RefCell, rc::Rc};
+
+pub struct HistoryPage {
+    pub nav_page: NavigationPage,
+    header: HeaderBar,
+    snapshot_list: Rc<SnapshotList>,
+    state: PageState,
+    title: String,
+}
+impl NavPage for HistoryPage {
+    const LABEL: &str = "history-page";
+    const LOG_TARGET: &str = Self::LABEL;
+
+    fn init(&mut self, application: Rc<Application>) {
+        self.on_init(application);
+        self.state.set_init(true);
+    }
+
+    fn is_init(&self) -> bool {
+        self.state.get_init()
+    }
+
+    fn get_state(&self) -> &PageState {
+        &self.state
+    }
+
+    fn get_navpage(&self) -> &NavigationPage {
+        &self.nav_page
+    }
+
+    fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    /// Refreshes the list every time, every apply adds a snapshot.
+    fn load_page(&mut self, application: Rc<Application>, view: &NavigationSplitView) {
+        if !self.is_init() {
+            self.init(application.clone());
+        }
+        self.snapshot_list.refresh(&application);
+
+        let nav_page = self.get_navpage();
+        if nav_page.parent().is_some() {
+            return;
+        };
+        view.set_content(Some(nav_page));
+    }
+}
+impl HistoryPage {
+    pub fn new(descriptor: &'static PageDescriptor) -> Self {
+        let title = descriptor.get_title();
+        let pref_page = PreferencesPage::new();
+        let header = HeaderBar::new();
+        let toolbar = ToolbarView::new();
+        toolbar.add_top_bar(&header);
+        toolbar.set_content(Some(&pref_page));
+
+        let compare_button = Button::builder()
+            .label(gettext("Compare"))
+            .tooltip_text(gettext(
+                "Show the differences between two selected restore points",
+            ))
+            .sensitive(false)
+            .build();
+        let group = PreferencesGroup::builder()
+            .title(gettext("Restore points"))
+            .description(gettext(
+                "A snapshot of your PipeWire and WirePlumber config dirs is taken on every apply",
+            ))
+            .header_suffix(&compare_button)
+            .build();
+        pref_page.add(&group);
+
+        let nav_page = NavigationPage::builder()
+            .title(&title)
+            .tag(&title)
+            .child(&toolbar)
+            .build();
+
+        let snapshot_list = Rc::new(SnapshotList {
+            group,
+            compare_button,
+            rows: RefCell::new(Vec::new()),
+            selected: RefCell::new(Vec::new()),
+        });
+
+        Self {
+            nav_page,
+            header,
+            snapshot_list,
+            state: PageState::new(),
+            title,
+        }
+    }
+
+    fn on_init(&mut self, application: Rc<Application>) {
+        let refresh_button = Button::builder()
+            .icon_name("view-refresh-symbolic")
+            .tooltip_text(gettext("Refresh"))
+            .build();
+        self.header.pack_end(&refresh_button);
+
+        let snapshot_list = self.snapshot_list.clone();
+        let refresh_application = application.clone();
+        refresh_button.connect_clicked(move |_| snapshot_list.refresh(&refresh_application));
+
+        let snapshot_list = self.snapshot_list.clone();
+        self.snapshot_list
+            .compare_button
+            .connect_clicked(move |_| snapshot_list.compare(&application));
+    }
+}
+
+struct SnapshotList {
+    group: PreferencesGroup,
+    compare_button: Button,
+    rows: RefCell<Vec<ActionRow>>,
+    /// Ids of the snapshots checked for comparing, compare needs exactly two
+    selected: RefCell<Vec<String>>,
+}
+impl SnapshotList {
+    const LOG_TARGET: &str = HistoryPage::LOG_TARGET;
+    const RESPONSE_CANCEL: &str = "cancel";
+    const RESPONSE_RESTORE: &str = "restore";
+    const RESPONSE_CLOSE: &str = "close";
+
+    fn refresh(self: &Rc<Self>, application: &Rc<Application>) {
+        for row in self.rows.take() {
+            self.group.remove(&row);
+        }
+        self.selected.borrow_mut().clear();
+        self.compare_button.set_sensitive(false);
+
+        let snapshots = match application.pipewire.history.list() {
+            Ok(snapshots) => snapshots,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Listing history failed:\n{:?}", error);
+                Vec::new()
+            }
+        };
+
+        let mut rows = self.rows.borrow_mut();
+        for snapshot in &snapshots {
+            let row = self.build_row(application, snapshot);
+            self.group.add(&row);
+            rows.push(row);
+        }
+    }
+
+    fn build_row(
+        self: &Rc<Self>,
+        application: &Rc<Application>,
+        snapshot: &PwSnapshot,
+    ) -> ActionRow {
+        let row = ActionRow::builder()
+            .title(Self::format_timestamp(snapshot.timestamp))
+            .subtitle(&snapshot.summary)
+            .subtitle_lines(2)
+            .build();
+
+        let check_button = CheckButton::builder()
+            .valign(gtk::Align::Center)
+            .tooltip_text(gettext("Select to compare"))
+            .build();
+        let snapshot_list = self.clone();
+        let id = snapshot.id.clone();
+        check_button.connect_toggled(move |check_button| {
+            snapshot_list.toggle_selected(&id, check_button.is_active());
+        });
+        row.add_prefix(&check_button);
+
+        let restore_button = Button::builder()
+            .icon_name("edit-undo-symbolic")
+            .tooltip_text(gettext("Restore this configuration"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        let snapshot_list = self.clone();
+        let application = application.clone();
+        let id = snapshot.id.clone();
+        restore_button.connect_clicked(move |_| {
+            let snapshot_list = snapshot_list.clone();
+            let application = application.clone();
+            let id = id.clone();
+            glib::spawn_future_local(async move { snapshot_list.restore(&application, &id).await });
+        });
+        row.add_suffix(&restore_button);
+
+        row
+    }
+
+    fn toggle_selected(&self, id: &str, active: bool) {
+        let mut selected = self.selected.borrow_mut();
+        selected.retain(|selected_id| selected_id != id);
+        if active {
+            selected.push(id.to_string());
+        }
+
+        self.compare_button.set_sensitive(selected.len() == 2);
+    }
+
+    fn compare(&self, application: &Rc<Application>) {
+        let history = &application.pipewire.history;
+        let mut selected = self.selected.borrow().clone();
+        // Ids sort by time, compare old to new
+        selected.sort();
+
+        let diff = history.list().and_then(|snapshots| {
+            let old = Self::find(&snapshots, &selected[0])?;
+            let new = Self::find(&snapshots, &selected[1])?;
+            history.diff(old, new)
+        });
+        let diff = match diff {
+            Ok(diff) => diff,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Comparing failed:\n{:?}", error);
+                return;
+            }
+        };
+
+        let buffer = TextBuffer::new(None);
+        buffer.create_tag(Some("file"), &[("weight", &700)]);
+        buffer.create_tag(Some("added"), &[("foreground", &"#26a269")]);
+        buffer.create_tag(Some("removed"), &[("foreground", &"#c01c28")]);
+        if diff.is_empty() {
+            buffer.set_text(&gettext("No differences"));
+        }
+        for line in diff {
+            let (text, tag) = match line {
+                PwDiffLine::File(path) => (format!("\n{}\n", path), "file"),
+                PwDiffLine::Same(line) => (format!("  {}\n", line), ""),
+                PwDiffLine::Removed(line) => (format!("- {}\n", line), "removed"),
+                PwDiffLine::Added(line) => (format!("+ {}\n", line), "added"),
+            };
+            let mut end = buffer.end_iter();
+            match tag.is_empty() {
+                true => buffer.insert(&mut end, &text),
+                false => buffer.insert_with_tags_by_name(&mut end, &text, &[tag]),
+            }
+        }
+
+        let text_view = TextView::builder()
+            .buffer(&buffer)
+            .editable(false)
+            .monospace(true)
+            .wrap_mode(WrapMode::None)
+            .build();
+        let scrolled_window = ScrolledWindow::builder()
+            .child(&text_view)
+            .hscrollbar_policy(PolicyType::Automatic)
+            .min_content_height(360)
+            .min_content_width(560)
+            .build();
+
+        let dialog = MessageDialog::new(
+            Some(&application.window.window),
+            Some(&gettext("Differences")),
+            None,
+        );
+        dialog.set_extra_child(Some(&scrolled_window));
+        dialog.add_response(Self::RESPONSE_CLOSE, &gettext("Close"));
+        dialog.present();
+    }
+
+    async fn restore(self: &Rc<Self>, application: &Rc<Application>, id: &str) {
+        let window = &application.window.window;
+        let history = &application.pipewire.history;
+        let snapshot = match history
+            .list()
+            .and_then(|snapshots| Self::find(&snapshots, id).cloned())
+        {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Finding snapshot failed:\n{:?}", error);
+                return;
+            }
+        };
+        let date = Self::format_timestamp(snapshot.timestamp);
+
+        let dialog = MessageDialog::new(
+            Some(window),
+            Some(&gettext("Restore this configuration?")),
+            Some(&gettext_f(
+                "Your PipeWire and WirePlumber config dirs are replaced with the restore point of {}. The current configuration is kept as a new restore point.",
+                &[&date],
+            )),
+        );
+        dialog.add_responses(&[
+            (Self::RESPONSE_CANCEL, &gettext("Cancel")),
+            (Self::RESPONSE_RESTORE, &gettext("Restore")),
+        ]);
+        dialog.set_response_appearance(Self::RESPONSE_RESTORE, ResponseAppearance::Destructive);
+        dialog.set_close_response(Self::RESPONSE_CANCEL);
+        if dialog.choose_future().await != Self::RESPONSE_RESTORE {
+            return;
+        }
+
+        let restored = {
+            let _pause = application.watcher.pause();
+            history
+                .snapshot(&format!("Before restoring {}", date))
+                .and_then(|_snapshot| history.restore(&snapshot))
+                .and_then(|()| application.pipewire.reload())
+        };
+        self.refresh(application);
+
+        if let Err(error) = restored {
+            error!(target: Self::LOG_TARGET, "Restoring failed:\n{:?}", error);
+            ApplyDialog::show_error(
+                window,
+                &gettext("Could not restore the configuration"),
+                &error,
+            )
+            .await;
+            return;
+        }
+
+        let files: Vec<_> = application
+            .pipewire
+            .get_configs()
+            .iter()
+            .map(|pw_config| pw_config.borrow().config_file)
+            .collect();
+        ApplyDialog::restart_units(window, &PwServices::get_affected_units(&files)).await;
+    }
+
+    fn find<'a>(snapshots: &'a [PwSnapshot], id: &str) -> anyhow::Result<&'a PwSnapshot> {
+        snapshots
+            .iter()
+            .find(|snapshot| snapshot.id == id)
+            .ok_or_else(|| anyhow::anyhow!("Snapshot {} does not exist anymore", id))
+    }
+
+    fn format_timestamp(timestamp: u64) -> String {
+        DateTime::from_unix_local(timestamp as i64)
+            .and_then(|date_time| date_time.format("%x %X"))
+            .map(|formatted| formatted.to_string())
+            .unwrap_or_else(|_| timestamp.to_string())
+    }
+}
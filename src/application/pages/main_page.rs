@@ -1,14 +1,23 @@
-use super::{NavPage, PageState};
-use crate::application::{Application, pipewire::pipewire::Pipewire};
+use super::{NavPage, PageState, registry::PageDescriptor};
+use crate::{
+    application::{
+        Application,
+        pipewire::{config::PwConfig, pipewire::PwLoaded},
+        window::{apply_dialog::ApplyDialog, file_chooser::FileChooser},
+    },
+    i18n::{gettext, gettext_f},
+};
+use ashpd::desktop::file_chooser::FileFilter;
 use libadwaita::{
-    NavigationPage,
+    MessageDialog, NavigationPage,
     glib::{self},
     gtk::{
         self, Button, Label,
         prelude::{BoxExt, ButtonExt},
     },
+    prelude::{MessageDialogExt, MessageDialogExtManual},
 };
-use log::info;
+use log::{error, info};
 use std::rc::Rc;
 
 pub struct MainPage {
@@ -21,13 +30,37 @@ impl NavPage for MainPage {
     const LABEL: &str = "main-page";
     const LOG_TARGET: &str = Self::LABEL;
 
-    fn new() -> Self {
-        let title = String::from("Main page");
+    fn init(&mut self, application: Rc<Application>) {
+        self.on_init(application);
+        self.state.set_init(true);
+    }
+
+    fn is_init(&self) -> bool {
+        self.state.get_init()
+    }
+
+    fn get_state(&self) -> &PageState {
+        &self.state
+    }
+
+    fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    fn get_navpage(&self) -> &NavigationPage {
+        &self.nav_page
+    }
+}
+impl MainPage {
+    pub fn new(descriptor: &'static PageDescriptor) -> Self {
+        let title = descriptor.get_title();
         let label = Label::builder()
-            .label(concat!(
-                "<b>Label title</b>\n",
-                "<span>Label text</span>\n",
-                "<span>Some more text</span>"
+            .label(format!(
+                "<b>{}</b>\n{}",
+                gettext("Open a config file"),
+                glib::markup_escape_text(&gettext(
+                    "Load the settings of a PipeWire or WirePlumber config file, e.g. a drop-in from elsewhere, as pending settings to review and apply."
+                ))
             ))
             .wrap(true)
             .use_markup(true)
@@ -39,7 +72,7 @@ impl NavPage for MainPage {
             .margin_bottom(12)
             .margin_start(12)
             .margin_end(12)
-            .label("Open file")
+            .label(gettext("Open file"))
             .build();
 
         let (nav_page, _header, content_box, state) = Self::build_nav_page(&title);
@@ -55,40 +88,75 @@ impl NavPage for MainPage {
         };
     }
 
-    fn init(&mut self, application: Rc<Application>) {
-        self.on_init(application);
-        self.state.set_init(true);
-    }
+    const RESPONSE_CLOSE: &str = "close";
 
-    fn is_init(&self) -> bool {
-        self.state.get_init()
+    fn on_init(&mut self, application: Rc<Application>) {
+        self.button.connect_clicked(move |_| {
+            let application = application.clone();
+            glib::spawn_future_local(async { Self::get_document(application).await });
+        });
     }
 
-    fn get_state(&self) -> &PageState {
-        &self.state
-    }
+    async fn get_document(application: Rc<Application>) {
+        let window = &application.window.window;
+        let filter = FileFilter::new(&gettext("PipeWire config")).glob("*.conf");
+        let path = match FileChooser::open(window, &gettext("Open config file"), filter).await {
+            Ok(Some(path)) => path,
+            Ok(None) => return,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Choosing file failed:\n{:?}", error);
+                ApplyDialog::show_error(window, &gettext("Could not open file"), &error).await;
+                return;
+            }
+        };
 
-    fn get_title(&self) -> &str {
-        &self.title
-    }
+        let loaded = match application.pipewire.load_file(&path) {
+            Ok(loaded) => loaded,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Loading file failed:\n{:?}", error);
+                ApplyDialog::show_error(window, &gettext("Could not open file"), &error).await;
+                return;
+            }
+        };
+        info!(target: Self::LOG_TARGET, "Loaded {} as {}", path.display(), loaded.file_name);
 
-    fn get_navpage(&self) -> &NavigationPage {
-        &self.nav_page
-    }
-}
-impl MainPage {
-    fn on_init(&mut self, application: Rc<Application>) {
-        let pipewire = application.pipewire.clone();
+        application
+            .pages
+            .borrow_mut()
+            .show_pending(application.clone());
 
-        self.button.connect_clicked(move |_| {
-            let pipewire = pipewire.clone();
-            glib::spawn_future_local(async { Self::get_document(pipewire).await });
-        });
+        Self::show_loaded(&application, &loaded).await;
     }
 
-    async fn get_document(pipewire: Rc<Pipewire>) {
-        let a = &pipewire.surround.borrow().current;
-        info!("{:?}", a);
-        todo!()
+    async fn show_loaded(application: &Application, loaded: &PwLoaded) {
+        let mut body = gettext("These settings are now pending:");
+        for (config_file, keys) in &loaded.loaded {
+            let (file_name, section_name, _subsection_name) =
+                PwConfig::get_config_file_and_sections(config_file);
+            body.push_str(&format!(
+                "\n• {} {}: {}",
+                file_name,
+                section_name,
+                keys.join(", ")
+            ));
+        }
+        body.push_str("\n\n");
+        body.push_str(&gettext("Review them on their pages and apply them there."));
+
+        if !loaded.skipped.is_empty() {
+            body.push_str("\n\n");
+            body.push_str(&gettext("These can not be edited here and were left out:"));
+            for skipped in &loaded.skipped {
+                body.push_str(&format!("\n• {}", skipped));
+            }
+        }
+
+        let dialog = MessageDialog::new(
+            Some(&application.window.window),
+            Some(&gettext_f("Loaded {}", &[&loaded.file_name])),
+            Some(&body),
+        );
+        dialog.add_response(Self::RESPONSE_CLOSE, &gettext("Close"));
+        dialog.choose_future().await;
     }
 }
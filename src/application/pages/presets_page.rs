@@ -0,0 +1,477 @@
+use super::{NavPage, PageState, registry::PageDescriptor};
+use crate::{
+    application::{
+        Application,
+        pipewire::preset::{PwPreset, PwPresetBundle, PwPresetChange, PwPresetSource},
+        window::{apply_dialog::ApplyDialog, file_chooser::FileChooser},
+    },
+    i18n::{gettext, gettext_f},
+};
+use anyhow::{Context, Result, bail};
+use ashpd::desktop::file_chooser::FileFilter;
+use libadwaita::{
+    ActionRow, ComboRow, EntryRow, HeaderBar, MessageDialog, NavigationPage, PreferencesGroup,
+    PreferencesPage, ResponseAppearance, ToolbarView, glib,
+    gtk::{
+        self, Button, StringList,
+        prelude::{ButtonExt, EditableExt},
+    },
+    prelude::{
+        ActionRowExt, ComboRowExt, MessageDialogExt, MessageDialogExtManual, PreferencesGroupExt,
+        PreferencesPageExt,
+    },
+};
+use log::{error, info};
+use std::{cell::RefCell, fs, rc::Rc};
+
+pub struct PresetsPage {
+    pub nav_page: NavigationPage,
+    header: HeaderBar,
+    new_group: PreferencesGroup,
+    name_row: EntryRow,
+    source_row: ComboRow,
+    preset_list: Rc<PresetList>,
+    state: PageState,
+    title: String,
+}
+impl NavPage for PresetsPage {
+    const LABEL: &str = "presets-page";
+    const LOG_TARGET: &str = Self::LABEL;
+
+    fn init(&mut self, application: Rc<Application>) {
+        self.on_init(application);
+        self.state.set_init(true);
+    }
+
+    fn is_init(&self) -> bool {
+        self.state.get_init()
+    }
+
+    fn get_state(&self) -> &PageState {
+        &self.state
+    }
+
+    fn get_navpage(&self) -> &NavigationPage {
+        &self.nav_page
+    }
+
+    fn get_title(&self) -> &str {
+        &self.title
+    }
+}
+impl PresetsPage {
+    pub fn new(descriptor: &'static PageDescriptor) -> Self {
+        let title = descriptor.get_title();
+        let pref_page = PreferencesPage::new();
+        let header = HeaderBar::new();
+        let toolbar = ToolbarView::new();
+        toolbar.add_top_bar(&header);
+        toolbar.set_content(Some(&pref_page));
+
+        let name_row = EntryRow::builder().title(gettext("Name")).build();
+        let source_row = ComboRow::builder()
+            .title(gettext("Settings"))
+            .subtitle(gettext("Settings of all pages to save in the preset"))
+            .model(&StringList::new(&[
+                &gettext("Pending"),
+                &gettext("Applied"),
+            ]))
+            .build();
+        let new_group = PreferencesGroup::builder()
+            .title(gettext("New preset"))
+            .description(gettext("Saving a preset with the same name replaces it"))
+            .build();
+        new_group.add(&name_row);
+        new_group.add(&source_row);
+        pref_page.add(&new_group);
+
+        let group = PreferencesGroup::builder()
+            .title(gettext("Presets"))
+            .build();
+        pref_page.add(&group);
+
+        let nav_page = NavigationPage::builder()
+            .title(&title)
+            .tag(&title)
+            .child(&toolbar)
+            .build();
+
+        let preset_list = Rc::new(PresetList {
+            group,
+            rows: RefCell::new(Vec::new()),
+        });
+
+        Self {
+            nav_page,
+            header,
+            new_group,
+            name_row,
+            source_row,
+            preset_list,
+            state: PageState::new(),
+            title,
+        }
+    }
+
+    fn on_init(&mut self, application: Rc<Application>) {
+        let save_button = Button::builder()
+            .label(gettext("Save"))
+            .tooltip_text(gettext("Save the settings as a preset"))
+            .css_classes(["suggested-action"])
+            .valign(gtk::Align::Center)
+            .build();
+        self.new_group.set_header_suffix(Some(&save_button));
+
+        let name_row = self.name_row.clone();
+        let source_row = self.source_row.clone();
+        let preset_list = self.preset_list.clone();
+        let save_application = application.clone();
+        save_button.connect_clicked(move |_| {
+            let source = match source_row.selected() {
+                0 => PwPresetSource::Pending,
+                _ => PwPresetSource::Applied,
+            };
+            let saved = preset_list.save(&save_application, name_row.text().trim(), source);
+            if saved {
+                name_row.set_text("");
+            }
+        });
+
+        let import_button = Button::builder()
+            .icon_name("document-open-symbolic")
+            .tooltip_text(gettext("Import preset"))
+            .build();
+        self.header.pack_start(&import_button);
+
+        let preset_list = self.preset_list.clone();
+        let import_application = application.clone();
+        import_button.connect_clicked(move |_| {
+            glib::spawn_future_local(preset_list.clone().import(import_application.clone()));
+        });
+
+        self.preset_list.refresh(&application);
+    }
+}
+
+struct PresetList {
+    group: PreferencesGroup,
+    rows: RefCell<Vec<ActionRow>>,
+}
+impl PresetList {
+    const LOG_TARGET: &str = PresetsPage::LOG_TARGET;
+    const RESPONSE_CANCEL: &str = "cancel";
+    const RESPONSE_IMPORT: &str = "import";
+    const RESPONSE_DELETE: &str = "delete";
+
+    fn refresh(self: &Rc<Self>, application: &Rc<Application>) {
+        for row in self.rows.take() {
+            self.group.remove(&row);
+        }
+
+        let presets = match application.pipewire.presets.list() {
+            Ok(presets) => presets,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Listing presets failed:\n{:?}", error);
+                Vec::new()
+            }
+        };
+        let description = gettext("No presets yet");
+        self.group.set_description(match presets.is_empty() {
+            true => Some(&description),
+            false => None,
+        });
+
+        let mut rows = self.rows.borrow_mut();
+        for preset in &presets {
+            let row = self.build_row(application, preset);
+            self.group.add(&row);
+            rows.push(row);
+        }
+
+        application.window.view.app_menu.set_presets(&presets);
+    }
+
+    fn build_row(self: &Rc<Self>, application: &Rc<Application>, preset: &PwPreset) -> ActionRow {
+        let row = ActionRow::builder()
+            .title(&preset.name)
+            .subtitle(preset.get_summary())
+            .subtitle_lines(2)
+            .build();
+
+        let activate_button = Button::builder()
+            .label(gettext("Activate"))
+            .tooltip_text(gettext("Write the preset to the drop-in files"))
+            .valign(gtk::Align::Center)
+            .build();
+        let activate_application = application.clone();
+        let activate_preset = preset.clone();
+        activate_button.connect_clicked(move |_| {
+            glib::spawn_future_local(ApplyDialog::activate_preset(
+                activate_application.clone(),
+                activate_preset.clone(),
+            ));
+        });
+        row.add_suffix(&activate_button);
+
+        let export_button = Button::builder()
+            .icon_name("document-save-symbolic")
+            .tooltip_text(gettext("Export preset to a file"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        let export_application = application.clone();
+        let export_preset = preset.clone();
+        export_button.connect_clicked(move |_| {
+            glib::spawn_future_local(Self::export(
+                export_application.clone(),
+                export_preset.clone(),
+            ));
+        });
+        row.add_suffix(&export_button);
+
+        let delete_button = Button::builder()
+            .icon_name("user-trash-symbolic")
+            .tooltip_text(gettext("Delete preset"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        let preset_list = self.clone();
+        let delete_application = application.clone();
+        let name = preset.name.clone();
+        delete_button.connect_clicked(move |_| {
+            glib::spawn_future_local(
+                preset_list
+                    .clone()
+                    .delete(delete_application.clone(), name.clone()),
+            );
+        });
+        row.add_suffix(&delete_button);
+
+        row
+    }
+
+    /// Returns if the preset was saved, errors are shown in a dialog.
+    fn save(
+        self: &Rc<Self>,
+        application: &Rc<Application>,
+        name: &str,
+        source: PwPresetSource,
+    ) -> bool {
+        let configs: Vec<_> = application.pipewire.get_configs();
+        let configs: Vec<_> = configs.iter().map(|pw_config| pw_config.borrow()).collect();
+        let configs: Vec<_> = configs.iter().map(|pw_config| &**pw_config).collect();
+
+        let saved = PwPreset::from_configs(name, &configs, source)
+            .and_then(|preset| application.pipewire.presets.save(&preset));
+        if let Err(error) = saved {
+            error!(target: Self::LOG_TARGET, "Saving preset failed:\n{:?}", error);
+
+            let window = application.window.window.clone();
+            glib::spawn_future_local(async move {
+                ApplyDialog::show_error(&window, &gettext("Could not save preset"), &error).await;
+            });
+            return false;
+        }
+
+        self.refresh(application);
+        true
+    }
+
+    async fn delete(self: Rc<Self>, application: Rc<Application>, name: String) {
+        let dialog = MessageDialog::new(
+            Some(&application.window.window),
+            Some(&gettext_f("Delete {}?", &[&name])),
+            Some(&gettext("The preset can not be restored.")),
+        );
+        dialog.add_responses(&[
+            (Self::RESPONSE_CANCEL, &gettext("Cancel")),
+            (Self::RESPONSE_DELETE, &gettext("Delete")),
+        ]);
+        dialog.set_response_appearance(Self::RESPONSE_DELETE, ResponseAppearance::Destructive);
+        dialog.set_close_response(Self::RESPONSE_CANCEL);
+        if dialog.choose_future().await != Self::RESPONSE_DELETE {
+            return;
+        }
+
+        if let Err(error) = application.pipewire.presets.delete(&name) {
+            error!(target: Self::LOG_TARGET, "Deleting preset failed:\n{:?}", error);
+        }
+        self.refresh(&application);
+    }
+
+    async fn export(application: Rc<Application>, preset: PwPreset) {
+        let window = &application.window.window;
+        let path = FileChooser::save(
+            window,
+            &gettext("Export preset"),
+            &PwPreset::get_file_name(&preset.name),
+            Self::build_file_filter(),
+        )
+        .await;
+
+        let exported = path.and_then(|path| {
+            let Some(path) = path else {
+                return Ok(());
+            };
+            let bundle = PwPresetBundle {
+                preset,
+                author: glib::real_name().to_string_lossy().into_owned(),
+                pipewire_version: application.pipewire.versions.pipewire.to_string(),
+            };
+            fs::write(&path, bundle.to_string()?).context(format!("Writing {}", path.display()))?;
+
+            info!(target: Self::LOG_TARGET, "Exported preset to {}", path.display());
+            Ok(())
+        });
+
+        if let Err(error) = exported {
+            error!(target: Self::LOG_TARGET, "Exporting preset failed:\n{:?}", error);
+            ApplyDialog::show_error(window, &gettext("Could not export preset"), &error).await;
+        }
+    }
+
+    async fn import(self: Rc<Self>, application: Rc<Application>) {
+        let window = &application.window.window;
+        let path =
+            match FileChooser::open(window, &gettext("Import preset"), Self::build_file_filter())
+                .await
+            {
+                Ok(Some(path)) => path,
+                Ok(None) => return,
+                Err(error) => {
+                    error!(target: Self::LOG_TARGET, "Choosing preset failed:\n{:?}", error);
+                    ApplyDialog::show_error(window, &gettext("Could not import preset"), &error)
+                        .await;
+                    return;
+                }
+            };
+
+        let reviewed = match Self::review(&application, &path) {
+            Ok(reviewed) => reviewed,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Reading preset failed:\n{:?}", error);
+                ApplyDialog::show_error(window, &gettext("Could not import preset"), &error).await;
+                return;
+            }
+        };
+        let (bundle, known, unknown, changes) = reviewed;
+
+        if !Self::ask_import(&application, &bundle, &unknown, &changes).await {
+            return;
+        }
+
+        if let Err(error) = application.pipewire.presets.save(&known) {
+            error!(target: Self::LOG_TARGET, "Saving preset failed:\n{:?}", error);
+            ApplyDialog::show_error(window, &gettext("Could not import preset"), &error).await;
+            return;
+        }
+        info!(target: Self::LOG_TARGET, "Imported preset {}", known.name);
+
+        self.refresh(&application);
+    }
+
+    /// Parses the file and splits it into what this machine knows and what it would change.
+    fn review(
+        application: &Application,
+        path: &std::path::Path,
+    ) -> Result<(PwPresetBundle, PwPreset, Vec<String>, Vec<PwPresetChange>)> {
+        let contents = fs::read_to_string(path).context(format!("Reading {}", path.display()))?;
+        let bundle = PwPresetBundle::parse(&contents)?;
+
+        let configs = application.pipewire.get_configs();
+        let configs: Vec<_> = configs.iter().map(|pw_config| pw_config.borrow()).collect();
+        let configs: Vec<_> = configs.iter().map(|pw_config| &**pw_config).collect();
+
+        let (known, unknown) = bundle.preset.split_unknown(&configs);
+        if known.files.is_empty() {
+            bail!(
+                "None of the settings in {} are known on this machine",
+                bundle.preset.name
+            );
+        }
+        let changes = known.get_changes(&configs);
+
+        Ok((bundle, known, unknown, changes))
+    }
+
+    async fn ask_import(
+        application: &Application,
+        bundle: &PwPresetBundle,
+        unknown: &[String],
+        changes: &[PwPresetChange],
+    ) -> bool {
+        let mut body = String::new();
+        if !bundle.author.is_empty() {
+            body.push_str(&gettext_f("By {}", &[&bundle.author]));
+            body.push('\n');
+        }
+        let version = application.pipewire.versions.pipewire.to_string();
+        if !bundle.pipewire_version.is_empty() && bundle.pipewire_version != version {
+            body.push_str(&gettext_f(
+                "Made with PipeWire {}, this machine has {}",
+                &[&bundle.pipewire_version, &version],
+            ));
+            body.push('\n');
+        }
+
+        body.push('\n');
+        match changes.is_empty() {
+            true => body.push_str(&gettext("Activating it changes nothing on this machine.")),
+            false => body.push_str(&gettext("Activating it changes:")),
+        }
+        for change in changes {
+            let current = match &change.current {
+                Some(current) => current.to_string(),
+                None => gettext("unset"),
+            };
+            body.push_str(&format!(
+                "\n• {} {}: {} → {}",
+                change.file, change.key, current, change.new
+            ));
+        }
+
+        if !unknown.is_empty() {
+            body.push_str("\n\n");
+            body.push_str(&gettext(
+                "These settings are not known on this machine and are left out:",
+            ));
+            for key in unknown {
+                body.push_str(&format!("\n• {}", key));
+            }
+        }
+
+        let exists = application
+            .pipewire
+            .presets
+            .get(&bundle.preset.name)
+            .is_ok();
+        if exists {
+            body.push_str("\n\n");
+            body.push_str(&gettext("This replaces the preset with the same name."));
+        }
+
+        let dialog = MessageDialog::new(
+            Some(&application.window.window),
+            Some(&gettext_f("Import {}?", &[&bundle.preset.name])),
+            Some(&body),
+        );
+        dialog.add_responses(&[
+            (Self::RESPONSE_CANCEL, &gettext("Cancel")),
+            (Self::RESPONSE_IMPORT, &gettext("Import")),
+        ]);
+        dialog.set_response_appearance(
+            Self::RESPONSE_IMPORT,
+            match exists {
+                true => ResponseAppearance::Destructive,
+                false => ResponseAppearance::Suggested,
+            },
+        );
+        dialog.set_close_response(Self::RESPONSE_CANCEL);
+
+        dialog.choose_future().await == Self::RESPONSE_IMPORT
+    }
+
+    fn build_file_filter() -> FileFilter {
+        FileFilter::new(&gettext("Preset")).glob("*.json")
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    application::pipewire::config::{
+        PwConfigFile, PwPipewireSection, PwPipewireSectionSub, PwPulseSection, PwPulseSectionSub,
+        PwWireplumberSection,
+    },
+    i18n::gettext,
+};
+
+/// Which page type builds a page.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PageKind {
+    Main,
+    /// Rows for the settings of a config
+    Settings,
+    /// Settings rows with the speaker layout editor
+    Surround,
+    /// Settings rows that can also be tried live
+    Clock,
+    Presets,
+    History,
+    Diagnostics,
+}
+
+/// A page in the sidebar, navigated to by its id.
+#[derive(Debug)]
+pub struct PageDescriptor {
+    pub id: &'static str,
+    /// In English, use `get_title` to show it
+    pub title: &'static str,
+    pub icon_name: &'static str,
+    pub kind: PageKind,
+    /// Config the rows are for, the page is left out when it is unavailable
+    pub config_file: Option<PwConfigFile>,
+    /// Only keys starting with this are shown, e.g. `channelmix.`
+    pub key_filter: Option<&'static str>,
+}
+impl PageDescriptor {
+    pub fn get_title(&self) -> String {
+        gettext(self.title)
+    }
+
+    pub fn is_shown_key(&self, key: &str) -> bool {
+        self.key_filter.is_none_or(|filter| key.starts_with(filter))
+    }
+}
+
+pub const MAIN_PAGE: &str = "main";
+pub const SURROUND_PAGE: &str = "surround";
+pub const CLOCK_PAGE: &str = "clock";
+pub const SESSION_PAGE: &str = "session";
+pub const PRESETS_PAGE: &str = "presets";
+pub const HISTORY_PAGE: &str = "history";
+pub const DIAGNOSTICS_PAGE: &str = "diagnostics";
+/// Shown on the first start, later the page shown last
+pub const DEFAULT_PAGE: &str = SURROUND_PAGE;
+
+/// All pages in sidebar order. A new page of an existing `PageKind` only needs an entry here,
+/// a new kind also needs an `AnyPage` variant built in `AnyPage::new` and an arm in `with_page!`.
+pub static PAGES: [PageDescriptor; 7] = [
+    PageDescriptor {
+        id: MAIN_PAGE,
+        title: "Main page",
+        icon_name: "document-open-symbolic",
+        kind: PageKind::Main,
+        config_file: None,
+        key_filter: None,
+    },
+    PageDescriptor {
+        id: SURROUND_PAGE,
+        title: "Surround",
+        icon_name: "audio-speakers-symbolic",
+        kind: PageKind::Surround,
+        config_file: Some(PwConfigFile::PipewirePulse(
+            PwPulseSection::StreamProperties(PwPulseSectionSub::None),
+        )),
+        key_filter: None,
+    },
+    PageDescriptor {
+        id: CLOCK_PAGE,
+        title: "Clock",
+        icon_name: "preferences-system-time-symbolic",
+        kind: PageKind::Clock,
+        config_file: Some(PwConfigFile::Pipewire(
+            PwPipewireSection::ContextProperties(PwPipewireSectionSub::Clock),
+        )),
+        key_filter: None,
+    },
+    PageDescriptor {
+        id: SESSION_PAGE,
+        title: "Session",
+        icon_name: "preferences-other-symbolic",
+        kind: PageKind::Settings,
+        config_file: Some(PwConfigFile::Wireplumber(PwWireplumberSection::Settings)),
+        key_filter: None,
+    },
+    PageDescriptor {
+        id: PRESETS_PAGE,
+        title: "Presets",
+        icon_name: "view-list-symbolic",
+        kind: PageKind::Presets,
+        config_file: None,
+        key_filter: None,
+    },
+    PageDescriptor {
+        id: HISTORY_PAGE,
+        title: "History",
+        icon_name: "document-open-recent-symbolic",
+        kind: PageKind::History,
+        config_file: None,
+        key_filter: None,
+    },
+    PageDescriptor {
+        id: DIAGNOSTICS_PAGE,
+        title: "Diagnostics",
+        icon_name: "utilities-system-monitor-symbolic",
+        kind: PageKind::Diagnostics,
+        config_file: None,
+        key_filter: None,
+    },
+];
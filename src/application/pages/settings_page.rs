@@ -0,0 +1,118 @@
+use super::{NavPage, PageState, PrefPage, PreferencesPageEntries, registry::PageDescriptor};
+use crate::application::Application;
+use libadwaita::{
+    HeaderBar, NavigationPage, PreferencesPage, PreferencesRow,
+    gio::{SimpleActionGroup, prelude::ActionMapExtManual},
+    prelude::PreferencesPageExt,
+};
+use std::{collections::HashMap, rc::Rc};
+
+/// Rows for the settings of the config a page descriptor names.
+pub struct SettingsPage {
+    pub descriptor: &'static PageDescriptor,
+    pub nav_page: NavigationPage,
+    pref_page: PreferencesPage,
+    pref_groups: PreferencesPageEntries,
+    page_switch: Option<PreferencesRow>,
+    header: HeaderBar,
+    state: PageState,
+    title: String,
+    actions: SimpleActionGroup,
+}
+impl NavPage for SettingsPage {
+    const LABEL: &str = "settings-page";
+    const LOG_TARGET: &str = Self::LABEL;
+
+    fn is_init(&self) -> bool {
+        self.state.get_init()
+    }
+
+    fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    fn get_state(&self) -> &PageState {
+        &self.state
+    }
+
+    fn init(&mut self, application: Rc<Application>) {
+        self.on_init(application);
+        self.state.set_init(true);
+    }
+
+    fn get_navpage(&self) -> &NavigationPage {
+        &self.nav_page
+    }
+}
+// Every settings page has its own action group, so they share the labels
+impl PrefPage for SettingsPage {
+    const ACTION_LABEL: &str = "settings";
+    const INPUT_ACTION_LABEL: &str = "input";
+    const INPUT_PAGE_ACTION_LABEL: &str = "settings.input";
+    const PAGE_ENABLE_ACTION_LABEL: &str = "page-enable";
+    const PAGE_ENABLE_PAGE_ACTION_LABEL: &str = "settings.page-enable";
+    const APPLY_ACTION_LABEL: &str = "apply";
+    const APPLY_PAGE_ACTION_LABEL: &str = "settings.apply";
+
+    fn get_pref_groups(&self) -> &PreferencesPageEntries {
+        &self.pref_groups
+    }
+
+    fn get_page_switch(&self) -> Option<&PreferencesRow> {
+        self.page_switch.as_ref()
+    }
+
+    fn set_state_enabled(&mut self, enabled: bool) {
+        self.state.set_page_enabled(enabled);
+    }
+}
+impl SettingsPage {
+    pub fn new(descriptor: &'static PageDescriptor) -> Self {
+        let title = descriptor.get_title();
+        let (nav_page, pref_page, header, _banner, state, actions) = Self::build_pref_page(&title);
+
+        Self {
+            descriptor,
+            nav_page,
+            pref_page,
+            pref_groups: HashMap::with_capacity(0),
+            page_switch: None,
+            header,
+            state,
+            title,
+            actions,
+        }
+    }
+
+    fn on_init(&mut self, application: Rc<Application>) {
+        let pipewire = application.pipewire.clone();
+        let Some(pw_config) = self
+            .descriptor
+            .config_file
+            .and_then(|config_file| pipewire.get_config(&config_file))
+        else {
+            return;
+        };
+
+        let input_action = self.build_input_action(application.clone(), pw_config);
+        let apply_action = self.build_apply_action(application.clone(), pw_config);
+        let page_enabled_action =
+            self.build_page_switch_action(application.clone(), pw_config, self.descriptor.id);
+        self.actions
+            .add_action_entries([input_action, apply_action, page_enabled_action]);
+        self.header.pack_end(&self.build_apply_button());
+
+        let is_active = pw_config.borrow().is_dropin_active();
+        let (enable_pref_group, page_switch) = self.build_page_switch(is_active);
+        self.pref_page.add(&enable_pref_group);
+        self.page_switch = Some(page_switch);
+
+        self.pref_groups = self.build_sections_from_default(&pw_config.borrow(), self.descriptor);
+        for (group, _rows) in &self.pref_groups {
+            self.pref_page.add(group);
+        }
+
+        self.show_advanced(application.preferences.get_show_advanced());
+        self.set_enabled(is_active);
+    }
+}
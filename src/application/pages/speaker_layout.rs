@@ -0,0 +1,435 @@
+use super::Pages;
+use crate::{
+    application::{
+        Application,
+        pipewire::{
+            channel_map::{PwChannelLayout, PwChannelMap, PwSpeaker},
+            config::PwConfig,
+            version::{PwCapability, PwProgram},
+        },
+    },
+    i18n::{gettext, gettext_f},
+};
+use libadwaita::{
+    ActionRow, ComboRow, PreferencesGroup,
+    glib::variant::ToVariant,
+    gtk::{
+        self, Button, DrawingArea, StringList, cairo,
+        prelude::{ButtonExt, DrawingAreaExtManual, WidgetExt},
+    },
+    prelude::{ActionRowExt, ComboRowExt, PreferencesGroupExt},
+};
+use log::warn;
+use serde_json::json;
+use std::{
+    cell::{Cell, RefCell},
+    f64::consts::TAU,
+    rc::Rc,
+};
+
+/// A room diagram with a position row per channel. The channel map is used for the
+/// stream properties of the page, or copied as a loopback sink or ALSA rule.
+pub struct SpeakerLayout {
+    layout_group: PreferencesGroup,
+    channels_group: PreferencesGroup,
+    use_group: PreferencesGroup,
+    diagram: DrawingArea,
+    layout_row: ComboRow,
+    channel_rows: RefCell<Vec<ComboRow>>,
+    use_buttons: Vec<Button>,
+    map: RefCell<PwChannelMap>,
+    /// Channel drawn in the accent color
+    highlighted: Cell<Option<usize>>,
+    test_action: &'static str,
+}
+impl SpeakerLayout {
+    const LOG_TARGET: &str = "speaker-layout";
+    const KEY: &str = "audio.position";
+    const DIAGRAM_SIZE: i32 = 260;
+    const SPEAKER_RADIUS: f64 = 15.0;
+
+    /// Starts with the pending, current or default channel map of the config, or 5.1.
+    /// The input action takes the stream properties as pending values, the test action
+    /// the index of a channel to test.
+    pub fn new(
+        application: Rc<Application>,
+        pw_config: &PwConfig,
+        input_action: &'static str,
+        test_action: &'static str,
+    ) -> Rc<Self> {
+        let map = pw_config
+            .new
+            .get(Self::KEY)
+            .or_else(|| pw_config.current.get(Self::KEY))
+            .or_else(|| {
+                pw_config
+                    .default
+                    .get(Self::KEY)
+                    .map(|(value, _options)| value)
+            })
+            .and_then(PwChannelMap::from_value)
+            .unwrap_or_else(|| PwChannelMap::from_layout(PwChannelLayout::Surround51));
+
+        let diagram = DrawingArea::builder()
+            .content_width(Self::DIAGRAM_SIZE)
+            .content_height(Self::DIAGRAM_SIZE)
+            .margin_bottom(12)
+            .build();
+        let mut layout_titles: Vec<String> = PwChannelLayout::ALL
+            .iter()
+            .map(|layout| gettext(layout.get_title()))
+            .collect();
+        layout_titles.push(gettext("Custom"));
+        let layout_titles: Vec<&str> = layout_titles.iter().map(String::as_str).collect();
+        let layout_row = ComboRow::builder()
+            .title(gettext("Layout"))
+            .model(&StringList::new(&layout_titles))
+            .selected(Self::get_layout_index(&map))
+            .build();
+        let layout_group = PreferencesGroup::builder()
+            .title(gettext("Speaker layout"))
+            .description(gettext(
+                "Which speaker each channel plays on, seen from above",
+            ))
+            .build();
+        layout_group.add(&diagram);
+        layout_group.add(&layout_row);
+
+        let channels_group = PreferencesGroup::builder()
+            .title(gettext("Channels"))
+            .build();
+
+        let (use_group, use_buttons) = Self::build_use_group(pw_config);
+
+        let speaker_layout = Rc::new(Self {
+            layout_group,
+            channels_group,
+            use_group,
+            diagram,
+            layout_row,
+            channel_rows: RefCell::new(Vec::new()),
+            use_buttons,
+            map: RefCell::new(map),
+            highlighted: Cell::new(None),
+            test_action,
+        });
+        speaker_layout.connect(application, input_action);
+        speaker_layout.show_channels();
+
+        speaker_layout
+    }
+
+    pub fn get_groups(&self) -> [&PreferencesGroup; 3] {
+        [&self.layout_group, &self.channels_group, &self.use_group]
+    }
+
+    pub fn get_map(&self) -> PwChannelMap {
+        self.map.borrow().clone()
+    }
+
+    /// Draws a channel in the accent color, e.g. while it is tested.
+    pub fn highlight(&self, channel: Option<usize>) {
+        self.highlighted.set(channel);
+        self.diagram.queue_draw();
+    }
+
+    /// Follows a channel map that was set elsewhere, e.g. by a preset.
+    pub fn show_value(self: &Rc<Self>, value: &serde_json::Value) {
+        let Some(map) = PwChannelMap::from_value(value) else {
+            return;
+        };
+        if *self.map.borrow() == map {
+            return;
+        }
+        self.map.replace(map);
+        self.show_channels();
+    }
+
+    fn get_layout_index(map: &PwChannelMap) -> u32 {
+        let index = map
+            .get_layout()
+            .and_then(|layout| {
+                PwChannelLayout::ALL
+                    .iter()
+                    .position(|other| *other == layout)
+            })
+            .unwrap_or(PwChannelLayout::ALL.len());
+
+        index as u32
+    }
+
+    fn build_use_group(pw_config: &PwConfig) -> (PreferencesGroup, Vec<Button>) {
+        let group = PreferencesGroup::builder()
+            .title(gettext("Use layout"))
+            .build();
+        let mut buttons = Vec::new();
+
+        let rows = [
+            (
+                "Stream properties",
+                "Pending settings of this page, for apps playing through PulseAudio",
+                "Use",
+            ),
+            (
+                "Loopback sink",
+                "A virtual sink with this layout, for a file in ~/.config/pipewire/pipewire.conf.d",
+                "Copy",
+            ),
+            (
+                "ALSA rule",
+                "Gives ALSA outputs this layout, for a file in ~/.config/wireplumber/wireplumber.conf.d",
+                "Copy",
+            ),
+        ];
+        for (title, subtitle, label) in rows {
+            let row = ActionRow::builder()
+                .title(gettext(title))
+                .subtitle(gettext(subtitle))
+                .subtitle_lines(2)
+                .build();
+            let button = Button::builder()
+                .label(gettext(label))
+                .valign(gtk::Align::Center)
+                .build();
+            row.add_suffix(&button);
+            group.add(&row);
+            buttons.push(button);
+        }
+
+        // The rule syntax is the one of WirePlumber's SPA-JSON config
+        let (_program, since) = PwCapability::WireplumberSettings.get_requirement();
+        if let Some(reason) = pw_config
+            .versions
+            .get_unsupported_reason(PwProgram::Wireplumber, since)
+            && let Some(button) = buttons.last()
+        {
+            button.set_sensitive(false);
+            button.set_tooltip_text(Some(&reason));
+        }
+
+        (group, buttons)
+    }
+
+    fn connect(self: &Rc<Self>, application: Rc<Application>, input_action: &'static str) {
+        let speaker_layout = Rc::downgrade(self);
+        self.diagram
+            .set_draw_func(move |_diagram, context, width, height| {
+                let Some(speaker_layout) = speaker_layout.upgrade() else {
+                    return;
+                };
+                if let Err(error) = speaker_layout.draw(context, width, height) {
+                    warn!(target: Self::LOG_TARGET, "Drawing speaker layout failed: {}", error);
+                }
+            });
+
+        let speaker_layout = Rc::downgrade(self);
+        self.layout_row.connect_selected_notify(move |layout_row| {
+            let Some(speaker_layout) = speaker_layout.upgrade() else {
+                return;
+            };
+            // Custom only shows that the channels match no layout
+            let Some(layout) = PwChannelLayout::ALL.get(layout_row.selected() as usize) else {
+                return;
+            };
+            if speaker_layout.map.borrow().get_layout() == Some(*layout) {
+                return;
+            }
+            speaker_layout
+                .map
+                .replace(PwChannelMap::from_layout(*layout));
+            speaker_layout.show_channels();
+        });
+
+        let [use_button, loopback_button, alsa_button] = &self.use_buttons[..] else {
+            return;
+        };
+
+        let speaker_layout = Rc::downgrade(self);
+        use_button.connect_clicked(move |button| {
+            let Some(speaker_layout) = speaker_layout.upgrade() else {
+                return;
+            };
+            let properties = speaker_layout.map.borrow().to_properties();
+            for (key, value) in properties {
+                let json_variant = json!({ &key: value }).to_string().to_variant();
+                if let Err(error) = button.activate_action(input_action, Some(&json_variant)) {
+                    warn!(target: Self::LOG_TARGET, "Setting {} failed: {}", key, error);
+                }
+            }
+
+            application
+                .pages
+                .borrow_mut()
+                .show_pending(application.clone());
+            Pages::show_enabled(&application);
+        });
+
+        let speaker_layout = Rc::downgrade(self);
+        loopback_button.connect_clicked(move |button| {
+            let Some(speaker_layout) = speaker_layout.upgrade() else {
+                return;
+            };
+            let map = speaker_layout.map.borrow();
+            let description = map.get_layout().map_or(gettext("Speakers"), |layout| {
+                gettext_f("Speakers {}", &[&gettext(layout.get_title())])
+            });
+            button
+                .clipboard()
+                .set_text(&map.to_loopback_sink(&description));
+        });
+
+        let speaker_layout = Rc::downgrade(self);
+        alsa_button.connect_clicked(move |button| {
+            if let Some(speaker_layout) = speaker_layout.upgrade() {
+                button
+                    .clipboard()
+                    .set_text(&speaker_layout.map.borrow().to_alsa_rule());
+            }
+        });
+    }
+
+    /// Builds a row per channel of the map.
+    fn show_channels(self: &Rc<Self>) {
+        for row in self.channel_rows.take() {
+            self.channels_group.remove(&row);
+        }
+
+        let titles: Vec<String> = PwSpeaker::ALL
+            .iter()
+            .map(|speaker| format!("{} ({})", gettext(speaker.title), speaker.name))
+            .collect();
+        let titles: Vec<&str> = titles.iter().map(|title| title.as_str()).collect();
+        let model = StringList::new(&titles);
+
+        let positions = self.map.borrow().positions.clone();
+        let mut rows = self.channel_rows.borrow_mut();
+        for (index, position) in positions.iter().enumerate() {
+            let selected = PwSpeaker::get_index(position)
+                .map_or(gtk::INVALID_LIST_POSITION, |selected| selected as u32);
+            let row = ComboRow::builder()
+                .title(gettext_f("Channel {}", &[&(index + 1).to_string()]))
+                .model(&model)
+                .selected(selected)
+                .build();
+            let test_button = Button::builder()
+                .icon_name("media-playback-start-symbolic")
+                .tooltip_text(gettext("Play a test signal on this channel"))
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .action_name(self.test_action)
+                .action_target(&(index as u32).to_variant())
+                .build();
+            row.add_suffix(&test_button);
+
+            let speaker_layout = Rc::downgrade(self);
+            row.connect_selected_notify(move |row| {
+                let Some(speaker_layout) = speaker_layout.upgrade() else {
+                    return;
+                };
+                let Some(speaker) = PwSpeaker::ALL.get(row.selected() as usize) else {
+                    return;
+                };
+                speaker_layout.map.borrow_mut().set(index, speaker.name);
+                speaker_layout.show_map();
+            });
+
+            self.channels_group.add(&row);
+            rows.push(row);
+        }
+        drop(rows);
+
+        self.show_map();
+    }
+
+    /// Shows a changed map in the diagram and the layout row, a wrong map can not be used.
+    fn show_map(&self) {
+        let (layout_index, invalid) = {
+            let map = self.map.borrow();
+            (Self::get_layout_index(&map), map.get_invalid())
+        };
+
+        // The layout row does not rebuild the channels when they already match
+        if self.layout_row.selected() != layout_index {
+            self.layout_row.set_selected(layout_index);
+        }
+
+        let description = invalid.join("\n");
+        self.channels_group
+            .set_description(match invalid.is_empty() {
+                true => None,
+                false => Some(&description),
+            });
+        if let Some(use_button) = self.use_buttons.first() {
+            use_button.set_sensitive(invalid.is_empty());
+        }
+
+        self.diagram.queue_draw();
+    }
+
+    fn draw(&self, context: &cairo::Context, width: i32, height: i32) -> Result<(), cairo::Error> {
+        const ACCENT: (f64, f64, f64) = (0.21, 0.52, 0.89);
+        const NEUTRAL: (f64, f64, f64) = (0.5, 0.5, 0.5);
+
+        let size = f64::from(width.min(height));
+        let (center_x, center_y) = (f64::from(width) / 2.0, f64::from(height) / 2.0);
+        let distance = size / 2.0 - Self::SPEAKER_RADIUS * 2.0;
+
+        // The room and the listener in the middle, facing up
+        context.set_source_rgba(NEUTRAL.0, NEUTRAL.1, NEUTRAL.2, 0.4);
+        context.set_line_width(2.0);
+        context.rectangle(
+            center_x - size / 2.0 + 1.0,
+            center_y - size / 2.0 + 1.0,
+            size - 2.0,
+            size - 2.0,
+        );
+        context.stroke()?;
+        context.arc(center_x, center_y, Self::SPEAKER_RADIUS / 2.0, 0.0, TAU);
+        context.fill()?;
+        context.move_to(center_x - 6.0, center_y - Self::SPEAKER_RADIUS / 2.0 - 2.0);
+        context.line_to(center_x, center_y - Self::SPEAKER_RADIUS);
+        context.line_to(center_x + 6.0, center_y - Self::SPEAKER_RADIUS / 2.0 - 2.0);
+        context.stroke()?;
+
+        context.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
+        context.set_font_size(11.0);
+
+        let map = self.map.borrow();
+        for (index, position) in map.positions.iter().enumerate() {
+            let Some((x, y)) = PwSpeaker::get(position).and_then(PwSpeaker::get_diagram_point)
+            else {
+                continue;
+            };
+            let (x, y) = (center_x + x * distance, center_y + y * distance);
+            let color = match self.highlighted.get() == Some(index) {
+                true => ACCENT,
+                false => NEUTRAL,
+            };
+
+            context.set_source_rgb(color.0, color.1, color.2);
+            context.arc(x, y, Self::SPEAKER_RADIUS, 0.0, TAU);
+            context.fill()?;
+
+            context.set_source_rgb(1.0, 1.0, 1.0);
+            Self::draw_centered_text(context, &(index + 1).to_string(), x, y)?;
+            context.set_source_rgb(color.0, color.1, color.2);
+            Self::draw_centered_text(context, position, x, y + Self::SPEAKER_RADIUS + 8.0)?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_centered_text(
+        context: &cairo::Context,
+        text: &str,
+        x: f64,
+        y: f64,
+    ) -> Result<(), cairo::Error> {
+        let extents = context.text_extents(text)?;
+        context.move_to(
+            x - extents.width() / 2.0 - extents.x_bearing(),
+            y - extents.height() / 2.0 - extents.y_bearing(),
+        );
+        context.show_text(text)
+    }
+}
@@ -0,0 +1,199 @@
+use super::speaker_layout::SpeakerLayout;
+use crate::{
+    application::{
+        Application,
+        pipewire::speaker_test::{PwSink, PwSpeakerTest, PwTestSignal},
+        window::apply_dialog::ApplyDialog,
+    },
+    i18n::gettext,
+};
+use anyhow::anyhow;
+use libadwaita::{
+    ActionRow, ComboRow, PreferencesGroup,
+    gio::{self, ActionEntry, SimpleActionGroup},
+    glib::{self, VariantTy},
+    gtk::{
+        self, Button, StringList,
+        prelude::{ButtonExt, WidgetExt},
+    },
+    prelude::{ActionRowExt, ComboRowExt, PreferencesGroupExt},
+};
+use log::{error, warn};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+/// Plays a test signal on the speakers of the layout, highlighting the one that plays.
+pub struct SpeakerTest {
+    group: PreferencesGroup,
+    sink_row: ComboRow,
+    signal_row: ComboRow,
+    play_all_button: Button,
+    /// The sinks after the default sink in the sink row
+    sinks: RefCell<Vec<PwSink>>,
+    is_playing: Cell<bool>,
+    speaker_layout: Rc<SpeakerLayout>,
+}
+impl SpeakerTest {
+    const LOG_TARGET: &str = "speaker-test";
+
+    pub fn new(application: Rc<Application>, speaker_layout: Rc<SpeakerLayout>) -> Rc<Self> {
+        let sink_row = ComboRow::builder()
+            .title(gettext("Output"))
+            .subtitle(gettext("Sink the test signal is played on"))
+            .build();
+        let signal_titles: Vec<String> = PwTestSignal::ALL
+            .iter()
+            .map(|signal| gettext(signal.get_title()))
+            .collect();
+        let signal_titles: Vec<&str> = signal_titles.iter().map(String::as_str).collect();
+        let signal_row = ComboRow::builder()
+            .title(gettext("Signal"))
+            .model(&StringList::new(&signal_titles))
+            .build();
+        let play_all_button = Button::builder()
+            .label(gettext("Play All"))
+            .valign(gtk::Align::Center)
+            .build();
+        let play_all_row = ActionRow::builder()
+            .title(gettext("All channels"))
+            .subtitle(gettext("One after the other, in channel order"))
+            .build();
+        play_all_row.add_suffix(&play_all_button);
+
+        let group = PreferencesGroup::builder()
+            .title(gettext("Speaker test"))
+            .description(gettext(
+                "Check that every channel plays on the speaker the layout shows",
+            ))
+            .build();
+        group.add(&sink_row);
+        group.add(&signal_row);
+        group.add(&play_all_row);
+
+        let speaker_test = Rc::new(Self {
+            group,
+            sink_row,
+            signal_row,
+            play_all_button,
+            sinks: RefCell::new(Vec::new()),
+            is_playing: Cell::new(false),
+            speaker_layout,
+        });
+
+        let play_all_test = speaker_test.clone();
+        speaker_test.play_all_button.connect_clicked(move |_| {
+            let channels = (0..play_all_test.speaker_layout.get_map().positions.len()).collect();
+            glib::spawn_future_local(play_all_test.clone().play(application.clone(), channels));
+        });
+
+        speaker_test
+    }
+
+    pub fn get_group(&self) -> &PreferencesGroup {
+        &self.group
+    }
+
+    /// Action with the index of the channel to test, for the buttons of the channel rows.
+    pub fn build_test_action(
+        self: &Rc<Self>,
+        application: Rc<Application>,
+        label: &'static str,
+    ) -> ActionEntry<SimpleActionGroup> {
+        let speaker_test = self.clone();
+        ActionEntry::builder(label)
+            .parameter_type(Some(VariantTy::UINT32))
+            .activate(move |_group, _action, parameter| {
+                let Some(channel) = parameter.and_then(|parameter| parameter.get::<u32>()) else {
+                    return;
+                };
+                glib::spawn_future_local(
+                    speaker_test
+                        .clone()
+                        .play(application.clone(), vec![channel as usize]),
+                );
+            })
+            .build()
+    }
+
+    /// Lists the sinks off the main loop, the selected sink stays selected if it is still there.
+    pub fn refresh_sinks(self: &Rc<Self>) {
+        let speaker_test = self.clone();
+        glib::spawn_future_local(async move {
+            let sinks = gio::spawn_blocking(PwSink::list)
+                .await
+                .unwrap_or_else(|_panic| Err(anyhow!("Listing sinks panicked")))
+                .unwrap_or_else(|error| {
+                    warn!(target: Self::LOG_TARGET, "Listing sinks failed:\n{:?}", error);
+                    Vec::new()
+                });
+            speaker_test.show_sinks(sinks);
+        });
+    }
+
+    fn show_sinks(&self, sinks: Vec<PwSink>) {
+        let selected = self.get_sink();
+
+        let default_title = gettext("Default output");
+        let mut titles = vec![default_title.as_str()];
+        titles.extend(sinks.iter().map(|sink| sink.description.as_str()));
+        self.sink_row.set_model(Some(&StringList::new(&titles)));
+
+        let position = sinks
+            .iter()
+            .position(|sink| Some(&sink.name) == selected.as_ref())
+            .map_or(0, |index| index + 1);
+        self.sink_row.set_selected(position as u32);
+        self.sinks.replace(sinks);
+    }
+
+    /// The selected sink, `None` for the default sink.
+    fn get_sink(&self) -> Option<String> {
+        let selected = self.sink_row.selected() as usize;
+
+        selected
+            .checked_sub(1)
+            .and_then(|index| self.sinks.borrow().get(index).map(|sink| sink.name.clone()))
+    }
+
+    async fn play(self: Rc<Self>, application: Rc<Application>, channels: Vec<usize>) {
+        if self.is_playing.replace(true) {
+            return;
+        }
+        self.play_all_button.set_sensitive(false);
+
+        let map = self.speaker_layout.get_map();
+        let signal = PwTestSignal::ALL
+            .get(self.signal_row.selected() as usize)
+            .copied()
+            .unwrap_or(PwTestSignal::PinkNoise);
+        let sink = self.get_sink();
+
+        for channel in channels {
+            self.speaker_layout.highlight(Some(channel));
+
+            let test = PwSpeakerTest::new(signal, &map, channel);
+            let sink = sink.clone();
+            // Blocks until played, off the main loop
+            let played = gio::spawn_blocking(move || test.play(sink.as_deref()))
+                .await
+                .unwrap_or_else(|_panic| Err(anyhow!("Playing the test signal panicked")));
+
+            if let Err(error) = played {
+                error!(target: Self::LOG_TARGET, "Testing channel {} failed:\n{:?}", channel + 1, error);
+                ApplyDialog::show_error(
+                    &application.window.window,
+                    &gettext("Could not play the test signal"),
+                    &error,
+                )
+                .await;
+                break;
+            }
+        }
+
+        self.speaker_layout.highlight(None);
+        self.play_all_button.set_sensitive(true);
+        self.is_playing.set(false);
+    }
+}
@@ -1,16 +1,26 @@
-use super::{NavPage, Page, PageState, PrefPage, PreferencesPageEntries};
+use super::{
+    NavPage, PageState, PrefPage, PreferencesPageEntries, registry::PageDescriptor,
+    speaker_layout::SpeakerLayout, speaker_test::SpeakerTest,
+};
 use crate::application::Application;
 use libadwaita::{
-    NavigationPage, PreferencesPage,
+    HeaderBar, NavigationPage, NavigationSplitView, PreferencesPage, PreferencesRow,
     gio::{SimpleActionGroup, prelude::ActionMapExtManual},
+    gtk::prelude::WidgetExt,
     prelude::PreferencesPageExt,
 };
 use std::{collections::HashMap, rc::Rc};
 
+/// Settings rows with a speaker layout editor for the channel map and a speaker test.
 pub struct SurroundPage {
+    pub descriptor: &'static PageDescriptor,
     pub nav_page: NavigationPage,
     pref_page: PreferencesPage,
     pref_groups: PreferencesPageEntries,
+    page_switch: Option<PreferencesRow>,
+    speaker_layout: Option<Rc<SpeakerLayout>>,
+    speaker_test: Option<Rc<SpeakerTest>>,
+    header: HeaderBar,
     state: PageState,
     title: String,
     actions: SimpleActionGroup,
@@ -19,20 +29,6 @@ impl NavPage for SurroundPage {
     const LABEL: &str = "surround-page";
     const LOG_TARGET: &str = Self::LABEL;
 
-    fn new() -> Self {
-        let title = String::from("Surround");
-        let (nav_page, pref_page, _header, state, actions) = Self::build_pref_page(&title);
-
-        return Self {
-            nav_page,
-            pref_page,
-            pref_groups: HashMap::with_capacity(0),
-            state,
-            title,
-            actions,
-        };
-    }
-
     fn is_init(&self) -> bool {
         self.state.get_init()
     }
@@ -46,13 +42,29 @@ impl NavPage for SurroundPage {
     }
 
     fn init(&mut self, application: Rc<Application>) {
-        self.on_onit(application);
+        self.on_init(application);
         self.state.set_init(true);
     }
 
     fn get_navpage(&self) -> &NavigationPage {
         &self.nav_page
     }
+
+    /// Lists the sinks again every time, outputs may have been plugged in since.
+    fn load_page(&mut self, application: Rc<Application>, view: &NavigationSplitView) {
+        if !self.is_init() {
+            self.init(application);
+        }
+        if let Some(speaker_test) = &self.speaker_test {
+            speaker_test.refresh_sinks();
+        }
+
+        let nav_page = self.get_navpage();
+        if nav_page.parent().is_some() {
+            return;
+        };
+        view.set_content(Some(nav_page));
+    }
 }
 impl PrefPage for SurroundPage {
     const ACTION_LABEL: &str = "surround";
@@ -60,33 +72,101 @@ impl PrefPage for SurroundPage {
     const INPUT_PAGE_ACTION_LABEL: &str = "surround.input";
     const PAGE_ENABLE_ACTION_LABEL: &str = "page-enable";
     const PAGE_ENABLE_PAGE_ACTION_LABEL: &str = "surround.page-enable";
+    const APPLY_ACTION_LABEL: &str = "apply";
+    const APPLY_PAGE_ACTION_LABEL: &str = "surround.apply";
 
     fn get_pref_groups(&self) -> &PreferencesPageEntries {
         &self.pref_groups
     }
 
+    fn get_page_switch(&self) -> Option<&PreferencesRow> {
+        self.page_switch.as_ref()
+    }
+
     fn set_state_enabled(&mut self, enabled: bool) {
         self.state.set_page_enabled(enabled);
     }
 }
 impl SurroundPage {
-    fn on_onit(&mut self, application: Rc<Application>) {
+    const TEST_CHANNEL_ACTION_LABEL: &str = "test-channel";
+    const TEST_CHANNEL_PAGE_ACTION_LABEL: &str = "surround.test-channel";
+
+    pub fn new(descriptor: &'static PageDescriptor) -> Self {
+        let title = descriptor.get_title();
+        let (nav_page, pref_page, header, _banner, state, actions) = Self::build_pref_page(&title);
+
+        Self {
+            descriptor,
+            nav_page,
+            pref_page,
+            pref_groups: HashMap::with_capacity(0),
+            page_switch: None,
+            speaker_layout: None,
+            speaker_test: None,
+            header,
+            state,
+            title,
+            actions,
+        }
+    }
+
+    fn on_init(&mut self, application: Rc<Application>) {
         let pipewire = application.pipewire.clone();
+        let Some(pw_config) = self
+            .descriptor
+            .config_file
+            .and_then(|config_file| pipewire.get_config(&config_file))
+        else {
+            return;
+        };
 
-        let input_action = self.build_input_action(&pipewire.surround);
-        let page_enabled_action = self.build_page_switch_action(application, Page::Surround);
+        let input_action = self.build_input_action(application.clone(), pw_config);
+        let apply_action = self.build_apply_action(application.clone(), pw_config);
+        let page_enabled_action =
+            self.build_page_switch_action(application.clone(), pw_config, self.descriptor.id);
         self.actions
-            .add_action_entries([input_action, page_enabled_action]);
+            .add_action_entries([input_action, apply_action, page_enabled_action]);
+        self.header.pack_end(&self.build_apply_button());
 
-        let enable_pref_group = self.build_page_switch();
+        let is_active = pw_config.borrow().is_dropin_active();
+        let (enable_pref_group, page_switch) = self.build_page_switch(is_active);
         self.pref_page.add(&enable_pref_group);
+        self.page_switch = Some(page_switch);
 
-        self.pref_groups = self.build_sections_from_default(&pipewire.surround.borrow());
-        for (group, _rows) in &self.pref_groups {
+        self.pref_groups = self.build_sections_from_default(&pw_config.borrow(), self.descriptor);
+        for group in self.pref_groups.keys() {
             self.pref_page.add(group);
         }
 
-        // TODO based on current settings
-        self.set_enabled(false);
+        // Stays usable while the page is off, using the layout turns the page on
+        let speaker_layout = SpeakerLayout::new(
+            application.clone(),
+            &pw_config.borrow(),
+            Self::INPUT_PAGE_ACTION_LABEL,
+            Self::TEST_CHANNEL_PAGE_ACTION_LABEL,
+        );
+        for group in speaker_layout.get_groups() {
+            self.pref_page.add(group);
+        }
+        let speaker_test = SpeakerTest::new(application.clone(), speaker_layout.clone());
+        self.actions
+            .add_action_entries([speaker_test
+                .build_test_action(application.clone(), Self::TEST_CHANNEL_ACTION_LABEL)]);
+        self.pref_page.add(speaker_test.get_group());
+        self.speaker_layout = Some(speaker_layout);
+        self.speaker_test = Some(speaker_test);
+
+        self.show_advanced(application.preferences.get_show_advanced());
+        self.set_enabled(is_active);
+    }
+
+    /// Shows values in the rows and the channel map in the speaker layout.
+    pub fn show_values(&self, values: &serde_json::Map<String, serde_json::Value>) {
+        self.set_row_values(values);
+        if let Some(speaker_layout) = &self.speaker_layout
+            && let Some(value) = values.get("audio.position")
+        {
+            speaker_layout.show_value(value);
+        }
     }
 }
@@ -0,0 +1,375 @@
+use crate::{
+    application::pipewire::schema::{PwProperty, PwPropertyType},
+    i18n::{gettext, gettext_f},
+};
+use libadwaita::{
+    ActionRow, ComboRow, EntryRow, ExpanderRow, PreferencesRow, SpinRow, SwitchRow,
+    gio::{ActionEntry, SimpleActionGroup, prelude::ActionMapExtManual, prelude::ListModelExt},
+    glib::{
+        self, VariantTy,
+        object::{Cast, CastNone},
+        variant::ToVariant,
+    },
+    gtk::{
+        self, Button, StringList,
+        prelude::{ButtonExt, EditableExt, WidgetExt},
+    },
+    prelude::{ActionRowExt, ComboRowExt, EntryRowExt, ExpanderRowExt, ObjectExt},
+};
+use log::error;
+use serde_json::{Map, Value, json};
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+/// Array and object rows keep their whole value and send all of it through the input action
+/// when an item or child changes. They have their own action group to set them from outside.
+pub struct ValueRows;
+impl ValueRows {
+    const LOG_TARGET: &str = "value-rows";
+    const SET_ACTION_LABEL: &str = "set";
+    const INPUT_ACTION_LABEL: &str = "input";
+
+    /// Shows a value in a row, e.g. a pending value loaded from a file.
+    pub fn set_row_value(row: &PreferencesRow, value: &Value) {
+        if let Some(switch_row) = row.downcast_ref::<SwitchRow>() {
+            if let Some(value) = value.as_bool() {
+                switch_row.set_active(value);
+            }
+        } else if let Some(spin_row) = row.downcast_ref::<SpinRow>() {
+            if let Some(value) = value.as_f64() {
+                spin_row.set_value(value);
+            }
+        } else if let Some(combo_row) = row.downcast_ref::<ComboRow>() {
+            let list = combo_row.model().and_downcast::<StringList>();
+            let position = list.and_then(|list| {
+                (0..list.n_items())
+                    .find(|position| list.string(*position).as_deref() == value.as_str())
+            });
+            if let Some(position) = position {
+                combo_row.set_selected(position);
+            }
+        } else if let Some(entry_row) = row.downcast_ref::<EntryRow>() {
+            match value.as_str() {
+                Some(value) => entry_row.set_text(value),
+                None => entry_row.set_text(&value.to_string()),
+            }
+        } else if row.downcast_ref::<ExpanderRow>().is_some() {
+            let action_label = format!(
+                "{}.{}",
+                Self::get_group_name(&row.widget_name()),
+                Self::SET_ACTION_LABEL
+            );
+            let _ = row.activate_action(&action_label, Some(&value.to_string().to_variant()));
+        }
+    }
+
+    /// Unique per key, so nested rows do not handle each other's actions.
+    fn get_group_name(key: &str) -> String {
+        format!("value-{}", key.replace('.', "-"))
+    }
+
+    fn add_set_action(
+        row: &ExpanderRow,
+        key: &str,
+        set: impl Fn(Value) + 'static,
+    ) -> SimpleActionGroup {
+        let action = ActionEntry::builder(Self::SET_ACTION_LABEL)
+            .parameter_type(Some(VariantTy::STRING))
+            .activate(move |_: &SimpleActionGroup, _, parameter| {
+                let string_value = parameter.unwrap().try_get::<String>().unwrap();
+                if let Ok(value) = serde_json::from_str(&string_value) {
+                    set(value);
+                }
+            })
+            .build();
+        let actions = SimpleActionGroup::new();
+        actions.add_action_entries([action]);
+
+        row.insert_action_group(&Self::get_group_name(key), Some(&actions));
+
+        actions
+    }
+
+    /// Sends the whole value of an array or object row to its input action.
+    fn send(row: &glib::WeakRef<ExpanderRow>, action_label: &str, key: &str, value: Value) {
+        let Some(row) = row.upgrade() else {
+            return;
+        };
+
+        let json_variant = json!({ key: value }).to_string().to_variant();
+        if let Err(error) = row.activate_action(action_label, Some(&json_variant)) {
+            error!(target: Self::LOG_TARGET, "Sending {} failed: {}", key, error);
+        }
+    }
+
+    fn to_label(value: &Value) -> String {
+        match value.as_str() {
+            Some(value) => value.to_string(),
+            None => value.to_string(),
+        }
+    }
+}
+
+/// Items of an array as rows with a remove button, and a row to add one.
+pub struct ArrayRow {
+    key: String,
+    row: glib::WeakRef<ExpanderRow>,
+    add_row: PreferencesRow,
+    item_property: PwProperty,
+    items: RefCell<Vec<Value>>,
+    item_rows: RefCell<Vec<ActionRow>>,
+    action_label: String,
+}
+impl ArrayRow {
+    pub fn build(
+        key: &str,
+        title: &str,
+        items: &[Value],
+        item_property: PwProperty,
+        action_label: String,
+    ) -> PreferencesRow {
+        let row = ExpanderRow::builder().name(key).title(title).build();
+        let add_row: PreferencesRow = match &item_property.kind {
+            PwPropertyType::Enum(values) => {
+                let list = StringList::new(&[]);
+                for value in values {
+                    list.append(value);
+                }
+                ComboRow::builder()
+                    .title(gettext("Add"))
+                    .model(&list)
+                    .build()
+                    .upcast()
+            }
+            _ => EntryRow::builder()
+                .title(gettext("Add"))
+                .show_apply_button(true)
+                .build()
+                .upcast(),
+        };
+
+        let array_row = Rc::new(Self {
+            key: key.to_owned(),
+            row: row.downgrade(),
+            add_row,
+            item_property,
+            items: RefCell::new(items.to_vec()),
+            item_rows: RefCell::new(Vec::new()),
+            action_label,
+        });
+        array_row.connect_add_row();
+        array_row.render();
+
+        // The set action owns the array row, which only has a weak reference back to its widget
+        let set_array_row = array_row.clone();
+        ValueRows::add_set_action(&row, key, move |value| {
+            if let Some(items) = value.as_array() {
+                set_array_row.items.replace(items.to_vec());
+                set_array_row.render();
+                set_array_row.send();
+            }
+        });
+
+        row.upcast()
+    }
+
+    fn connect_add_row(self: &Rc<Self>) {
+        if let Some(combo_row) = self.add_row.downcast_ref::<ComboRow>() {
+            let add_button = Self::build_button("list-add-symbolic", &gettext("Add"));
+            let array_row = Rc::downgrade(self);
+            let combo_row_clone = combo_row.clone();
+            add_button.connect_clicked(move |_button| {
+                let item = combo_row_clone
+                    .selected_item()
+                    .and_downcast::<gtk::StringObject>()
+                    .map(|item| json!(item.string().as_str()));
+                Self::with(&array_row, |array_row| {
+                    if let Some(item) = item {
+                        array_row.add(item);
+                    }
+                });
+            });
+            combo_row.add_suffix(&add_button);
+        } else if let Some(entry_row) = self.add_row.downcast_ref::<EntryRow>() {
+            let array_row = Rc::downgrade(self);
+            entry_row.connect_apply(move |entry_row| {
+                Self::with(&array_row, |array_row| {
+                    let text = entry_row.text();
+                    let item = array_row.parse_item(text.trim());
+                    match item {
+                        Ok(item) => {
+                            entry_row.remove_css_class("error");
+                            entry_row.set_tooltip_text(None);
+                            entry_row.set_text("");
+                            array_row.add(item);
+                        }
+                        Err(reason) => {
+                            entry_row.add_css_class("error");
+                            entry_row.set_tooltip_text(Some(&gettext_f("The item {}", &[&reason])));
+                        }
+                    }
+                });
+            });
+        }
+    }
+
+    fn parse_item(&self, text: &str) -> Result<Value, String> {
+        let item = match self.item_property.kind {
+            PwPropertyType::Int => text.parse::<i64>().map(|item| json!(item)).ok(),
+            PwPropertyType::Float => text.parse::<f64>().map(|item| json!(item)).ok(),
+            PwPropertyType::Bool => text.parse::<bool>().map(|item| json!(item)).ok(),
+            _ => (!text.is_empty()).then(|| json!(text)),
+        };
+        let item = item.ok_or("should be a valid value".to_string())?;
+        self.item_property.validate(&item)?;
+
+        Ok(item)
+    }
+
+    fn add(self: &Rc<Self>, item: Value) {
+        self.items.borrow_mut().push(item);
+        self.render();
+        self.send();
+    }
+
+    fn remove(self: &Rc<Self>, index: usize) {
+        self.items.borrow_mut().remove(index);
+        self.render();
+        self.send();
+    }
+
+    fn send(&self) {
+        let items = self.items.borrow().clone();
+        ValueRows::send(&self.row, &self.action_label, &self.key, json!(items));
+    }
+
+    /// Rebuilds the item rows, the add row stays last.
+    fn render(self: &Rc<Self>) {
+        let Some(row) = self.row.upgrade() else {
+            return;
+        };
+        for item_row in self.item_rows.take() {
+            row.remove(&item_row);
+        }
+        if self.add_row.parent().is_some() {
+            row.remove(&self.add_row);
+        }
+
+        let items = self.items.borrow();
+        for (index, item) in items.iter().enumerate() {
+            let item_row = ActionRow::builder()
+                .title(glib::markup_escape_text(&ValueRows::to_label(item)))
+                .build();
+            let remove_button = Self::build_button("list-remove-symbolic", &gettext("Remove"));
+            let array_row = Rc::downgrade(self);
+            remove_button.connect_clicked(move |_button| {
+                Self::with(&array_row, |array_row| array_row.remove(index));
+            });
+            item_row.add_suffix(&remove_button);
+
+            row.add_row(&item_row);
+            self.item_rows.borrow_mut().push(item_row);
+        }
+        row.add_row(&self.add_row);
+
+        let summary: Vec<String> = items.iter().map(ValueRows::to_label).collect();
+        row.set_subtitle(&glib::markup_escape_text(&summary.join(", ")));
+    }
+
+    fn build_button(icon_name: &str, tooltip: &str) -> Button {
+        Button::builder()
+            .icon_name(icon_name)
+            .tooltip_text(tooltip)
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build()
+    }
+
+    fn with(array_row: &Weak<Self>, action: impl FnOnce(&Rc<Self>)) {
+        if let Some(array_row) = array_row.upgrade() {
+            action(&array_row);
+        }
+    }
+}
+
+/// Properties of an object as child rows, built by the page like any other row.
+pub struct ObjectRow {
+    key: String,
+    row: glib::WeakRef<ExpanderRow>,
+    values: RefCell<Map<String, Value>>,
+    children: RefCell<Vec<PreferencesRow>>,
+    action_label: String,
+}
+impl ObjectRow {
+    /// `build_child` builds the row of a property from its key, value and input action label.
+    pub fn build(
+        key: &str,
+        title: &str,
+        values: &Map<String, Value>,
+        action_label: String,
+        build_child: impl Fn(&str, &Value, String) -> Option<PreferencesRow>,
+    ) -> PreferencesRow {
+        let row = ExpanderRow::builder().name(key).title(title).build();
+        let object_row = Rc::new(Self {
+            key: key.to_owned(),
+            row: row.downgrade(),
+            values: RefCell::new(values.clone()),
+            children: RefCell::new(Vec::new()),
+            action_label,
+        });
+
+        for (child_key, child_value) in values {
+            let child = build_child(child_key, child_value, object_row.get_input_action_label());
+            if let Some(child) = child {
+                row.add_row(&child);
+                object_row.children.borrow_mut().push(child);
+            }
+        }
+
+        let input_object_row = Rc::downgrade(&object_row);
+        let input_action = ActionEntry::builder(ValueRows::INPUT_ACTION_LABEL)
+            .parameter_type(Some(VariantTy::STRING))
+            .activate(move |_: &SimpleActionGroup, _, parameter| {
+                let string_value = parameter.unwrap().try_get::<String>().unwrap();
+                let Ok(Value::Object(child)) = serde_json::from_str(&string_value) else {
+                    return;
+                };
+                if let Some(object_row) = input_object_row.upgrade() {
+                    object_row.values.borrow_mut().extend(child);
+                    object_row.send();
+                }
+            })
+            .build();
+
+        // The set action owns the object row, which only has a weak reference back to its widget
+        let actions = ValueRows::add_set_action(&row, key, move |value| {
+            let Some(values) = value.as_object() else {
+                return;
+            };
+            for child in object_row.children.borrow().iter() {
+                if let Some(value) = values.get(child.widget_name().as_str()) {
+                    ValueRows::set_row_value(child, value);
+                }
+            }
+        });
+        actions.add_action_entries([input_action]);
+
+        row.upcast()
+    }
+
+    /// Action the child rows send their changes to.
+    fn get_input_action_label(&self) -> String {
+        format!(
+            "{}.{}",
+            ValueRows::get_group_name(&self.key),
+            ValueRows::INPUT_ACTION_LABEL
+        )
+    }
+
+    fn send(&self) {
+        let values = self.values.borrow().clone();
+        ValueRows::send(&self.row, &self.action_label, &self.key, json!(values));
+    }
+}
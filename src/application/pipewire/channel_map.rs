@@ -0,0 +1,356 @@
+use super::spa_json::SpaJson;
+use crate::i18n::gettext_f;
+use serde_json::{Map, Value, json};
+use std::collections::HashSet;
+
+/// Where a speaker stands around the listener.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PwSpeakerPlace {
+    /// At ear height, degrees clockwise from the front
+    Ear(f64),
+    /// Above the listener
+    Top(f64),
+    /// Subwoofers, on the floor
+    Low(f64),
+    /// No fixed place, e.g. `AUX0`
+    None,
+}
+
+/// A channel position of `audio.position`.
+#[derive(Debug)]
+pub struct PwSpeaker {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub place: PwSpeakerPlace,
+}
+impl PwSpeaker {
+    /// The positions PipeWire knows, in the order of the schema.
+    pub const ALL: [PwSpeaker; 44] = [
+        Self::new("MONO", "Mono", PwSpeakerPlace::Ear(0.0)),
+        Self::new("FL", "Front Left", PwSpeakerPlace::Ear(-30.0)),
+        Self::new("FR", "Front Right", PwSpeakerPlace::Ear(30.0)),
+        Self::new("FC", "Front Center", PwSpeakerPlace::Ear(0.0)),
+        Self::new("LFE", "Subwoofer", PwSpeakerPlace::Low(20.0)),
+        Self::new("SL", "Side Left", PwSpeakerPlace::Ear(-90.0)),
+        Self::new("SR", "Side Right", PwSpeakerPlace::Ear(90.0)),
+        Self::new("FLC", "Front Left Center", PwSpeakerPlace::Ear(-15.0)),
+        Self::new("FRC", "Front Right Center", PwSpeakerPlace::Ear(15.0)),
+        Self::new("RC", "Rear Center", PwSpeakerPlace::Ear(180.0)),
+        Self::new("RL", "Rear Left", PwSpeakerPlace::Ear(-150.0)),
+        Self::new("RR", "Rear Right", PwSpeakerPlace::Ear(150.0)),
+        Self::new("TC", "Top Center", PwSpeakerPlace::Top(180.0)),
+        Self::new("TFL", "Top Front Left", PwSpeakerPlace::Top(-30.0)),
+        Self::new("TFC", "Top Front Center", PwSpeakerPlace::Top(0.0)),
+        Self::new("TFR", "Top Front Right", PwSpeakerPlace::Top(30.0)),
+        Self::new("TRL", "Top Rear Left", PwSpeakerPlace::Top(-150.0)),
+        Self::new("TRC", "Top Rear Center", PwSpeakerPlace::Top(180.0)),
+        Self::new("TRR", "Top Rear Right", PwSpeakerPlace::Top(150.0)),
+        Self::new("RLC", "Rear Left Center", PwSpeakerPlace::Ear(-165.0)),
+        Self::new("RRC", "Rear Right Center", PwSpeakerPlace::Ear(165.0)),
+        Self::new("FLW", "Front Left Wide", PwSpeakerPlace::Ear(-60.0)),
+        Self::new("FRW", "Front Right Wide", PwSpeakerPlace::Ear(60.0)),
+        Self::new("LFE2", "Second Subwoofer", PwSpeakerPlace::Low(-20.0)),
+        Self::new("FLH", "Front Left High", PwSpeakerPlace::Top(-45.0)),
+        Self::new("FCH", "Front Center High", PwSpeakerPlace::Top(0.0)),
+        Self::new("FRH", "Front Right High", PwSpeakerPlace::Top(45.0)),
+        Self::new("TFLC", "Top Front Left Center", PwSpeakerPlace::Top(-15.0)),
+        Self::new("TFRC", "Top Front Right Center", PwSpeakerPlace::Top(15.0)),
+        Self::new("TSL", "Top Side Left", PwSpeakerPlace::Top(-90.0)),
+        Self::new("TSR", "Top Side Right", PwSpeakerPlace::Top(90.0)),
+        Self::new("LLFE", "Left Subwoofer", PwSpeakerPlace::Low(-45.0)),
+        Self::new("RLFE", "Right Subwoofer", PwSpeakerPlace::Low(45.0)),
+        Self::new("BC", "Bottom Center", PwSpeakerPlace::Low(0.0)),
+        Self::new("BLC", "Bottom Left Center", PwSpeakerPlace::Low(-15.0)),
+        Self::new("BRC", "Bottom Right Center", PwSpeakerPlace::Low(15.0)),
+        Self::new("AUX0", "Auxiliary 0", PwSpeakerPlace::None),
+        Self::new("AUX1", "Auxiliary 1", PwSpeakerPlace::None),
+        Self::new("AUX2", "Auxiliary 2", PwSpeakerPlace::None),
+        Self::new("AUX3", "Auxiliary 3", PwSpeakerPlace::None),
+        Self::new("AUX4", "Auxiliary 4", PwSpeakerPlace::None),
+        Self::new("AUX5", "Auxiliary 5", PwSpeakerPlace::None),
+        Self::new("AUX6", "Auxiliary 6", PwSpeakerPlace::None),
+        Self::new("AUX7", "Auxiliary 7", PwSpeakerPlace::None),
+    ];
+
+    const fn new(name: &'static str, title: &'static str, place: PwSpeakerPlace) -> Self {
+        Self { name, title, place }
+    }
+
+    pub fn get(name: &str) -> Option<&'static Self> {
+        Self::ALL.iter().find(|speaker| speaker.name == name)
+    }
+
+    pub fn get_index(name: &str) -> Option<usize> {
+        Self::ALL.iter().position(|speaker| speaker.name == name)
+    }
+
+    /// The place in a room diagram from -1 to 1, the front is at the top and the
+    /// listener in the middle. Top speakers are drawn closer to the listener.
+    pub fn get_diagram_point(&self) -> Option<(f64, f64)> {
+        let (angle, distance): (f64, f64) = match self.place {
+            PwSpeakerPlace::Ear(angle) => (angle, 1.0),
+            PwSpeakerPlace::Top(angle) => (angle, 0.5),
+            PwSpeakerPlace::Low(angle) => (angle, 0.75),
+            PwSpeakerPlace::None => return None,
+        };
+        let radians = angle.to_radians();
+
+        Some((radians.sin() * distance, -radians.cos() * distance))
+    }
+}
+
+/// The common speaker setups.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PwChannelLayout {
+    Stereo,
+    Stereo21,
+    Quad,
+    Surround51,
+    Surround71,
+}
+impl PwChannelLayout {
+    pub const ALL: [PwChannelLayout; 5] = [
+        Self::Stereo,
+        Self::Stereo21,
+        Self::Quad,
+        Self::Surround51,
+        Self::Surround71,
+    ];
+
+    pub fn get_title(&self) -> &'static str {
+        match self {
+            Self::Stereo => "2.0 Stereo",
+            Self::Stereo21 => "2.1 Stereo",
+            Self::Quad => "4.0 Quadraphonic",
+            Self::Surround51 => "5.1 Surround",
+            Self::Surround71 => "7.1 Surround",
+        }
+    }
+
+    /// The channel order PipeWire uses for the layout.
+    pub fn get_positions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Stereo => &["FL", "FR"],
+            Self::Stereo21 => &["FL", "FR", "LFE"],
+            Self::Quad => &["FL", "FR", "RL", "RR"],
+            Self::Surround51 => &["FL", "FR", "FC", "LFE", "RL", "RR"],
+            Self::Surround71 => &["FL", "FR", "FC", "LFE", "RL", "RR", "SL", "SR"],
+        }
+    }
+
+    /// The layout with exactly these positions in this order.
+    pub fn from_positions(positions: &[String]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|layout| layout.get_positions() == positions)
+    }
+}
+
+/// Which position each channel index plays on, e.g. `[ FL FR ]`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PwChannelMap {
+    pub positions: Vec<String>,
+}
+impl PwChannelMap {
+    pub fn from_layout(layout: PwChannelLayout) -> Self {
+        Self {
+            positions: layout
+                .get_positions()
+                .iter()
+                .map(|position| position.to_string())
+                .collect(),
+        }
+    }
+
+    /// Reads an array of positions, or a string of them as older configs have,
+    /// e.g. `FL,FR` or `[ FL FR ]`.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let positions: Vec<String> = match value {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| item.as_str().map(|item| item.to_string()))
+                .collect::<Option<_>>()?,
+            Value::String(string) => string
+                .split(|char: char| !char.is_ascii_alphanumeric())
+                .filter(|position| !position.is_empty())
+                .map(|position| position.to_uppercase())
+                .collect(),
+            _ => return None,
+        };
+
+        match positions.is_empty() {
+            true => None,
+            false => Some(Self { positions }),
+        }
+    }
+
+    pub fn get_layout(&self) -> Option<PwChannelLayout> {
+        PwChannelLayout::from_positions(&self.positions)
+    }
+
+    pub fn set(&mut self, index: usize, position: &str) {
+        if let Some(channel) = self.positions.get_mut(index) {
+            *channel = position.to_string();
+        }
+    }
+
+    /// Why the map is wrong, e.g. `Channel 3: FL is already channel 1`. Channels are
+    /// counted from 1 as in the rows.
+    pub fn get_invalid(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut invalid = Vec::new();
+
+        for (index, position) in self.positions.iter().enumerate() {
+            if PwSpeaker::get(position).is_none() {
+                invalid.push(gettext_f(
+                    "Channel {}: {} is no position",
+                    &[&(index + 1).to_string(), position],
+                ));
+            } else if !seen.insert(position) {
+                let first = self
+                    .positions
+                    .iter()
+                    .position(|other| other == position)
+                    .unwrap_or_default();
+                invalid.push(gettext_f(
+                    "Channel {}: {} is already channel {}",
+                    &[&(index + 1).to_string(), position, &(first + 1).to_string()],
+                ));
+            }
+        }
+
+        invalid
+    }
+
+    /// `audio.channels` and `audio.position`, e.g. for the stream properties.
+    pub fn to_properties(&self) -> Map<String, Value> {
+        let mut properties = Map::new();
+        properties.insert(
+            String::from("audio.channels"),
+            Value::from(self.positions.len()),
+        );
+        properties.insert(
+            String::from("audio.position"),
+            Value::from(self.positions.clone()),
+        );
+
+        properties
+    }
+
+    /// A PipeWire drop-in with a virtual sink of this layout that plays on the default sink.
+    pub fn to_loopback_sink(&self, description: &str) -> String {
+        let properties = self.to_properties();
+        let mut capture = properties.clone();
+        capture.insert(String::from("node.name"), json!("speaker-layout"));
+        capture.insert(String::from("media.class"), json!("Audio/Sink"));
+        let mut playback = properties;
+        playback.insert(String::from("node.name"), json!("speaker-layout.output"));
+        playback.insert(String::from("node.passive"), json!(true));
+
+        let mut dropin = Map::new();
+        dropin.insert(
+            String::from("context.modules"),
+            json!([{
+                "name": "libpipewire-module-loopback",
+                "args": {
+                    "node.description": description,
+                    "capture.props": capture,
+                    "playback.props": playback,
+                },
+            }]),
+        );
+
+        SpaJson::to_string(&dropin)
+    }
+
+    /// A WirePlumber drop-in that gives every ALSA output this layout.
+    pub fn to_alsa_rule(&self) -> String {
+        let mut dropin = Map::new();
+        dropin.insert(
+            String::from("monitor.alsa.rules"),
+            json!([{
+                "matches": [{ "node.name": "~alsa_output.*" }],
+                "actions": { "update-props": self.to_properties() },
+            }]),
+        );
+
+        SpaJson::to_string(&dropin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PwChannelLayout, PwChannelMap, PwSpeaker};
+    use crate::application::pipewire::{schema::PwSchema, spa_json::SpaJson};
+    use serde_json::{Value, json};
+
+    #[test]
+    fn it_should_know_every_schema_position() {
+        let schema = PwSchema::bundled();
+        let names: Vec<Value> = PwSpeaker::ALL
+            .iter()
+            .map(|speaker| json!(speaker.name))
+            .collect();
+
+        assert_eq!(
+            schema.validate("audio.position", &Value::from(names)),
+            Ok(())
+        );
+        for speaker in &PwSpeaker::ALL {
+            if let Some((x, y)) = speaker.get_diagram_point() {
+                assert!((-1.0..=1.0).contains(&x) && (-1.0..=1.0).contains(&y));
+            }
+        }
+        let (x, y) = PwSpeaker::get("FR").unwrap().get_diagram_point().unwrap();
+        assert!(x > 0.0 && y < 0.0);
+    }
+
+    #[test]
+    fn it_should_read_and_check_channel_maps() {
+        let map = PwChannelMap::from_value(&json!("[ FL FR FC LFE RL RR ]")).unwrap();
+        assert_eq!(map.get_layout(), Some(PwChannelLayout::Surround51));
+        assert_eq!(
+            PwChannelMap::from_value(&json!("fl,fr")),
+            Some(PwChannelMap::from_layout(PwChannelLayout::Stereo))
+        );
+        assert_eq!(PwChannelMap::from_value(&json!([])), None);
+        assert_eq!(PwChannelMap::from_value(&json!(2)), None);
+
+        let mut map = PwChannelMap::from_layout(PwChannelLayout::Quad);
+        map.set(2, "FL");
+        map.set(3, "XX");
+        assert_eq!(map.get_layout(), None);
+        assert_eq!(
+            map.get_invalid(),
+            vec![
+                "Channel 3: FL is already channel 1",
+                "Channel 4: XX is no position"
+            ]
+        );
+        assert!(
+            PwChannelMap::from_layout(PwChannelLayout::Surround71)
+                .get_invalid()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn it_should_write_properties_and_dropins() {
+        let map = PwChannelMap::from_layout(PwChannelLayout::Stereo21);
+        let properties = map.to_properties();
+        assert_eq!(properties["audio.channels"], json!(3));
+        assert_eq!(properties["audio.position"], json!(["FL", "FR", "LFE"]));
+
+        let sink = SpaJson::parse(&map.to_loopback_sink("Speakers")).unwrap();
+        let args = &sink["context.modules"][0]["args"];
+        assert_eq!(args["node.description"], json!("Speakers"));
+        assert_eq!(args["capture.props"]["media.class"], json!("Audio/Sink"));
+        assert_eq!(args["playback.props"]["audio.channels"], json!(3));
+
+        let rule = SpaJson::parse(&map.to_alsa_rule()).unwrap();
+        assert_eq!(
+            rule["monitor.alsa.rules"][0]["actions"]["update-props"]["audio.position"],
+            json!(["FL", "FR", "LFE"])
+        );
+    }
+}
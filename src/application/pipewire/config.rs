@@ -1,68 +1,432 @@
-use anyhow::{Context, Result};
+use super::{
+    dropin::PwDropIn,
+    error::PwError,
+    host::PwHost,
+    schema::PwSchema,
+    spa_json::SpaJson,
+    version::{PwProgram, PwVersions},
+};
+use anyhow::{Context, Result, bail};
 use log::debug;
 use regex::Regex;
 use serde_json::{Map, Value, json};
-use std::{cell::RefCell, collections::HashMap, process::Command, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::LazyLock,
+};
 
 #[allow(dead_code)] // This can be None to get all the properties
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum PwPulseSectionSub {
     Channelmix,
     None,
 }
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum PwPulseSection {
     StreamProperties(PwPulseSectionSub),
 }
 
+#[allow(dead_code)] // This can be None to get all the properties
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PwPipewireSectionSub {
+    Clock,
+    None,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PwPipewireSection {
+    ContextProperties(PwPipewireSectionSub),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PwWireplumberSection {
+    Settings,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum PwConfigFile {
+    Pipewire(PwPipewireSection),
     PipewirePulse(PwPulseSection),
+    Wireplumber(PwWireplumberSection),
+}
+
+/// What the comment after a default value says it can be, e.g. `# none, simple` or `# 0-14`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PwOptions {
+    /// Alternatives, including the default value
+    pub values: Option<Vec<String>>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub unit: Option<String>,
 }
 
-pub type MapWithOptions = HashMap<String, (Value, Option<Vec<String>>)>;
+pub type MapWithOptions = HashMap<String, (Value, Option<PwOptions>)>;
+type OptionsMap = HashMap<String, PwOptions>;
+
+/// A pending value of a setting that was also changed outside of the app.
+#[derive(Debug, PartialEq)]
+pub struct PwConflict {
+    pub key: String,
+    pub pending: Value,
+    /// `None` when the setting was removed
+    pub external: Option<Value>,
+}
+
+/// What an apply changed, so it can be rolled back.
+pub struct PwApplied {
+    pub config_file: PwConfigFile,
+    pub path: PathBuf,
+    previous: Option<String>,
+    values: Map<String, Value>,
+    /// Become pending again on rollback
+    pending: Map<String, Value>,
+}
+impl PwApplied {
+    /// Short description of the applied values for the history.
+    pub fn get_summary(&self) -> String {
+        let (file_name, section_name, _subsection_name) =
+            PwConfig::get_config_file_and_sections(&self.config_file);
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|(key, value)| format!("{} = {}", key, value))
+            .collect();
+
+        format!("{} {}: {}", file_name, section_name, values.join(", "))
+    }
+}
 
 pub struct PwConfig {
+    pub config_file: PwConfigFile,
     pub current: Map<String, Value>,
     pub default: MapWithOptions,
+    /// Comments of the upstream default config, by key
+    pub descriptions: HashMap<String, String>,
     pub new: Map<String, Value>,
     pub paths: Map<String, Value>,
+    /// Settings newer than the installed version are invalid, their rows are off
+    pub versions: PwVersions,
 }
 impl PwConfig {
     const LOG_TARGET: &str = "PwConfig";
+    const KNOWN_FILES: [&str; 6] = [
+        "pipewire.conf",
+        "pipewire-pulse.conf",
+        "client.conf",
+        "jack.conf",
+        "minimal.conf",
+        "wireplumber.conf",
+    ];
+    /// `-r` also lists the drop-ins, so the values are the merged ones
+    const LIST_FLAGS: &str = "-LNr";
 
-    pub fn new(config_file: PwConfigFile) -> Result<Rc<RefCell<Self>>> {
+    pub fn new(config_file: PwConfigFile, versions: PwVersions) -> Result<Rc<RefCell<Self>>> {
         let (file_name, section_name, subsection_name) =
             Self::get_config_file_and_sections(&config_file);
 
         let paths = Self::get_paths(file_name)?;
         let current = Self::get_current(file_name, section_name, subsection_name)?;
-        let default = Self::get_default(file_name, section_name, subsection_name)?;
+        let (default, descriptions) = Self::get_default(file_name, section_name, subsection_name)?;
         let new = json!({}).as_object().unwrap().to_owned();
 
         Ok(Rc::new(RefCell::new(Self {
+            config_file,
             current,
             default,
+            descriptions,
             new,
             paths,
+            versions,
         })))
     }
 
+    /// What a property does, from the bundled schema or else the upstream config's comments.
+    pub fn get_description(&self, key: &str) -> Option<&str> {
+        PwSchema::bundled()
+            .get(key)
+            .and_then(|property| property.description.as_deref())
+            .or_else(|| {
+                self.descriptions
+                    .get(key)
+                    .map(|description| description.as_str())
+            })
+    }
+
+    /// File and section, e.g. `pipewire.conf context.properties default.clock`.
+    pub fn get_name(&self) -> String {
+        let (file_name, section_name, subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+
+        match subsection_name {
+            Some(subsection_name) => format!("{} {} {}", file_name, section_name, subsection_name),
+            None => format!("{} {}", file_name, section_name),
+        }
+    }
+
+    /// `key: reason` for every pending value that does not fit the schema
+    /// or the installed version.
+    pub fn get_invalid(&self) -> Vec<String> {
+        let mut invalid = PwSchema::bundled().get_invalid(&self.new);
+        for key in self.new.keys() {
+            if let Some(reason) = self.get_unsupported_reason(key) {
+                invalid.push(format!("{}: {}", key, reason));
+            }
+        }
+        invalid.sort();
+
+        invalid
+    }
+
+    /// Why the installed version does not have this property, e.g. `Needs PipeWire 1.0.0 or newer`.
+    pub fn get_unsupported_reason(&self, key: &str) -> Option<String> {
+        let since = PwSchema::bundled().get(key)?.since?;
+        let (file_name, _section_name, _subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+
+        self.versions
+            .get_unsupported_reason(PwProgram::from_file(file_name), since)
+    }
+
+    /// Writes the pending values to this config's drop-in and reloads the current values.
+    pub fn apply(&mut self) -> Result<PwApplied> {
+        let invalid = self.get_invalid();
+        if !invalid.is_empty() {
+            bail!("Invalid settings:\n{}", invalid.join("\n"));
+        }
+
+        let (file_name, section_name, subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+        let path = PwDropIn::get_path(file_name, section_name, subsection_name);
+        let previous = PwDropIn::read_raw(&path)?;
+
+        let mut values = PwDropIn::read(&path, section_name)?;
+        values.extend(self.new.clone());
+        PwDropIn::write(&path, section_name, &values)?;
+
+        self.current = Self::get_current(file_name, section_name, subsection_name)?;
+        let pending = std::mem::take(&mut self.new);
+        let applied = PwApplied {
+            config_file: self.config_file,
+            path,
+            previous,
+            values: pending.clone(),
+            pending,
+        };
+
+        debug!(target: Self::LOG_TARGET, "{} {} applied:\n{:#?}", file_name, section_name, values);
+
+        Ok(applied)
+    }
+
+    /// Replaces the drop-in with exactly these values, e.g. from a preset.
+    /// Pending values are kept, `None` when the drop-in already has these values.
+    pub fn activate(&mut self, values: Map<String, Value>) -> Result<Option<PwApplied>> {
+        let invalid = PwSchema::bundled().get_invalid(&values);
+        if !invalid.is_empty() {
+            bail!("Invalid settings:\n{}", invalid.join("\n"));
+        }
+
+        let (file_name, section_name, subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+        let path = PwDropIn::get_path(file_name, section_name, subsection_name);
+        let previous = PwDropIn::read_raw(&path)?;
+
+        if previous.is_some() && PwDropIn::read(&path, section_name)? == values {
+            return Ok(None);
+        }
+        if previous.is_none() && values.is_empty() {
+            return Ok(None);
+        }
+
+        match values.is_empty() {
+            true => PwDropIn::restore(&path, None)?,
+            false => PwDropIn::write(&path, section_name, &values)?,
+        }
+        self.current = Self::get_current(file_name, section_name, subsection_name)?;
+
+        debug!(target: Self::LOG_TARGET, "{} {} activated:\n{:#?}", file_name, section_name, values);
+
+        Ok(Some(PwApplied {
+            config_file: self.config_file,
+            path,
+            previous,
+            values,
+            pending: Map::new(),
+        }))
+    }
+
+    /// Puts the values of this config's section from a loaded file into the pending values.
+    /// Returns the keys that were taken.
+    pub fn load(&mut self, file_name: &str, sections: &Map<String, Value>) -> Vec<String> {
+        let (config_file_name, section_name, subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+        if file_name != config_file_name {
+            return Vec::new();
+        }
+        let Some(Value::Object(values)) = sections.get(section_name) else {
+            return Vec::new();
+        };
+
+        let mut loaded = Vec::new();
+        for (key, value) in values {
+            if let Some(subsection) = subsection_name
+                && !key.starts_with(&format!("{}.", subsection))
+            {
+                continue;
+            }
+
+            self.new.insert(key.to_owned(), value.to_owned());
+            loaded.push(key.to_owned());
+        }
+
+        debug!(target: Self::LOG_TARGET, "{} {} loaded:\n{:?}", file_name, section_name, loaded);
+
+        loaded
+    }
+
+    /// Config file a loaded file belongs to, from the dir of a drop-in,
+    /// its file name or else the sections in it.
+    pub fn detect_file_name(path: &Path, sections: &Map<String, Value>) -> Option<String> {
+        let dir_name = path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .map(|dir_name| dir_name.to_string_lossy());
+        if let Some(dir_name) = dir_name
+            && let Some(file_name) = dir_name.strip_suffix(".d")
+            && file_name.ends_with(".conf")
+        {
+            return Some(file_name.to_string());
+        }
+
+        let file_name = path.file_name()?.to_string_lossy();
+        if Self::KNOWN_FILES.contains(&file_name.as_ref()) {
+            return Some(file_name.into_owned());
+        }
+
+        let has_section = |prefix: &str| sections.keys().any(|key| key.starts_with(prefix));
+        if has_section("wireplumber.") {
+            Some(String::from("wireplumber.conf"))
+        } else if has_section("pulse.") || sections.contains_key("stream.properties") {
+            Some(String::from("pipewire-pulse.conf"))
+        } else if sections.contains_key("context.properties") {
+            Some(String::from("pipewire.conf"))
+        } else {
+            None
+        }
+    }
+
+    /// Values in this config's drop-in, what was applied before.
+    pub fn get_applied(&self) -> Result<Map<String, Value>> {
+        let (file_name, section_name, subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+        let path = PwDropIn::get_path(file_name, section_name, subsection_name);
+
+        PwDropIn::read(&path, section_name)
+    }
+
+    /// Whether this config's drop-in is there, the switch of its page is on then.
+    pub fn is_dropin_active(&self) -> bool {
+        self.get_dropin_path().exists()
+    }
+
+    /// Moves the drop-in aside so its values are no longer read, `enable` restores them.
+    /// `None` when there is no drop-in.
+    pub fn disable(&mut self) -> Result<Option<PwApplied>> {
+        let path = self.get_dropin_path();
+        let Some(previous) = PwDropIn::read_raw(&path)? else {
+            return Ok(None);
+        };
+
+        PwDropIn::disable(&path)?;
+        self.reload()?;
+
+        debug!(target: Self::LOG_TARGET, "{} disabled", self.get_name());
+
+        Ok(Some(PwApplied {
+            config_file: self.config_file,
+            path,
+            previous: Some(previous),
+            values: Map::new(),
+            pending: Map::new(),
+        }))
+    }
+
+    /// Restores the drop-in values from before `disable`, `None` when there are none.
+    pub fn enable(&mut self) -> Result<Option<PwApplied>> {
+        let path = self.get_dropin_path();
+        if path.exists() || !PwDropIn::enable(&path)? {
+            return Ok(None);
+        }
+
+        let (_file_name, section_name, _subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+        let values = PwDropIn::read(&path, section_name)?;
+        self.reload()?;
+
+        debug!(target: Self::LOG_TARGET, "{} enabled", self.get_name());
+
+        Ok(Some(PwApplied {
+            config_file: self.config_file,
+            path,
+            previous: None,
+            values,
+            pending: Map::new(),
+        }))
+    }
+
+    fn get_dropin_path(&self) -> PathBuf {
+        let (file_name, section_name, subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+
+        PwDropIn::get_path(file_name, section_name, subsection_name)
+    }
+
+    /// Restores the drop-in from before the apply, applied pending values become pending again.
+    pub fn rollback(&mut self, applied: PwApplied) -> Result<()> {
+        let (file_name, section_name, subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+
+        PwDropIn::restore(&applied.path, applied.previous.as_deref())?;
+        self.current = Self::get_current(file_name, section_name, subsection_name)?;
+
+        // Edits made since the apply win
+        let mut values = applied.pending;
+        values.extend(std::mem::take(&mut self.new));
+        self.new = values;
+
+        debug!(target: Self::LOG_TARGET, "{} {} rolled back", file_name, section_name);
+
+        Ok(())
+    }
+
     fn get_paths(file: &str) -> Result<Map<String, Value>> {
-        // This should be json format
-        let pw_default_config_output = Command::new("pw-config")
-            .arg("--name")
-            .arg(file)
-            .arg("paths")
-            .arg("-LNr")
-            .output()
-            .context(format!("Reading paths of pw-config for {}", file))?;
+        let mut command = PwHost::command("pw-config");
+        // pw-config only searches the PipeWire dirs
+        if Self::is_wireplumber(file) {
+            command.arg("--prefix").arg(Self::get_system_dir(file));
+        }
 
-        let json = String::from_utf8_lossy(&pw_default_config_output.stdout).into_owned();
+        // This should be json format
+        let json = PwHost::run(
+            command
+                .arg("--name")
+                .arg(file)
+                .arg("paths")
+                .arg(Self::LIST_FLAGS),
+        )
+        .context(format!("Reading paths of pw-config for {}", file))?;
 
         debug!(target: Self::LOG_TARGET, "{} paths raw:\n{}",file, json);
 
-        let json_parsed: Value = serde_json::from_str(&json)
-            .context(format!("Parsing paths of pw-config for {}", file))?;
-
-        let json_object = json_parsed.as_object().unwrap().to_owned();
+        let mut json_object = Self::parse_object(&json, format!("paths of {}", file))?;
+        // Host paths, readable from inside a sandbox
+        for path in json_object.values_mut() {
+            if let Some(host_path) = path.as_str() {
+                *path = Value::from(PwHost::get_readable_path(host_path).to_string_lossy());
+            }
+        }
 
         debug!(target: Self::LOG_TARGET, "{} paths json:\n{:#?}", file, &json_object);
 
@@ -74,29 +438,28 @@ impl PwConfig {
         section: &str,
         subsection: Option<&str>,
     ) -> Result<Map<String, Value>> {
-        // This should be json format
-        let pw_default_config_output = Command::new("pw-config")
-            .arg("--name")
-            .arg(file)
-            .arg("list")
-            .arg("-LNr")
-            .arg(section)
-            .output()
-            .context(format!(
-                "Reading output of pw-config for {} {}",
-                file, section
-            ))?;
-
-        let json = String::from_utf8_lossy(&pw_default_config_output.stdout).into_owned();
-
-        debug!(target: Self::LOG_TARGET, "{} {} current raw:\n{}",file, section, json);
+        if Self::is_wireplumber(file) {
+            return Self::get_current_from_dropin(file, section, subsection);
+        }
 
-        let json_parsed: Value = serde_json::from_str(&json).context(format!(
-            "Parsing output of pw-config for {} {}",
+        // This should be json format
+        let json = PwHost::run(
+            PwHost::command("pw-config")
+                .arg("--name")
+                .arg(file)
+                .arg("list")
+                .arg(Self::LIST_FLAGS)
+                .arg(section),
+        )
+        .context(format!(
+            "Reading output of pw-config for {} {}",
             file, section
         ))?;
 
-        let mut json_object = json_parsed.as_object().unwrap().to_owned();
+        debug!(target: Self::LOG_TARGET, "{} {} current raw:\n{}",file, section, json);
+
+        let mut json_object =
+            Self::parse_object(&json, format!("current values of {} {}", file, section))?;
         if let Some(value) = subsection {
             json_object.retain(|key, _value| key.starts_with(&format!("{}.", value)));
         }
@@ -123,34 +486,34 @@ impl PwConfig {
         Ok(map_to_one_json_object)
     }
 
-    fn get_default(file: &str, section: &str, subsection: Option<&str>) -> Result<MapWithOptions> {
-        let pw_default_config_output = Command::new("pw-config")
-            .arg("--name")
-            .arg(file)
-            .arg("list")
-            .arg("-N")
-            .arg("-p")
-            .arg("/usr/share/pipewire")
-            .arg(section)
-            .output()
-            .context(format!(
-                "Reading output of pw-config for {} {}",
-                file, section
-            ))?;
-
-        let spa_json = String::from_utf8_lossy(&pw_default_config_output.stdout).into_owned();
+    /// The upstream default values with their options, and the comments describing them.
+    fn get_default(
+        file: &str,
+        section: &str,
+        subsection: Option<&str>,
+    ) -> Result<(MapWithOptions, HashMap<String, String>)> {
+        let spa_json = PwHost::run(
+            PwHost::command("pw-config")
+                .arg("--name")
+                .arg(file)
+                .arg("list")
+                .arg("-N")
+                .arg("-p")
+                .arg(Self::get_system_dir(file))
+                .arg(section),
+        )
+        .context(format!(
+            "Reading output of pw-config for {} {}",
+            file, section
+        ))?;
 
         debug!(target: Self::LOG_TARGET, "{} {} {:?} default raw:\n{}", file, section, subsection, spa_json);
 
-        let (json, options) = Self::parse_spa_json(spa_json);
+        let (json, options, mut descriptions) = Self::parse_spa_json(spa_json);
         let mut default_map: MapWithOptions = HashMap::new();
 
-        let json_parsed: Value = serde_json::from_str(&json).context(format!(
-            "Parsing output of pw-config for {} {}",
-            file, section
-        ))?;
-
-        let mut json_object = json_parsed.as_object().unwrap().to_owned();
+        let mut json_object =
+            Self::parse_object(&json, format!("default values of {} {}", file, section))?;
         if let Some(value) = subsection {
             json_object.retain(|key, _value| key.starts_with(&format!("{}.", value)));
         }
@@ -161,25 +524,168 @@ impl PwConfig {
             match &options {
                 Some(options_map) => match options_map.get(&key) {
                     None => default_map.insert(key, (value, None)),
-                    Some(options_vec) => {
-                        default_map.insert(key, (value, Some(options_vec.to_owned())))
+                    Some(value_options) => {
+                        default_map.insert(key, (value, Some(value_options.to_owned())))
                     }
                 },
                 None => default_map.insert(key, (value, None)),
             };
         }
 
-        Ok(default_map)
+        descriptions.retain(|key, _description| default_map.contains_key(key));
+
+        Ok((default_map, descriptions))
     }
 
-    fn get_config_file_and_sections(
+    /// WirePlumber reads its own config dirs which pw-config does not merge,
+    /// so this is the upstream default with this app's drop-in on top.
+    fn get_current_from_dropin(
+        file: &str,
+        section: &str,
+        subsection: Option<&str>,
+    ) -> Result<Map<String, Value>> {
+        let mut current: Map<String, Value> = Self::get_default(file, section, subsection)?
+            .0
+            .into_iter()
+            .map(|(key, (value, _options))| (key, value))
+            .collect();
+        let path = PwDropIn::get_path(file, section, subsection);
+        current.extend(PwDropIn::read(&path, section)?);
+        current.sort_keys();
+
+        Ok(current)
+    }
+
+    /// The JSON object pw-config printed, or what was wrong with it.
+    fn parse_object(json: &str, what: String) -> Result<Map<String, Value>, PwError> {
+        let parse_error = |reason: String| PwError::Parse {
+            what: what.clone(),
+            reason,
+            text: json.to_string(),
+        };
+
+        match serde_json::from_str(json) {
+            Ok(Value::Object(json_object)) => Ok(json_object),
+            Ok(_) => Err(parse_error(String::from("it should be a JSON object"))),
+            Err(error) => Err(parse_error(error.to_string())),
+        }
+    }
+
+    fn is_wireplumber(file: &str) -> bool {
+        file.starts_with("wireplumber")
+    }
+
+    /// Dir of the upstream config, `/usr/share/pipewire` or `/usr/share/wireplumber`.
+    /// A host path, pw-config runs on the host.
+    fn get_system_dir(file: &str) -> &'static str {
+        match Self::is_wireplumber(file) {
+            true => "/usr/share/wireplumber",
+            false => "/usr/share/pipewire",
+        }
+    }
+
+    /// Re-reads the paths and current values, e.g. after the drop-ins changed.
+    pub fn reload(&mut self) -> Result<()> {
+        let (file_name, section_name, subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+
+        self.paths = Self::get_paths(file_name)?;
+        self.current = Self::get_current(file_name, section_name, subsection_name)?;
+
+        Ok(())
+    }
+
+    /// Reloads after the config files changed outside of the app. Pending values that now
+    /// are the current value are dropped, the ones that differ from a changed value are returned.
+    pub fn reload_external(&mut self) -> Result<Vec<PwConflict>> {
+        let previous = self.current.clone();
+        self.reload()?;
+
+        Ok(self.get_conflicts(&previous))
+    }
+
+    fn get_conflicts(&mut self, previous: &Map<String, Value>) -> Vec<PwConflict> {
+        let current = &self.current;
+        self.new
+            .retain(|key, pending| current.get(key) != Some(pending));
+
+        self.new
+            .iter()
+            .filter(|(key, _pending)| previous.get(*key) != current.get(*key))
+            .map(|(key, pending)| PwConflict {
+                key: key.to_owned(),
+                pending: pending.to_owned(),
+                external: current.get(key).cloned(),
+            })
+            .collect()
+    }
+
+    /// Dirs of the files this config is read from and of its drop-ins, to watch for changes.
+    pub fn get_watched_dirs(&self) -> Vec<PathBuf> {
+        let (file_name, section_name, subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+        let dropin_path = PwDropIn::get_path(file_name, section_name, subsection_name);
+        let mut dirs: Vec<PathBuf> = dropin_path
+            .parent()
+            .map(PathBuf::from)
+            .into_iter()
+            .collect();
+
+        for path in self.paths.values().filter_map(|path| path.as_str()) {
+            if let Some(dir) = Path::new(path).parent()
+                && !dirs.iter().any(|watched| watched == dir)
+            {
+                dirs.push(dir.to_path_buf());
+            }
+        }
+
+        dirs
+    }
+
+    /// Dirs in the user's config home this config is read from, e.g. `~/.config/pipewire`.
+    pub fn get_user_config_dirs(&self) -> Vec<PathBuf> {
+        let (file_name, _section_name, _subsection_name) =
+            Self::get_config_file_and_sections(&self.config_file);
+        let config_home = PwDropIn::get_config_home();
+        let mut dirs = vec![PwDropIn::get_config_dir(file_name)];
+
+        for path in self.paths.values().filter_map(|path| path.as_str()) {
+            let Ok(relative) = Path::new(path).strip_prefix(&config_home) else {
+                continue;
+            };
+            if let Some(dir_name) = relative.components().next() {
+                let dir = config_home.join(dir_name);
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+
+        dirs
+    }
+
+    pub fn get_config_file_and_sections(
         file: &PwConfigFile,
-    ) -> (&'static str, &'static str, Option<&str>) {
+    ) -> (&'static str, &'static str, Option<&'static str>) {
         let file_name: &str;
         let section_name: &str;
         let subsection_name: Option<&str>;
 
         match file {
+            PwConfigFile::Pipewire(section) => {
+                file_name = "pipewire.conf";
+
+                match section {
+                    PwPipewireSection::ContextProperties(subsection) => {
+                        section_name = "context.properties";
+
+                        match subsection {
+                            PwPipewireSectionSub::Clock => subsection_name = Some("default.clock"),
+                            PwPipewireSectionSub::None => subsection_name = None,
+                        }
+                    }
+                }
+            }
             PwConfigFile::PipewirePulse(section) => {
                 file_name = "pipewire-pulse.conf";
 
@@ -194,19 +700,75 @@ impl PwConfig {
                     }
                 }
             }
+            PwConfigFile::Wireplumber(section) => {
+                file_name = "wireplumber.conf";
+
+                match section {
+                    PwWireplumberSection::Settings => {
+                        section_name = "wireplumber.settings";
+                        subsection_name = None;
+                    }
+                }
+            }
         };
 
         (file_name, section_name, subsection_name)
     }
 
-    fn parse_spa_json(spa_json: String) -> (String, Option<HashMap<String, Vec<String>>>) {
+    /// Alternatives like `none, simple` or `on | off`, a range like `0-14` or `0.0 .. 1.0`
+    /// and a unit like `in Hz` from the comment after a value.
+    fn parse_options(comment: &str, value: &str) -> Option<PwOptions> {
+        static REGEX_VALUES: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^[\w.-]+(\s*(,|\||\sor\s)\s*[\w.-]+)+$").unwrap());
+        static REGEX_SEPARATOR: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"\s*(,|\||\sor\s)\s*").unwrap());
+        static REGEX_RANGE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"(-?\d+(?:\.\d+)?)\s*(?:-|\.\.|to)\s*(-?\d+(?:\.\d+)?)").unwrap()
+        });
+        static REGEX_UNIT: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"\b(Hz|kHz|ms|usec|nsec|dB|samples|bits)\b").unwrap());
+        let comment = comment.trim();
+        let mut options = PwOptions::default();
+
+        if REGEX_VALUES.is_match(comment) {
+            let mut values: Vec<String> = REGEX_SEPARATOR
+                .split(comment)
+                .map(|value| value.to_string())
+                .collect();
+            if !values.iter().any(|option| option == value) {
+                values.push(value.to_string());
+            }
+            options.values = Some(values);
+
+            return Some(options);
+        }
+
+        if let Some(captures) = REGEX_RANGE.captures(comment) {
+            let min: Option<f64> = captures[1].parse().ok();
+            let max: Option<f64> = captures[2].parse().ok();
+            // A reversed range like `14-0` is still a range
+            if let (Some(min), Some(max)) = (min, max) {
+                options.min = Some(min.min(max));
+                options.max = Some(min.max(max));
+            }
+        }
+        if let Some(captures) = REGEX_UNIT.captures(comment) {
+            options.unit = Some(captures[1].to_string());
+        }
+
+        (options != PwOptions::default()).then_some(options)
+    }
+
+    /// Also returns the options and the comments above or after each key.
+    fn parse_spa_json(spa_json: String) -> (String, Option<OptionsMap>, HashMap<String, String>) {
         debug!(target: Self::LOG_TARGET,"Parsing spa-json to json");
 
         let split = spa_json.lines();
         let regex_key_value = Regex::new(r"^.*\s+=\s.*$").unwrap();
-        let regex_options = Regex::new(r"^(.+,.+)+$").unwrap();
         let mut json = String::new();
-        let mut options_map: HashMap<String, Vec<String>> = HashMap::new();
+        let mut options_map: OptionsMap = HashMap::new();
+        let mut descriptions: HashMap<String, String> = HashMap::new();
+        let mut comment_lines: Vec<&str> = Vec::new();
 
         json.push('{');
 
@@ -215,12 +777,22 @@ impl PwConfig {
 
             if regex_key_value.is_match(mut_line) {
                 let mut new_line = String::new();
-                let mut value_options: Option<Vec<String>> = None;
+                let mut value_options: Option<PwOptions> = None;
 
                 // Trim key-values
                 if mut_line.starts_with("#") {
                     mut_line = &mut_line[1..];
                 }
+                // With its spaces, they are removed from the line below
+                let trailing_comment = mut_line
+                    .split_once('#')
+                    .map(|(_value, comment)| comment.trim());
+                // Arrays and objects also need their spaces, e.g. `[ FL FR ]`
+                let structured_value = mut_line
+                    .split_once('=')
+                    .map(|(_key, value)| value.split('#').next().unwrap_or_default().trim())
+                    .filter(|value| value.starts_with('[') || value.starts_with('{'))
+                    .and_then(|value| SpaJson::parse(value).ok());
                 let mut_line = &mut_line.replace(" ", "");
                 let line_split = mut_line.split("=");
                 let mut line_key: Option<String> = None;
@@ -245,29 +817,17 @@ impl PwConfig {
                     if value.contains('#') {
                         let mut split_value = line_s.split("#");
                         value = split_value.next().unwrap().to_string();
-
-                        let comment = split_value.next();
-                        match comment {
-                            Some(comment_value) => {
-                                if regex_options.is_match(comment_value) {
-                                    let option_values =
-                                        comment_value.split(',').map(|s| s.to_owned()).collect();
-
-                                    value_options = Some(option_values);
-                                }
-                            }
-                            None => {}
-                        }
                     }
                     value = value.trim().to_string();
 
-                    // Also add value to options
-                    if let Some(ref mut option_values) = value_options {
-                        option_values.push(value.clone());
+                    if let Some(comment) = trailing_comment {
+                        value_options = Self::parse_options(comment, &value);
                     }
 
                     // Check if primitive type should have quotes
-                    if !value.parse::<f64>().is_ok() && !value.parse::<bool>().is_ok() {
+                    if let Some(structured_value) = &structured_value {
+                        value = structured_value.to_string();
+                    } else if !value.parse::<f64>().is_ok() && !value.parse::<bool>().is_ok() {
                         value = format!("\"{}\"", value);
                     }
 
@@ -275,6 +835,20 @@ impl PwConfig {
                 }
 
                 debug!(target: Self::LOG_TARGET,"Found key-value: {}", new_line);
+                if let Some(key) = &line_key {
+                    let is_values = value_options
+                        .as_ref()
+                        .is_some_and(|options| options.values.is_some());
+                    if let (false, Some(comment)) = (is_values, trailing_comment) {
+                        comment_lines.push(comment);
+                    }
+                    let description = comment_lines.join(" ");
+                    if !description.is_empty() {
+                        descriptions.insert(key.to_owned(), description);
+                    }
+                }
+                comment_lines.clear();
+
                 if let Some(options) = value_options {
                     debug!(target: Self::LOG_TARGET,"Found options: {:?}", options);
 
@@ -287,6 +861,13 @@ impl PwConfig {
                 json.push(',');
             } else {
                 debug!(target: Self::LOG_TARGET, "Line does not match for spa-json parse: {}", mut_line);
+
+                // Comments above a key describe it
+                match mut_line.strip_prefix('#').map(|comment| comment.trim()) {
+                    Some("") => {}
+                    Some(comment) => comment_lines.push(comment),
+                    None => comment_lines.clear(),
+                }
             }
         }
         if json.ends_with(',') {
@@ -302,14 +883,36 @@ impl PwConfig {
             Some(options_map)
         };
 
-        (json, options)
+        (json, options, descriptions)
+    }
+}
+
+#[cfg(test)]
+impl PwConfig {
+    /// A config without values for tests, they set the fields they need.
+    pub fn for_test(config_file: PwConfigFile) -> Self {
+        Self {
+            config_file,
+            current: Map::new(),
+            default: HashMap::new(),
+            descriptions: HashMap::new(),
+            new: Map::new(),
+            paths: Map::new(),
+            versions: PwVersions::default(),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{PwConfig, PwConfigFile, PwPulseSection, PwPulseSectionSub};
+    use super::{
+        PwConfig, PwConfigFile, PwConflict, PwPipewireSection, PwPipewireSectionSub,
+        PwPulseSection, PwPulseSectionSub,
+    };
+    use crate::application::pipewire::version::{PwVersion, PwVersions};
     use anyhow::Result;
+    use serde_json::{Map, json};
+    use std::path::Path;
 
     #[test]
     fn it_should_get_current() -> Result<()> {
@@ -328,36 +931,218 @@ mod tests {
             r#"
                 {
                     "0-/usr/share/pipewire/pipewire-pulse.conf": {
+                        # Latency of new streams,
+                        # as quantum over rate
                         #node.latency          = 1024/48000
                         #node.autoconnect      = true
-                        #resample.quality      = 4
+                        #resample.quality      = 4 # 0-14
                         #channelmix.normalize  = false
                         #channelmix.mix-lfe    = true
                         #channelmix.upmix      = true
                         #channelmix.upmix-method = psd  # none, simple
                         #channelmix.lfe-cutoff = 150
                         #channelmix.fc-cutoff  = 12000
-                        #channelmix.rear-delay = 12.0
+                        #channelmix.rear-delay = 12.0 # in ms
                         #channelmix.stereo-widen = 0.0
                         #channelmix.hilbert-taps = 0
                         #dither.noise = 0
+                        #audio.position = [ FL FR ]
+                        #dither.method = none # rectangular | triangular or shaped5
                     }
                 }
             "#,
         );
         let json_expected = String::from(
-            r#"{"node.latency":"1024/48000","node.autoconnect":true,"resample.quality":4,"channelmix.normalize":false,"channelmix.mix-lfe":true,"channelmix.upmix":true,"channelmix.upmix-method":"psd","channelmix.lfe-cutoff":150,"channelmix.fc-cutoff":12000,"channelmix.rear-delay":12.0,"channelmix.stereo-widen":0.0,"channelmix.hilbert-taps":0,"dither.noise":0}"#,
+            r#"{"node.latency":"1024/48000","node.autoconnect":true,"resample.quality":4,"channelmix.normalize":false,"channelmix.mix-lfe":true,"channelmix.upmix":true,"channelmix.upmix-method":"psd","channelmix.lfe-cutoff":150,"channelmix.fc-cutoff":12000,"channelmix.rear-delay":12.0,"channelmix.stereo-widen":0.0,"channelmix.hilbert-taps":0,"dither.noise":0,"audio.position":["FL","FR"],"dither.method":"none"}"#,
         );
-        let (json, options) = PwConfig::parse_spa_json(spa_json);
+        let (json, options, descriptions) = PwConfig::parse_spa_json(spa_json);
 
         assert_eq!(json, json_expected);
+        assert_eq!(
+            descriptions.get("node.latency").unwrap(),
+            "Latency of new streams, as quantum over rate"
+        );
+        assert_eq!(descriptions.get("channelmix.rear-delay").unwrap(), "in ms");
+        assert!(!descriptions.contains_key("node.autoconnect"));
+        assert!(!descriptions.contains_key("channelmix.upmix-method"));
+        let options = options.unwrap();
+        let values = |key: &str| options.get(key).unwrap().values.clone().unwrap();
+        assert_eq!(values("channelmix.upmix-method"), ["none", "simple", "psd"]);
+        assert_eq!(
+            values("dither.method"),
+            ["rectangular", "triangular", "shaped5", "none"]
+        );
+
+        let quality = options.get("resample.quality").unwrap();
+        assert_eq!((quality.min, quality.max), (Some(0.0), Some(14.0)));
         assert_eq!(
             options
+                .get("channelmix.rear-delay")
                 .unwrap()
-                .get("channelmix.upmix-method")
+                .unit
+                .as_deref(),
+            Some("ms")
+        );
+        assert!(!options.contains_key("channelmix.lfe-cutoff"));
+    }
+
+    #[test]
+    fn it_should_parse_options_from_comments() {
+        let options = PwConfig::parse_options("0.0 .. 1.0", "0.5").unwrap();
+        assert_eq!((options.min, options.max), (Some(0.0), Some(1.0)));
+
+        let options = PwConfig::parse_options("14-0 in Hz", "7").unwrap();
+        assert_eq!((options.min, options.max), (Some(0.0), Some(14.0)));
+        assert_eq!(options.unit.as_deref(), Some("Hz"));
+
+        let options = PwConfig::parse_options("none, simple", "psd").unwrap();
+        assert_eq!(
+            options.values,
+            Some(vec!["none".into(), "simple".into(), "psd".into()])
+        );
+
+        assert_eq!(PwConfig::parse_options("just a comment", "1"), None);
+    }
+
+    #[test]
+    fn it_should_detect_file_name() {
+        let sections = |value: serde_json::Value| value.as_object().unwrap().to_owned();
+        let empty = Map::new();
+
+        assert_eq!(
+            PwConfig::detect_file_name(
+                Path::new("/home/me/.config/pipewire/pipewire-pulse.conf.d/10-upmix.conf"),
+                &empty
+            )
+            .as_deref(),
+            Some("pipewire-pulse.conf")
+        );
+        assert_eq!(
+            PwConfig::detect_file_name(Path::new("/etc/pipewire/pipewire.conf"), &empty).as_deref(),
+            Some("pipewire.conf")
+        );
+        assert_eq!(
+            PwConfig::detect_file_name(
+                Path::new("/tmp/low-latency.conf"),
+                &sections(json!({ "context.properties": {} }))
+            )
+            .as_deref(),
+            Some("pipewire.conf")
+        );
+        assert_eq!(
+            PwConfig::detect_file_name(
+                Path::new("/tmp/bluetooth.conf"),
+                &sections(json!({ "wireplumber.settings": {} }))
+            )
+            .as_deref(),
+            Some("wireplumber.conf")
+        );
+        assert_eq!(
+            PwConfig::detect_file_name(Path::new("/tmp/unknown.conf"), &empty),
+            None
+        );
+    }
+
+    #[test]
+    fn it_should_load_only_own_subsection() {
+        let mut pw_config = PwConfig::for_test(PwConfigFile::Pipewire(
+            PwPipewireSection::ContextProperties(PwPipewireSectionSub::Clock),
+        ));
+        let sections = json!({
+            "context.properties": { "default.clock.quantum": 64, "log.level": 2 },
+        });
+
+        let loaded = pw_config.load("pipewire.conf", sections.as_object().unwrap());
+
+        assert_eq!(loaded, ["default.clock.quantum"]);
+        assert_eq!(pw_config.new.get("default.clock.quantum"), Some(&json!(64)));
+        assert!(
+            pw_config
+                .load("pipewire-pulse.conf", sections.as_object().unwrap())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn it_should_report_settings_too_new_for_the_version() {
+        let pw_config = PwConfig {
+            new: json!({ "default.clock.quantum-floor": 4 })
+                .as_object()
                 .unwrap()
-                .join(","),
-            "none,simple,psd"
-        )
+                .to_owned(),
+            versions: PwVersions {
+                pipewire: PwVersion::new(0, 3, 80),
+                wireplumber: None,
+            },
+            ..PwConfig::for_test(PwConfigFile::Pipewire(
+                PwPipewireSection::ContextProperties(PwPipewireSectionSub::Clock),
+            ))
+        };
+        assert_eq!(
+            pw_config.get_invalid(),
+            ["default.clock.quantum-floor: Needs PipeWire 1.0.0 or newer, 0.3.80 is installed"]
+        );
+    }
+
+    #[test]
+    fn it_should_find_conflicts_with_external_changes() {
+        let values = |value: serde_json::Value| value.as_object().unwrap().to_owned();
+        let mut pw_config = PwConfig {
+            current: values(json!({ "default.clock.rate": 96000, "default.clock.quantum": 512 })),
+            new: values(json!({
+                "default.clock.rate": 44100,
+                "default.clock.quantum": 256,
+                "default.clock.min-quantum": 32,
+                "default.clock.max-quantum": 2048,
+            })),
+            paths: values(json!({ "0": "/usr/share/pipewire/pipewire.conf" })),
+            ..PwConfig::for_test(PwConfigFile::Pipewire(
+                PwPipewireSection::ContextProperties(PwPipewireSectionSub::Clock),
+            ))
+        };
+        let previous = values(json!({
+            "default.clock.rate": 48000,
+            "default.clock.quantum": 1024,
+            "default.clock.min-quantum": 16,
+            "default.clock.max-quantum": 4096,
+        }));
+
+        let conflicts = pw_config.get_conflicts(&previous);
+
+        assert_eq!(
+            conflicts,
+            [
+                PwConflict {
+                    key: "default.clock.max-quantum".into(),
+                    pending: json!(2048),
+                    external: None,
+                },
+                PwConflict {
+                    key: "default.clock.min-quantum".into(),
+                    pending: json!(32),
+                    external: None,
+                },
+                PwConflict {
+                    key: "default.clock.quantum".into(),
+                    pending: json!(256),
+                    external: Some(json!(512)),
+                },
+                PwConflict {
+                    key: "default.clock.rate".into(),
+                    pending: json!(44100),
+                    external: Some(json!(96000)),
+                },
+            ]
+        );
+
+        pw_config.current = values(json!({ "default.clock.rate": 44100 }));
+        let previous = pw_config.current.clone();
+        assert!(pw_config.get_conflicts(&previous).is_empty());
+        assert!(!pw_config.new.contains_key("default.clock.rate"));
+        assert!(
+            pw_config
+                .get_watched_dirs()
+                .contains(&std::path::PathBuf::from("/usr/share/pipewire"))
+        );
     }
 }
@@ -0,0 +1,226 @@
+use super::{config::PwConfig, dropin::PwDropIn, host::PwHost, version::PwVersion};
+use crate::i18n::gettext;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// A titled list of facts, e.g. the versions.
+#[derive(Debug, PartialEq)]
+pub struct PwDiagnosticsSection {
+    pub title: String,
+    pub entries: Vec<(String, String)>,
+}
+
+/// What we ask for when users report a problem, as sections to show and a report to copy.
+pub struct PwDiagnostics {
+    pub sections: Vec<PwDiagnosticsSection>,
+}
+impl PwDiagnostics {
+    const PROGRAMS: [&str; 3] = ["pipewire", "pipewire-pulse", "wireplumber"];
+    const CONFIG_FILES: [&str; 3] = ["pipewire.conf", "pipewire-pulse.conf", "wireplumber.conf"];
+    /// Other sound servers that fight over the devices
+    const DAEMONS: [&str; 3] = ["pulseaudio", "jackd", "jackdbus"];
+    const ENVIRONMENT_PREFIXES: [&str; 2] = ["PIPEWIRE_", "PULSE_"];
+
+    /// Everything that can be read without the session bus, the unit states are added later.
+    /// Runs the programs and `pgrep`, so it blocks. `paths` come from `get_config_paths`.
+    pub fn collect(paths: Vec<(String, String)>) -> Self {
+        let versions = Self::PROGRAMS
+            .iter()
+            .map(|program| {
+                let version = PwHost::run(PwHost::command(program).arg("--version"))
+                    .ok()
+                    .and_then(|output| PwVersion::from_output(&output))
+                    .map_or(gettext("not found"), |version| version.to_string());
+                (program.to_string(), version)
+            })
+            .collect();
+
+        let dropins = Self::CONFIG_FILES
+            .iter()
+            .flat_map(|file| Self::get_dropin_dirs(file))
+            .flat_map(|dir| Self::list_dropins(&dir))
+            .collect();
+
+        let daemons = Self::DAEMONS
+            .iter()
+            .map(|daemon| {
+                let state = match Self::is_running(daemon) {
+                    Some(true) => gettext("running"),
+                    Some(false) => gettext("not running"),
+                    None => gettext("unknown"),
+                };
+                (daemon.to_string(), state)
+            })
+            .collect();
+
+        Self {
+            sections: vec![
+                Self::section(&gettext("Versions"), versions),
+                Self::section(&gettext("Config paths"), paths),
+                Self::section(&gettext("Drop-ins"), dropins),
+                Self::section(&gettext("Other sound servers"), daemons),
+                Self::section(&gettext("Environment"), Self::get_environment(env::vars())),
+            ],
+        }
+    }
+
+    /// The paths PipeWire reported for each config, named after the config.
+    pub fn get_config_paths(configs: &[&PwConfig]) -> Vec<(String, String)> {
+        let mut paths = Vec::new();
+        for pw_config in configs {
+            for (name, path) in &pw_config.paths {
+                let path = path
+                    .as_str()
+                    .map_or(path.to_string(), |path| path.to_string());
+                paths.push((format!("{} {}", pw_config.get_name(), name), path));
+            }
+        }
+
+        paths
+    }
+
+    /// Adds the `ActiveState` of the systemd units, after the section of the versions.
+    pub fn add_units(&mut self, units: Vec<(String, String)>) {
+        let index = self.sections.len().min(1);
+        self.sections
+            .insert(index, Self::section(&gettext("Services"), units));
+    }
+
+    /// Plain text to paste in an issue.
+    pub fn to_report(&self) -> String {
+        let mut report = Vec::new();
+        for section in &self.sections {
+            report.push(format!("## {}", section.title));
+            if section.entries.is_empty() {
+                report.push(gettext("(none)"));
+            }
+            for (name, value) in &section.entries {
+                report.push(format!("{}: {}", name, value));
+            }
+            report.push(String::new());
+        }
+
+        report.join("\n").trim_end().to_string()
+    }
+
+    fn section(title: &str, entries: Vec<(String, String)>) -> PwDiagnosticsSection {
+        PwDiagnosticsSection {
+            title: title.to_string(),
+            entries,
+        }
+    }
+
+    /// User dir first, then the system dirs, e.g. `/etc/pipewire/pipewire.conf.d`.
+    fn get_dropin_dirs(file: &str) -> Vec<PathBuf> {
+        let dir_name = match file.starts_with("wireplumber") {
+            true => "wireplumber",
+            false => "pipewire",
+        };
+        let dropin_dir = format!("{}.d", file);
+
+        vec![
+            PwDropIn::get_config_home().join(dir_name).join(&dropin_dir),
+            PwHost::get_readable_path(&format!("/etc/{}/{}", dir_name, dropin_dir)),
+            PwHost::get_readable_path(&format!("/usr/share/{}/{}", dir_name, dropin_dir)),
+        ]
+    }
+
+    fn list_dropins(dir: &Path) -> Vec<(String, String)> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+
+        let mut dropins: Vec<(String, String)> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|file_name| file_name.ends_with(".conf"))
+            .map(|file_name| (file_name, dir.display().to_string()))
+            .collect();
+        dropins.sort();
+
+        dropins
+    }
+
+    /// `None` when it can not be checked, e.g. without `pgrep`.
+    fn is_running(daemon: &str) -> Option<bool> {
+        let status = PwHost::command("pgrep")
+            .arg("-x")
+            .arg(daemon)
+            .status()
+            .ok()?;
+
+        match status.code() {
+            Some(0) => Some(true),
+            Some(1) => Some(false),
+            _ => None,
+        }
+    }
+
+    fn get_environment(variables: impl Iterator<Item = (String, String)>) -> Vec<(String, String)> {
+        let mut environment: Vec<(String, String)> = variables
+            .filter(|(name, _value)| {
+                Self::ENVIRONMENT_PREFIXES
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+            })
+            .collect();
+        environment.sort();
+
+        environment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PwDiagnostics;
+    use anyhow::Result;
+    use std::fs;
+
+    #[test]
+    fn it_should_list_dropins_and_environment() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("99-b.conf"), "")?;
+        fs::write(dir.path().join("10-a.conf"), "")?;
+        fs::write(dir.path().join("notes.txt"), "")?;
+
+        let dropins = PwDiagnostics::list_dropins(dir.path());
+        let names: Vec<&str> = dropins.iter().map(|(name, _dir)| name.as_str()).collect();
+        assert_eq!(names, ["10-a.conf", "99-b.conf"]);
+        assert!(PwDiagnostics::list_dropins(&dir.path().join("missing")).is_empty());
+
+        let variables = [
+            ("PULSE_SERVER", "unix:/run/pulse"),
+            ("HOME", "/home/me"),
+            ("PIPEWIRE_LATENCY", "256/48000"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        assert_eq!(
+            PwDiagnostics::get_environment(variables),
+            [
+                ("PIPEWIRE_LATENCY".to_string(), "256/48000".to_string()),
+                ("PULSE_SERVER".to_string(), "unix:/run/pulse".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_format_report() {
+        let mut diagnostics = PwDiagnostics {
+            sections: vec![PwDiagnostics::section(
+                "Versions",
+                vec![("pipewire".to_string(), "1.0.7".to_string())],
+            )],
+        };
+        diagnostics.add_units(Vec::new());
+
+        assert_eq!(
+            diagnostics.to_report(),
+            "## Versions\npipewire: 1.0.7\n\n## Services\n(none)"
+        );
+    }
+}
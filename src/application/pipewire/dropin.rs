@@ -0,0 +1,210 @@
+use super::{host::PwHost, spa_json::SpaJson};
+use anyhow::{Context, Result};
+use log::{debug, info};
+use serde_json::{Map, Value};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Where the app writes its drop-ins.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum PwDropInScope {
+    /// `~/.config`, only for this user, the only location the app can write to
+    #[default]
+    User,
+}
+impl PwDropInScope {
+    pub const ALL: [PwDropInScope; 1] = [Self::User];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.get_name() == name)
+    }
+}
+
+/// The drop-in file this app manages for a config section.
+/// PipeWire merges it on top of the system config on the next (re)start.
+pub struct PwDropIn;
+impl PwDropIn {
+    const LOG_TARGET: &str = "PwDropIn";
+    const FILE_PREFIX: &str = "99-pipewire-config-gnome";
+    const HEADER: &str = "# Generated by Pipewire config gnome, changes may be overwritten\n";
+
+    /// The host's config home, Flatpak points `XDG_CONFIG_HOME` into the sandbox.
+    pub fn get_config_home() -> PathBuf {
+        let variable = match PwHost::is_sandboxed() {
+            true => "HOST_XDG_CONFIG_HOME",
+            false => "XDG_CONFIG_HOME",
+        };
+
+        match env::var_os(variable) {
+            Some(config_home) if !config_home.is_empty() => PathBuf::from(config_home),
+            _ => PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".config"),
+        }
+    }
+
+    pub fn get_data_home() -> PathBuf {
+        match env::var_os("XDG_DATA_HOME") {
+            Some(data_home) if !data_home.is_empty() => PathBuf::from(data_home),
+            _ => PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".local/share"),
+        }
+    }
+
+    /// `~/.config/pipewire` or `~/.config/wireplumber`
+    pub fn get_config_dir(file: &str) -> PathBuf {
+        let dir_name = match file.starts_with("wireplumber") {
+            true => "wireplumber",
+            false => "pipewire",
+        };
+
+        Self::get_config_home().join(dir_name)
+    }
+
+    pub fn get_path(file: &str, section: &str, subsection: Option<&str>) -> PathBuf {
+        let mut file_name = format!("{}-{}", Self::FILE_PREFIX, section);
+        if let Some(subsection) = subsection {
+            file_name.push('-');
+            file_name.push_str(subsection);
+        }
+        let file_name = format!("{}.conf", file_name.replace('.', "-"));
+
+        Self::get_config_dir(file)
+            .join(format!("{}.d", file))
+            .join(file_name)
+    }
+
+    /// Values of a section in a drop-in, empty when the drop-in does not exist.
+    pub fn read(path: &Path, section: &str) -> Result<Map<String, Value>> {
+        if !path.exists() {
+            return Ok(Map::new());
+        }
+
+        let spa_json =
+            fs::read_to_string(path).context(format!("Reading drop-in {}", path.display()))?;
+        let parsed =
+            SpaJson::parse(&spa_json).context(format!("Parsing drop-in {}", path.display()))?;
+
+        debug!(target: Self::LOG_TARGET, "{} read:\n{:#?}", path.display(), parsed);
+
+        match parsed.get(section) {
+            Some(Value::Object(map)) => Ok(map.to_owned()),
+            _ => Ok(Map::new()),
+        }
+    }
+
+    /// Raw contents of a drop-in, `None` when it does not exist.
+    pub fn read_raw(path: &Path) -> Result<Option<String>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents =
+            fs::read_to_string(path).context(format!("Reading drop-in {}", path.display()))?;
+
+        Ok(Some(contents))
+    }
+
+    /// Puts back raw contents from `read_raw`, removing the drop-in if there were none.
+    pub fn restore(path: &Path, contents: Option<&str>) -> Result<()> {
+        match contents {
+            Some(contents) => Self::write_atomic(path, contents)?,
+            None if path.exists() => {
+                fs::remove_file(path).context(format!("Removing drop-in {}", path.display()))?
+            }
+            None => {}
+        }
+
+        info!(target: Self::LOG_TARGET, "Restored drop-in {}", path.display());
+
+        Ok(())
+    }
+
+    /// Where a disabled drop-in is kept, PipeWire only reads files ending in `.conf`.
+    pub fn get_disabled_path(path: &Path) -> PathBuf {
+        path.with_extension("conf.disabled")
+    }
+
+    /// Moves the drop-in aside, replacing the values kept from an earlier disable.
+    pub fn disable(path: &Path) -> Result<()> {
+        let disabled_path = Self::get_disabled_path(path);
+        fs::rename(path, &disabled_path)
+            .context(format!("Renaming into {}", disabled_path.display()))?;
+
+        info!(target: Self::LOG_TARGET, "Disabled drop-in {}", path.display());
+
+        Ok(())
+    }
+
+    /// Puts back the drop-in kept by `disable`, `false` when there is none.
+    /// The kept copy stays, so a rollback can remove the drop-in again.
+    pub fn enable(path: &Path) -> Result<bool> {
+        let disabled_path = Self::get_disabled_path(path);
+        let Some(contents) = Self::read_raw(&disabled_path)? else {
+            return Ok(false);
+        };
+        Self::write_atomic(path, &contents)?;
+
+        info!(target: Self::LOG_TARGET, "Enabled drop-in {}", path.display());
+
+        Ok(true)
+    }
+
+    /// Replaces the drop-in with the given section values.
+    pub fn write(path: &Path, section: &str, values: &Map<String, Value>) -> Result<()> {
+        let mut sections = Map::new();
+        sections.insert(section.to_string(), Value::Object(values.to_owned()));
+        let contents = format!("{}{}", Self::HEADER, SpaJson::to_string(&sections));
+
+        Self::write_atomic(path, &contents)?;
+
+        info!(target: Self::LOG_TARGET, "Wrote drop-in {}", path.display());
+
+        Ok(())
+    }
+
+    /// Writes to a temporary file next to the target and renames it into place.
+    pub fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+        let dir = path.parent().context("Drop-in path has no parent")?;
+        fs::create_dir_all(dir).context(format!("Creating {}", dir.display()))?;
+
+        let tmp_path = path.with_extension("conf.tmp");
+        fs::write(&tmp_path, contents).context(format!("Writing {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, path).context(format!("Renaming into {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PwDropIn;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn it_should_disable_and_enable_again() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir
+            .path()
+            .join("99-pipewire-config-gnome-stream-properties.conf");
+        let values = json!({ "channelmix.upmix": true });
+        let values = values.as_object().unwrap();
+
+        assert!(!PwDropIn::enable(&path)?);
+        PwDropIn::write(&path, "stream.properties", values)?;
+        PwDropIn::disable(&path)?;
+        assert!(!path.exists());
+        assert!(PwDropIn::read(&path, "stream.properties")?.is_empty());
+
+        assert!(PwDropIn::enable(&path)?);
+        assert_eq!(&PwDropIn::read(&path, "stream.properties")?, values);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt;
+
+/// Why reading the PipeWire configs failed, shown on the error page when the app starts.
+#[derive(Debug, PartialEq)]
+pub enum PwError {
+    /// The program is not installed, e.g. `pw-config`
+    MissingBinary {
+        program: String,
+    },
+    /// The command ran but exited with an error
+    Failed {
+        command: String,
+        status: Option<i32>,
+        stderr: String,
+    },
+    /// Output that is not what was expected
+    Parse {
+        what: String,
+        reason: String,
+        text: String,
+    },
+    UnsupportedVersion {
+        version: String,
+        minimum: String,
+    },
+}
+impl PwError {
+    /// Offending text longer than this is cut off in messages
+    const MAX_TEXT_LENGTH: usize = 500;
+
+    pub fn get_title(&self) -> &'static str {
+        match self {
+            Self::MissingBinary { .. } => "PipeWire Tools Missing",
+            Self::Failed { .. } => "PipeWire Config Could Not Be Read",
+            Self::Parse { .. } => "Unexpected PipeWire Config Output",
+            Self::UnsupportedVersion { .. } => "Unsupported PipeWire Version",
+        }
+    }
+
+    /// What the user can do about it, in English with the arguments for `gettext_f`.
+    pub fn get_hint(&self) -> (&'static str, Vec<&str>) {
+        match self {
+            Self::MissingBinary { program } => (
+                "{} was not found. Install the PipeWire tools of your distribution, e.g. pipewire-bin or pipewire, and retry.",
+                vec![program.as_str()],
+            ),
+            Self::Failed { .. } => (
+                "Check that PipeWire is installed and its config files are valid.",
+                Vec::new(),
+            ),
+            Self::Parse { .. } => (
+                "The output of pw-config could not be read, please report it with the details.",
+                Vec::new(),
+            ),
+            Self::UnsupportedVersion { minimum, .. } => (
+                "PipeWire {} or newer is needed, please update it.",
+                vec![minimum.as_str()],
+            ),
+        }
+    }
+
+    fn cut_off(text: &str) -> String {
+        match text.char_indices().nth(Self::MAX_TEXT_LENGTH) {
+            Some((index, _char)) => format!("{}…", &text[..index]),
+            None => text.to_string(),
+        }
+    }
+}
+impl fmt::Display for PwError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingBinary { program } => write!(formatter, "{} is not installed", program),
+            Self::Failed {
+                command,
+                status,
+                stderr,
+            } => {
+                let status = status.map_or(String::from("a signal"), |status| status.to_string());
+                write!(
+                    formatter,
+                    "{} exited with {}: {}",
+                    command,
+                    status,
+                    Self::cut_off(stderr.trim())
+                )
+            }
+            Self::Parse { what, reason, text } => write!(
+                formatter,
+                "Parsing {} failed, {}:\n{}",
+                what,
+                reason,
+                Self::cut_off(text)
+            ),
+            Self::UnsupportedVersion { version, minimum } => {
+                write!(formatter, "PipeWire {} is older than {}", version, minimum)
+            }
+        }
+    }
+}
+impl std::error::Error for PwError {}
+
+#[cfg(test)]
+mod tests {
+    use super::PwError;
+    use anyhow::{Context, Result};
+
+    #[test]
+    fn it_should_describe_errors() {
+        let failed = PwError::Failed {
+            command: String::from("pw-config --name pipewire.conf paths"),
+            status: Some(255),
+            stderr: String::from("error: no such file\n"),
+        };
+        assert_eq!(
+            failed.to_string(),
+            "pw-config --name pipewire.conf paths exited with 255: error: no such file"
+        );
+
+        let parse = PwError::Parse {
+            what: String::from("paths"),
+            reason: String::from("should be a JSON object"),
+            text: "x".repeat(600),
+        };
+        assert!(
+            parse
+                .to_string()
+                .ends_with(&format!("{}…", "x".repeat(500)))
+        );
+        assert_eq!(parse.get_title(), "Unexpected PipeWire Config Output");
+    }
+
+    #[test]
+    fn it_should_be_found_through_context() {
+        let result: Result<()> = Err(PwError::MissingBinary {
+            program: String::from("pw-config"),
+        })
+        .context("Reading paths");
+        let error = result.unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<PwError>(),
+            Some(&PwError::MissingBinary {
+                program: String::from("pw-config")
+            })
+        );
+    }
+}
@@ -0,0 +1,507 @@
+use super::dropin::PwDropIn;
+use crate::config;
+use anyhow::{Context, Result};
+use log::{debug, error, info};
+use serde_json::{Value, json};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A copy of the user's config dirs at one point in time.
+#[derive(Clone)]
+pub struct PwSnapshot {
+    pub id: String,
+    pub timestamp: u64,
+    pub summary: String,
+    dir: PathBuf,
+    /// Dir name, e.g. `pipewire`, to the config dir it was copied from
+    config_dirs: BTreeMap<String, PathBuf>,
+}
+
+/// A line of a diff between two snapshots.
+#[derive(Debug, PartialEq)]
+pub enum PwDiffLine {
+    File(String),
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Local store of snapshots of the user's PipeWire and WirePlumber config dirs.
+pub struct PwHistory {
+    store_dir: PathBuf,
+    config_dirs: Vec<PathBuf>,
+}
+impl PwHistory {
+    const LOG_TARGET: &str = "PwHistory";
+    const METADATA_FILE: &str = "snapshot.json";
+    const FILES_DIR: &str = "files";
+    /// Older snapshots are removed, except the first one
+    const MAX_SNAPSHOTS: usize = 50;
+
+    pub fn new(config_dirs: Vec<PathBuf>) -> Self {
+        let data_home = PwDropIn::get_data_home();
+
+        Self::with_store_dir(data_home.join(config::APP_ID).join("history"), config_dirs)
+    }
+
+    pub fn with_store_dir(store_dir: PathBuf, config_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            store_dir,
+            config_dirs,
+        }
+    }
+
+    /// Snapshots, newest first.
+    pub fn list(&self) -> Result<Vec<PwSnapshot>> {
+        if !self.store_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshots = Vec::new();
+        let entries = fs::read_dir(&self.store_dir)
+            .context(format!("Reading history {}", self.store_dir.display()))?;
+
+        for entry in entries {
+            let dir = entry?.path();
+            match Self::read_snapshot(&dir) {
+                Ok(snapshot) => snapshots.push(snapshot),
+                Err(error) => {
+                    debug!(target: Self::LOG_TARGET, "Skipping {}: {:?}", dir.display(), error)
+                }
+            }
+        }
+        snapshots.sort_by(|a, b| b.id.cmp(&a.id));
+
+        Ok(snapshots)
+    }
+
+    /// Snapshots the untouched config dirs once, so the first apply can be undone.
+    pub fn snapshot_initial(&self) -> Result<()> {
+        if self.list()?.is_empty() {
+            self.snapshot("Configuration before the first apply")?;
+        }
+
+        Ok(())
+    }
+
+    /// Copies the config dirs into a new snapshot.
+    pub fn snapshot(&self, summary: &str) -> Result<PwSnapshot> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        // Zero padded so ids sort by time
+        let id = format!("{:020}", now.as_micros());
+        let dir = self.store_dir.join(&id);
+        let mut config_dirs = BTreeMap::new();
+
+        for config_dir in &self.config_dirs {
+            let name = Self::get_dir_name(config_dir)?;
+            if config_dir.exists() {
+                Self::copy_dir(config_dir, &dir.join(Self::FILES_DIR).join(&name))?;
+            }
+            config_dirs.insert(name, config_dir.to_owned());
+        }
+
+        let metadata = json!({
+            "timestamp": now.as_secs(),
+            "summary": summary,
+            "config_dirs": config_dirs,
+        });
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join(Self::METADATA_FILE),
+            serde_json::to_string_pretty(&metadata)?,
+        )?;
+
+        info!(target: Self::LOG_TARGET, "Snapshot {}: {}", id, summary);
+
+        if let Err(error) = self.prune() {
+            error!(target: Self::LOG_TARGET, "Pruning snapshots failed:\n{:?}", error);
+        }
+
+        Self::read_snapshot(&dir)
+    }
+
+    /// Files of a snapshot by path relative to the config home, e.g. `pipewire/pipewire.conf.d/a.conf`.
+    pub fn read_files(&self, snapshot: &PwSnapshot) -> Result<BTreeMap<String, String>> {
+        let mut files = BTreeMap::new();
+        Self::read_dir_files(&snapshot.dir.join(Self::FILES_DIR), "", &mut files)?;
+
+        Ok(files)
+    }
+
+    /// Line diff of every file that differs between two snapshots.
+    pub fn diff(&self, old: &PwSnapshot, new: &PwSnapshot) -> Result<Vec<PwDiffLine>> {
+        let old_files = self.read_files(old)?;
+        let new_files = self.read_files(new)?;
+        let mut paths: Vec<&String> = old_files.keys().chain(new_files.keys()).collect();
+        paths.sort();
+        paths.dedup();
+
+        let mut diff = Vec::new();
+        for path in paths {
+            let old_text = old_files.get(path).map_or("", |text| text.as_str());
+            let new_text = new_files.get(path).map_or("", |text| text.as_str());
+            if old_text == new_text {
+                continue;
+            }
+
+            diff.push(PwDiffLine::File(path.to_owned()));
+            diff.append(&mut Self::diff_lines(old_text, new_text));
+        }
+
+        Ok(diff)
+    }
+
+    /// Replaces the files of the config dirs with the ones of the snapshot.
+    pub fn restore(&self, snapshot: &PwSnapshot) -> Result<()> {
+        Self::restore_with(snapshot, PwDropIn::write_atomic)
+    }
+
+    /// Writes every file of the snapshot into its config dir and removes the files it does not have.
+    /// Works inside the config dirs, a sandbox can not replace the dirs themselves.
+    /// When a file fails the files changed before are put back, so the dirs stay at one point.
+    fn restore_with(
+        snapshot: &PwSnapshot,
+        write: impl Fn(&Path, &str) -> Result<()>,
+    ) -> Result<()> {
+        let mut current = BTreeMap::new();
+        let mut restored = BTreeMap::new();
+        for (name, config_dir) in &snapshot.config_dirs {
+            Self::read_config_files(config_dir, &mut current)?;
+
+            let mut files = BTreeMap::new();
+            let snapshot_dir = snapshot.dir.join(Self::FILES_DIR).join(name);
+            Self::read_dir_files(&snapshot_dir, "", &mut files)?;
+            restored.extend(
+                files
+                    .into_iter()
+                    .map(|(relative, text)| (config_dir.join(relative), text)),
+            );
+        }
+
+        let mut changed: Vec<&PathBuf> = Vec::new();
+        let changes = restored
+            .iter()
+            .filter(|(path, text)| current.get(*path) != Some(*text))
+            .map(|(path, text)| (path, Some(text)))
+            .chain(
+                current
+                    .keys()
+                    .filter(|path| !restored.contains_key(*path))
+                    .map(|path| (path, None)),
+            );
+        for (path, text) in changes {
+            let result = match text {
+                Some(text) => write(path, text),
+                None => fs::remove_file(path).map_err(anyhow::Error::from),
+            };
+            if let Err(error) = result {
+                Self::put_back(&changed, &current, &write);
+                return Err(error.context(format!("Restoring {}", path.display())));
+            }
+            changed.push(path);
+        }
+
+        info!(target: Self::LOG_TARGET, "Restored {} file(s) from snapshot {}", changed.len(), snapshot.id);
+
+        Ok(())
+    }
+
+    /// Files of a config dir by full path, without leftovers of interrupted writes.
+    fn read_config_files(config_dir: &Path, files: &mut BTreeMap<PathBuf, String>) -> Result<()> {
+        let mut relative_files = BTreeMap::new();
+        Self::read_dir_files(config_dir, "", &mut relative_files)?;
+        files.extend(
+            relative_files
+                .into_iter()
+                .filter(|(relative, _text)| !relative.ends_with(".tmp"))
+                .map(|(relative, text)| (config_dir.join(relative), text)),
+        );
+
+        Ok(())
+    }
+
+    /// Undoes a partial restore, files that did not exist before are removed again.
+    fn put_back(
+        changed: &[&PathBuf],
+        previous: &BTreeMap<PathBuf, String>,
+        write: impl Fn(&Path, &str) -> Result<()>,
+    ) {
+        for path in changed.iter().rev() {
+            let result = match previous.get(*path) {
+                Some(text) => write(path, text),
+                None => fs::remove_file(path).map_err(anyhow::Error::from),
+            };
+            if let Err(error) = result {
+                error!(target: Self::LOG_TARGET, "Putting back {} failed:\n{:?}", path.display(), error);
+            }
+        }
+    }
+
+    /// Removes the oldest snapshots over the limit, except the very first one.
+    /// That one holds the configuration from before the app changed anything.
+    fn prune(&self) -> Result<()> {
+        let snapshots = self.list()?;
+        if snapshots.len() <= Self::MAX_SNAPSHOTS {
+            return Ok(());
+        }
+
+        let (_first, pruned) = snapshots[Self::MAX_SNAPSHOTS - 1..]
+            .split_last()
+            .context("No snapshots to prune")?;
+        for snapshot in pruned {
+            fs::remove_dir_all(&snapshot.dir)
+                .context(format!("Removing snapshot {}", snapshot.dir.display()))?;
+            debug!(target: Self::LOG_TARGET, "Pruned snapshot {}", snapshot.id);
+        }
+
+        Ok(())
+    }
+
+    fn read_snapshot(dir: &Path) -> Result<PwSnapshot> {
+        let metadata: Value = serde_json::from_str(
+            &fs::read_to_string(dir.join(Self::METADATA_FILE))
+                .context(format!("Reading snapshot {}", dir.display()))?,
+        )?;
+        let id = dir
+            .file_name()
+            .context("Snapshot dir has no name")?
+            .to_string_lossy()
+            .into_owned();
+        let config_dirs = metadata["config_dirs"]
+            .as_object()
+            .context("Snapshot has no config dirs")?
+            .iter()
+            .filter_map(|(name, path)| Some((name.to_owned(), PathBuf::from(path.as_str()?))))
+            .collect();
+
+        Ok(PwSnapshot {
+            id,
+            timestamp: metadata["timestamp"].as_u64().unwrap_or_default(),
+            summary: metadata["summary"].as_str().unwrap_or_default().to_string(),
+            dir: dir.to_owned(),
+            config_dirs,
+        })
+    }
+
+    fn get_dir_name(config_dir: &Path) -> Result<String> {
+        Ok(config_dir
+            .file_name()
+            .context(format!("{} has no dir name", config_dir.display()))?
+            .to_string_lossy()
+            .into_owned())
+    }
+
+    fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+        fs::create_dir_all(to)?;
+
+        for entry in fs::read_dir(from).context(format!("Reading {}", from.display()))? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name();
+
+            // Leftovers of interrupted writes
+            if name.to_string_lossy().ends_with(".tmp") {
+                continue;
+            }
+
+            match path.is_dir() {
+                true => Self::copy_dir(&path, &to.join(name))?,
+                false => {
+                    fs::copy(&path, to.join(name))
+                        .context(format!("Copying {}", path.display()))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_dir_files(
+        dir: &Path,
+        prefix: &str,
+        files: &mut BTreeMap<String, String>,
+    ) -> Result<()> {
+        if !dir.exists() {
+            return Ok(());
+        }
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let relative = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+
+            match path.is_dir() {
+                true => Self::read_dir_files(&path, &format!("{}/", relative), files)?,
+                false => {
+                    let bytes = fs::read(&path)?;
+                    files.insert(relative, String::from_utf8_lossy(&bytes).into_owned());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Longest common subsequence diff, config files are small.
+    fn diff_lines(old: &str, new: &str) -> Vec<PwDiffLine> {
+        let old: Vec<&str> = old.lines().collect();
+        let new: Vec<&str> = new.lines().collect();
+        let mut lengths = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lengths[i][j] = match old[i] == new[j] {
+                    true => lengths[i + 1][j + 1] + 1,
+                    false => lengths[i + 1][j].max(lengths[i][j + 1]),
+                };
+            }
+        }
+
+        let mut diff = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() && j < new.len() {
+            if old[i] == new[j] {
+                diff.push(PwDiffLine::Same(old[i].to_string()));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                diff.push(PwDiffLine::Removed(old[i].to_string()));
+                i += 1;
+            } else {
+                diff.push(PwDiffLine::Added(new[j].to_string()));
+                j += 1;
+            }
+        }
+        diff.extend(
+            old[i..]
+                .iter()
+                .map(|line| PwDiffLine::Removed(line.to_string())),
+        );
+        diff.extend(
+            new[j..]
+                .iter()
+                .map(|line| PwDiffLine::Added(line.to_string())),
+        );
+
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PwDiffLine, PwHistory};
+    use anyhow::{Result, bail};
+    use std::fs;
+
+    #[test]
+    fn it_should_diff_lines() {
+        let diff = PwHistory::diff_lines("a\nb\nc", "a\nc\nd");
+
+        assert_eq!(
+            diff,
+            [
+                PwDiffLine::Same("a".into()),
+                PwDiffLine::Removed("b".into()),
+                PwDiffLine::Same("c".into()),
+                PwDiffLine::Added("d".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_snapshot_diff_and_restore() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let config_dir = tmp.path().join("config/pipewire");
+        let drop_in = config_dir.join("pipewire.conf.d/99-test.conf");
+        fs::create_dir_all(drop_in.parent().unwrap())?;
+        fs::write(&drop_in, "context.properties = {\n    log.level = 2\n}\n")?;
+
+        let history = PwHistory::with_store_dir(tmp.path().join("history"), vec![config_dir]);
+        let first = history.snapshot("First")?;
+
+        fs::write(&drop_in, "context.properties = {\n    log.level = 4\n}\n")?;
+        let second = history.snapshot("Second")?;
+
+        let snapshots = history.list()?;
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].summary, "Second");
+
+        let diff = history.diff(&first, &second)?;
+        assert_eq!(
+            diff[0],
+            PwDiffLine::File("pipewire/pipewire.conf.d/99-test.conf".into())
+        );
+        assert!(diff.contains(&PwDiffLine::Removed("    log.level = 2".into())));
+        assert!(diff.contains(&PwDiffLine::Added("    log.level = 4".into())));
+
+        history.restore(&first)?;
+        assert!(fs::read_to_string(&drop_in)?.contains("log.level = 2"));
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_put_back_files_when_a_restore_fails_halfway() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let pipewire_dir = tmp.path().join("config/pipewire");
+        let wireplumber_dir = tmp.path().join("config/wireplumber");
+        for dir in [&pipewire_dir, &wireplumber_dir] {
+            fs::create_dir_all(dir)?;
+            fs::write(dir.join("99-test.conf"), "first")?;
+        }
+
+        let history = PwHistory::with_store_dir(
+            tmp.path().join("history"),
+            vec![pipewire_dir.clone(), wireplumber_dir.clone()],
+        );
+        let first = history.snapshot("First")?;
+        for dir in [&pipewire_dir, &wireplumber_dir] {
+            fs::write(dir.join("99-test.conf"), "second")?;
+        }
+        fs::write(pipewire_dir.join("50-new.conf"), "new")?;
+
+        // The pipewire files are restored before the wireplumber one fails
+        let restored = PwHistory::restore_with(&first, |path, contents| {
+            if path.starts_with(&wireplumber_dir) {
+                bail!("No space left on device");
+            }
+            fs::write(path, contents)?;
+            Ok(())
+        });
+
+        assert!(restored.is_err());
+        for dir in [&pipewire_dir, &wireplumber_dir] {
+            assert_eq!(fs::read_to_string(dir.join("99-test.conf"))?, "second");
+        }
+        assert_eq!(fs::read_to_string(pipewire_dir.join("50-new.conf"))?, "new");
+
+        history.restore(&first)?;
+        assert!(!pipewire_dir.join("50-new.conf").exists());
+        assert_eq!(
+            fs::read_to_string(wireplumber_dir.join("99-test.conf"))?,
+            "first"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_prune_old_snapshots_but_the_first() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let history = PwHistory::with_store_dir(
+            tmp.path().join("history"),
+            vec![tmp.path().join("config/pipewire")],
+        );
+        for index in 0..PwHistory::MAX_SNAPSHOTS + 3 {
+            history.snapshot(&index.to_string())?;
+        }
+
+        let snapshots = history.list()?;
+        assert_eq!(snapshots.len(), PwHistory::MAX_SNAPSHOTS);
+        assert_eq!(snapshots.last().unwrap().summary, "0");
+        assert_eq!(snapshots[PwHistory::MAX_SNAPSHOTS - 2].summary, "4");
+        Ok(())
+    }
+}
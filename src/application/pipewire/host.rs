@@ -0,0 +1,212 @@
+use super::error::PwError;
+use anyhow::{Result, bail};
+use log::debug;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+/// Access to the host system when running inside Flatpak.
+/// Outside of a sandbox everything is passed through as is.
+pub struct PwHost;
+impl PwHost {
+    const LOG_TARGET: &str = "PwHost";
+    const FLATPAK_INFO: &str = "/.flatpak-info";
+    /// Where Flatpak mounts the host's `/usr` and `/etc`
+    const HOST_ROOT: &str = "/run/host";
+    /// Filesystem permissions that give access to all of `~/.config`
+    const CONFIG_PERMISSIONS: [&str; 3] = ["host", "home", "xdg-config"];
+    /// Dirs in `~/.config` the drop-ins are written to
+    const CONFIG_DIRS: [&str; 2] = ["pipewire", "wireplumber"];
+
+    pub fn is_sandboxed() -> bool {
+        static IS_SANDBOXED: OnceLock<bool> = OnceLock::new();
+
+        *IS_SANDBOXED.get_or_init(|| Path::new(Self::FLATPAK_INFO).exists())
+    }
+
+    /// Command that runs the program on the host, through `flatpak-spawn --host` in a sandbox.
+    pub fn command(program: &str) -> Command {
+        if !Self::is_sandboxed() {
+            return Command::new(program);
+        }
+
+        let mut command = Command::new("flatpak-spawn");
+        command.arg("--host").arg(program);
+
+        command
+    }
+
+    /// Runs a command and returns its output, failing with a `PwError` when it did not work.
+    pub fn run(command: &mut Command) -> Result<String, PwError> {
+        let args: Vec<String> = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .filter(|arg| arg != "--host")
+            .collect();
+        let command_line = match Self::is_sandboxed() {
+            true => args.join(" "),
+            false => format!(
+                "{} {}",
+                command.get_program().to_string_lossy(),
+                args.join(" ")
+            ),
+        };
+
+        let output = command.output().map_err(|error| match error.kind() {
+            io::ErrorKind::NotFound => PwError::MissingBinary {
+                program: command_line
+                    .split(' ')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+            },
+            _ => PwError::Failed {
+                command: command_line.clone(),
+                status: None,
+                stderr: error.to_string(),
+            },
+        })?;
+
+        if !output.status.success() {
+            return Err(PwError::Failed {
+                command: command_line,
+                status: output.status.code(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Path the app can read a host file at, e.g. `/run/host/usr/share/pipewire` in a sandbox.
+    /// Only for reading files directly, commands run on the host take the host path.
+    pub fn get_readable_path(host_path: &str) -> PathBuf {
+        Self::translate_path(host_path, Self::is_sandboxed())
+    }
+
+    /// Fails when the sandbox can not write the PipeWire or WirePlumber config dir.
+    pub fn check_permissions() -> Result<()> {
+        if !Self::is_sandboxed() {
+            return Ok(());
+        }
+
+        let flatpak_info = fs::read_to_string(Self::FLATPAK_INFO).unwrap_or_default();
+        let filesystems = Self::get_filesystems(&flatpak_info);
+        debug!(target: Self::LOG_TARGET, "Sandbox filesystems: {:?}", filesystems);
+
+        let missing: Vec<String> = Self::CONFIG_DIRS
+            .iter()
+            .filter(|dir| !Self::has_config_permission(&filesystems, dir))
+            .map(|dir| format!("~/.config/{}", dir))
+            .collect();
+        if !missing.is_empty() {
+            bail!("The sandbox has no write access to {}", missing.join(", "));
+        }
+
+        Ok(())
+    }
+
+    /// Command that grants the permissions `check_permissions` needs.
+    pub fn get_override_command(app_id: &str) -> String {
+        format!(
+            "flatpak override --user --filesystem=xdg-config/pipewire --filesystem=xdg-config/wireplumber {}",
+            app_id
+        )
+    }
+
+    fn translate_path(host_path: &str, is_sandboxed: bool) -> PathBuf {
+        let is_host_only = host_path.starts_with("/usr/") || host_path.starts_with("/etc/");
+        match is_sandboxed && is_host_only {
+            true => PathBuf::from(format!("{}{}", Self::HOST_ROOT, host_path)),
+            false => PathBuf::from(host_path),
+        }
+    }
+
+    /// The `filesystems` of the `[Context]` group in `/.flatpak-info`.
+    fn get_filesystems(flatpak_info: &str) -> Vec<String> {
+        let mut group = "";
+
+        for line in flatpak_info.lines().map(|line| line.trim()) {
+            if line.starts_with('[') && line.ends_with(']') {
+                group = line;
+                continue;
+            }
+            if group != "[Context]" {
+                continue;
+            }
+            if let Some(filesystems) = line.strip_prefix("filesystems=") {
+                return filesystems
+                    .split(';')
+                    .filter(|filesystem| !filesystem.is_empty())
+                    .map(|filesystem| filesystem.to_string())
+                    .collect();
+            }
+        }
+
+        Vec::new()
+    }
+
+    /// Whether `~/.config/<dir>` is writable with one of the filesystem permissions.
+    fn has_config_permission(filesystems: &[String], dir: &str) -> bool {
+        let dir_permissions = [format!("xdg-config/{}", dir), format!("~/.config/{}", dir)];
+
+        filesystems.iter().any(|filesystem| {
+            // E.g. `xdg-config/pipewire:create`, read-only is not enough
+            let (path, mode) = filesystem.split_once(':').unwrap_or((filesystem, "rw"));
+            let path = path.trim_end_matches('/');
+            mode != "ro"
+                && (Self::CONFIG_PERMISSIONS.contains(&path)
+                    || dir_permissions.iter().any(|permission| permission == path))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PwHost;
+    use std::path::Path;
+
+    #[test]
+    fn it_should_translate_host_paths_in_sandbox() {
+        assert_eq!(
+            PwHost::translate_path("/usr/share/pipewire/pipewire.conf", true),
+            Path::new("/run/host/usr/share/pipewire/pipewire.conf")
+        );
+        assert_eq!(
+            PwHost::translate_path("/home/me/.config/pipewire", true),
+            Path::new("/home/me/.config/pipewire")
+        );
+        assert_eq!(
+            PwHost::translate_path("/usr/share/pipewire", false),
+            Path::new("/usr/share/pipewire")
+        );
+    }
+
+    #[test]
+    fn it_should_check_config_permission() {
+        let flatpak_info = "[Application]\nname=org.example\n\n[Context]\nshared=network;ipc;\nfilesystems=xdg-run/pipewire-0;xdg-config/pipewire:create;\n";
+        let filesystems = PwHost::get_filesystems(flatpak_info);
+
+        assert_eq!(
+            filesystems,
+            ["xdg-run/pipewire-0", "xdg-config/pipewire:create"]
+        );
+        assert!(PwHost::has_config_permission(&filesystems, "pipewire"));
+        assert!(!PwHost::has_config_permission(&filesystems, "wireplumber"));
+        assert!(PwHost::has_config_permission(
+            &["xdg-config".to_string()],
+            "wireplumber"
+        ));
+        assert!(!PwHost::has_config_permission(
+            &["xdg-config/pipewire:ro".to_string()],
+            "pipewire"
+        ));
+        assert!(!PwHost::has_config_permission(
+            &["xdg-run/pipewire-0".to_string()],
+            "pipewire"
+        ));
+    }
+}
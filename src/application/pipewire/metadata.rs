@@ -0,0 +1,185 @@
+use super::{host::PwHost, spa_json::SpaJson};
+use anyhow::{Context, Result, bail};
+use log::{debug, info};
+use regex::Regex;
+use serde_json::{Map, Value};
+
+/// A live trial of pending settings pushed to the `settings` metadata.
+/// Holds the previous runtime values so they can be restored.
+pub struct PwTrial {
+    previous: Vec<(String, Option<String>)>,
+}
+impl PwTrial {
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty()
+    }
+
+    pub fn revert(&self) -> Result<()> {
+        for (metadata_key, value) in &self.previous {
+            match value {
+                Some(value) => PwMetadata::set_setting(metadata_key, value)?,
+                None => PwMetadata::delete_setting(metadata_key)?,
+            }
+        }
+
+        info!(target: PwMetadata::LOG_TARGET, "Reverted {} live setting(s)", self.previous.len());
+
+        Ok(())
+    }
+}
+
+pub struct PwMetadata;
+impl PwMetadata {
+    const LOG_TARGET: &str = "PwMetadata";
+    const METADATA_NAME: &str = "settings";
+
+    /// Config keys that have a runtime equivalent in the `settings` metadata.
+    /// A default quantum or rate can only be tried live by forcing it.
+    const RUNTIME_KEYS: [(&str, &str); 4] = [
+        ("default.clock.quantum", "clock.force-quantum"),
+        ("default.clock.rate", "clock.force-rate"),
+        ("default.clock.min-quantum", "clock.min-quantum"),
+        ("default.clock.max-quantum", "clock.max-quantum"),
+    ];
+
+    pub fn get_runtime_key(config_key: &str) -> Option<&'static str> {
+        Self::RUNTIME_KEYS
+            .iter()
+            .find(|(key, _metadata_key)| *key == config_key)
+            .map(|(_key, metadata_key)| *metadata_key)
+    }
+
+    /// Push all pending values with a runtime equivalent to the `settings` metadata.
+    pub fn try_settings(pending: &Map<String, Value>) -> Result<PwTrial> {
+        let current = Self::get_settings()?;
+        let mut trial = PwTrial {
+            previous: Vec::new(),
+        };
+
+        for (key, value) in pending {
+            let Some(metadata_key) = Self::get_runtime_key(key) else {
+                continue;
+            };
+            let previous = current
+                .get(metadata_key)
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string());
+
+            if let Err(error) = Self::set_setting(metadata_key, &Self::value_to_string(value)) {
+                // Restore whatever was pushed before failing
+                if let Err(revert_error) = trial.revert() {
+                    bail!(
+                        "{:#}, and reverting the trial also failed: {:#}",
+                        error,
+                        revert_error
+                    );
+                }
+                return Err(error);
+            }
+            trial.previous.push((metadata_key.to_string(), previous));
+        }
+
+        info!(target: Self::LOG_TARGET, "Trying {} setting(s) live", trial.previous.len());
+
+        Ok(trial)
+    }
+
+    pub fn get_settings() -> Result<Map<String, Value>> {
+        let raw = PwHost::run(
+            PwHost::command("pw-metadata")
+                .arg("-n")
+                .arg(Self::METADATA_NAME),
+        )
+        .context("Reading settings metadata with pw-metadata")?;
+
+        debug!(target: Self::LOG_TARGET, "settings metadata raw:\n{}", raw);
+
+        Ok(Self::parse_metadata(&raw))
+    }
+
+    fn set_setting(metadata_key: &str, value: &str) -> Result<()> {
+        debug!(target: Self::LOG_TARGET, "Setting {} to {}", metadata_key, value);
+
+        PwHost::run(
+            PwHost::command("pw-metadata")
+                .arg("-n")
+                .arg(Self::METADATA_NAME)
+                .arg("0")
+                .arg(metadata_key)
+                .arg(value),
+        )
+        .context(format!("Setting {} with pw-metadata", metadata_key))?;
+
+        Ok(())
+    }
+
+    fn delete_setting(metadata_key: &str) -> Result<()> {
+        debug!(target: Self::LOG_TARGET, "Deleting {}", metadata_key);
+
+        PwHost::run(
+            PwHost::command("pw-metadata")
+                .arg("-n")
+                .arg(Self::METADATA_NAME)
+                .arg("-d")
+                .arg("0")
+                .arg(metadata_key),
+        )
+        .context(format!("Deleting {} with pw-metadata", metadata_key))?;
+
+        Ok(())
+    }
+
+    fn value_to_string(value: &Value) -> String {
+        match value {
+            Value::Number(number) => SpaJson::number_to_string(number),
+            Value::String(string) => string.to_owned(),
+            _ => value.to_string(),
+        }
+    }
+
+    fn parse_metadata(raw: &str) -> Map<String, Value> {
+        let regex_update = Regex::new(r"^update: id:0 key:'([^']*)' value:'([^']*)'").unwrap();
+        let mut map = Map::new();
+
+        for line in raw.lines() {
+            if let Some(captures) = regex_update.captures(line.trim()) {
+                map.insert(
+                    captures[1].to_string(),
+                    Value::String(captures[2].to_string()),
+                );
+            }
+        }
+
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PwMetadata;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn it_should_parse_pw_metadata_output() {
+        let raw = "Found \"settings\" metadata 31\n\
+            update: id:0 key:'log.level' value:'2' type:''\n\
+            update: id:0 key:'clock.rate' value:'48000' type:''\n\
+            update: id:0 key:'clock.allowed-rates' value:'[ 44100 48000 ]' type:''\n\
+            update: id:0 key:'clock.force-quantum' value:'0' type:''\n\
+            update: id:31 key:'other' value:'1' type:''\n";
+        let settings = PwMetadata::parse_metadata(raw);
+
+        assert_eq!(settings.len(), 4);
+        assert_eq!(settings["clock.rate"], json!("48000"));
+        assert_eq!(settings["clock.allowed-rates"], json!("[ 44100 48000 ]"));
+        assert!(!settings.contains_key("other"));
+    }
+
+    #[test]
+    fn it_should_convert_values_for_pw_metadata() {
+        assert_eq!(PwMetadata::value_to_string(&json!(1024)), "1024");
+        assert_eq!(PwMetadata::value_to_string(&json!(48000.0)), "48000");
+        assert_eq!(PwMetadata::value_to_string(&json!("auto")), "auto");
+        assert_eq!(PwMetadata::value_to_string(&Value::Bool(true)), "true");
+    }
+}
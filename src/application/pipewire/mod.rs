@@ -1,2 +1,16 @@
-pub mod pipewire;
+pub mod channel_map;
 pub mod config;
+pub mod diagnostics;
+pub mod dropin;
+pub mod error;
+pub mod history;
+pub mod host;
+pub mod metadata;
+pub mod pending;
+pub mod pipewire;
+pub mod preset;
+pub mod schema;
+pub mod services;
+pub mod spa_json;
+pub mod speaker_test;
+pub mod version;
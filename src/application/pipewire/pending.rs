@@ -0,0 +1,115 @@
+use super::{config::PwConfig, dropin::PwDropIn};
+use crate::config;
+use anyhow::{Context, Result};
+use log::debug;
+use serde_json::{Map, Value};
+use std::{fs, path::PathBuf};
+
+/// Pending values of the command line, kept in a file until they are applied.
+/// The app keeps its pending values in memory instead.
+pub struct PwPending {
+    path: PathBuf,
+}
+impl PwPending {
+    const LOG_TARGET: &str = "PwPending";
+
+    pub fn new() -> Self {
+        let data_home = PwDropIn::get_data_home();
+
+        Self::with_path(data_home.join(config::APP_ID).join("pending.json"))
+    }
+
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Puts the stored values of this config into its pending values.
+    pub fn load(&self, pw_config: &mut PwConfig) -> Result<()> {
+        let stored = self.read()?;
+        if let Some(Value::Object(values)) = stored.get(&pw_config.get_name()) {
+            pw_config.new.extend(values.to_owned());
+        }
+
+        Ok(())
+    }
+
+    /// Stores the pending values of the configs, the file is removed when there are none.
+    pub fn save(&self, configs: &[&PwConfig]) -> Result<()> {
+        let mut stored = Map::new();
+        for pw_config in configs.iter().filter(|pw_config| !pw_config.new.is_empty()) {
+            stored.insert(pw_config.get_name(), Value::Object(pw_config.new.clone()));
+        }
+
+        if stored.is_empty() {
+            if self.path.exists() {
+                fs::remove_file(&self.path).context(format!("Removing {}", self.path.display()))?;
+            }
+            return Ok(());
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).context(format!("Creating {}", dir.display()))?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&stored)?)
+            .context(format!("Writing {}", self.path.display()))?;
+
+        debug!(target: Self::LOG_TARGET, "Stored pending values:\n{:#?}", stored);
+
+        Ok(())
+    }
+
+    fn read(&self) -> Result<Map<String, Value>> {
+        if !self.path.exists() {
+            return Ok(Map::new());
+        }
+
+        let contents =
+            fs::read_to_string(&self.path).context(format!("Reading {}", self.path.display()))?;
+        let stored: Value =
+            serde_json::from_str(&contents).context(format!("Parsing {}", self.path.display()))?;
+
+        match stored {
+            Value::Object(stored) => Ok(stored),
+            _ => Ok(Map::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PwPending;
+    use crate::application::pipewire::config::{
+        PwConfig, PwConfigFile, PwPipewireSection, PwPipewireSectionSub, PwPulseSection,
+        PwPulseSectionSub,
+    };
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn it_should_keep_pending_values_between_runs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let pending = PwPending::with_path(dir.path().join("pending.json"));
+        let mut surround = PwConfig::for_test(PwConfigFile::PipewirePulse(
+            PwPulseSection::StreamProperties(PwPulseSectionSub::None),
+        ));
+        let mut clock = PwConfig::for_test(PwConfigFile::Pipewire(
+            PwPipewireSection::ContextProperties(PwPipewireSectionSub::Clock),
+        ));
+        surround
+            .new
+            .insert("channelmix.upmix".to_string(), json!(true));
+        pending.save(&[&surround, &clock])?;
+
+        surround.new.clear();
+        pending.load(&mut surround)?;
+        pending.load(&mut clock)?;
+        assert_eq!(surround.new.get("channelmix.upmix"), Some(&json!(true)));
+        assert!(clock.new.is_empty());
+
+        surround.new.clear();
+        pending.save(&[&surround, &clock])?;
+        assert!(!dir.path().join("pending.json").exists());
+
+        Ok(())
+    }
+}
@@ -1,16 +1,291 @@
-use super::config::{PwConfig, PwConfigFile, PwPulseSection, PwPulseSectionSub};
-use anyhow::Result;
-use std::{cell::RefCell, rc::Rc};
+use super::{
+    config::{
+        PwApplied, PwConfig, PwConfigFile, PwConflict, PwPipewireSection, PwPipewireSectionSub,
+        PwPulseSection, PwPulseSectionSub, PwWireplumberSection,
+    },
+    dropin::PwDropIn,
+    history::PwHistory,
+    preset::{PwPreset, PwPresets},
+    spa_json::SpaJson,
+    version::{PwCapability, PwVersions},
+};
+use anyhow::{Context, Result, bail};
+use log::{error, warn};
+use std::{
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+pub type PwAppliedConfigs = Vec<(Rc<RefCell<PwConfig>>, PwApplied)>;
+pub type PwConflicts = Vec<(Rc<RefCell<PwConfig>>, PwConflict)>;
+
+/// What loading a config file put into the pending values.
+pub struct PwLoaded {
+    pub file_name: String,
+    /// Keys taken per config
+    pub loaded: Vec<(PwConfigFile, Vec<String>)>,
+    /// Settings in the file that no config takes
+    pub skipped: Vec<String>,
+}
 
 pub struct Pipewire {
-    pub surround: Rc<RefCell<PwConfig>>,
+    /// The configs that could be read, their pages work
+    pub configs: Vec<Rc<RefCell<PwConfig>>>,
+    /// Why the other configs could not be read
+    pub errors: Vec<anyhow::Error>,
+    pub history: PwHistory,
+    pub presets: PwPresets,
+    pub versions: PwVersions,
 }
 impl Pipewire {
+    const LOG_TARGET: &str = "Pipewire";
+    const CONFIG_FILES: [PwConfigFile; 2] = [
+        PwConfigFile::PipewirePulse(PwPulseSection::StreamProperties(PwPulseSectionSub::None)),
+        PwConfigFile::Pipewire(PwPipewireSection::ContextProperties(
+            PwPipewireSectionSub::Clock,
+        )),
+    ];
+
+    /// Reads the configs that can be read, fails when none can.
     pub fn new() -> Result<Rc<Self>> {
-        let surround = PwConfig::new(PwConfigFile::PipewirePulse(
-            PwPulseSection::StreamProperties(PwPulseSectionSub::None),
+        let versions = PwVersions::detect()?;
+
+        let mut configs = Vec::new();
+        let mut errors = Vec::new();
+        for config_file in Self::CONFIG_FILES {
+            let (file_name, section_name, _subsection_name) =
+                PwConfig::get_config_file_and_sections(&config_file);
+            match PwConfig::new(config_file, versions) {
+                Ok(pw_config) => configs.push(pw_config),
+                Err(error) => {
+                    let error = error.context(format!("Reading {} {}", file_name, section_name));
+                    error!(target: Self::LOG_TARGET, "{:?}", error);
+                    errors.push(error);
+                }
+            }
+        }
+        if configs.is_empty() && !errors.is_empty() {
+            return Err(errors.remove(0));
+        }
+
+        // Optional, WirePlumber versions before 0.5 have no `wireplumber.conf`
+        let wireplumber = PwConfigFile::Wireplumber(PwWireplumberSection::Settings);
+        match versions.has(PwCapability::WireplumberSettings) {
+            true => match PwConfig::new(wireplumber, versions) {
+                Ok(wireplumber) => configs.push(wireplumber),
+                Err(error) => {
+                    warn!(target: Self::LOG_TARGET, "WirePlumber settings unavailable:\n{:?}", error);
+                }
+            },
+            false => {
+                warn!(target: Self::LOG_TARGET, "WirePlumber {:?} has no settings in wireplumber.conf", versions.wireplumber);
+            }
+        }
+
+        let mut config_dirs = vec![PwDropIn::get_config_dir("wireplumber.conf")];
+        for pw_config in &configs {
+            for dir in pw_config.borrow().get_user_config_dirs() {
+                if !config_dirs.contains(&dir) {
+                    config_dirs.push(dir);
+                }
+            }
+        }
+        let history = PwHistory::new(config_dirs);
+        let presets = PwPresets::new();
+
+        Ok(Rc::new(Self {
+            configs,
+            errors,
+            history,
+            presets,
+            versions,
+        }))
+    }
+
+    pub fn get_configs(&self) -> Vec<&Rc<RefCell<PwConfig>>> {
+        self.configs.iter().collect()
+    }
+
+    pub fn get_config(&self, config_file: &PwConfigFile) -> Option<&Rc<RefCell<PwConfig>>> {
+        self.get_configs()
+            .into_iter()
+            .find(|pw_config| pw_config.borrow().config_file == *config_file)
+    }
+
+    /// Reloads all configs after the drop-ins changed outside of an apply.
+    pub fn reload(&self) -> Result<()> {
+        for pw_config in self.get_configs() {
+            pw_config.borrow_mut().reload()?;
+        }
+
+        Ok(())
+    }
+
+    /// Reloads all configs after their files changed outside of the app.
+    /// Returns the pending values that differ from a changed value, with their config,
+    /// and why some configs could not be reloaded. Those keep their previous values.
+    pub fn reload_external(&self) -> (PwConflicts, Vec<anyhow::Error>) {
+        let mut conflicts = Vec::new();
+        let mut errors = Vec::new();
+        for pw_config in self.get_configs() {
+            let reloaded = pw_config.borrow_mut().reload_external();
+            match reloaded {
+                Ok(config_conflicts) => conflicts.extend(
+                    config_conflicts
+                        .into_iter()
+                        .map(|conflict| (pw_config.clone(), conflict)),
+                ),
+                Err(error) => {
+                    let error =
+                        error.context(format!("Reloading {}", pw_config.borrow().get_name()));
+                    error!(target: Self::LOG_TARGET, "{:?}", error);
+                    errors.push(error);
+                }
+            }
+        }
+
+        (conflicts, errors)
+    }
+
+    /// Dirs of all config files and drop-ins, without duplicates.
+    pub fn get_watched_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        for pw_config in self.get_configs() {
+            for dir in pw_config.borrow().get_watched_dirs() {
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+
+        dirs
+    }
+
+    /// Puts the values of a config file, e.g. a drop-in from elsewhere,
+    /// into the pending values of the configs it belongs to.
+    pub fn load_file(&self, path: &Path) -> Result<PwLoaded> {
+        let contents = fs::read_to_string(path).context(format!("Reading {}", path.display()))?;
+        let parsed = SpaJson::parse(&contents).context(format!("Parsing {}", path.display()))?;
+        let sections = parsed
+            .as_object()
+            .context(format!("{} has no sections", path.display()))?;
+        let file_name = PwConfig::detect_file_name(path, sections).context(format!(
+            "Could not tell which config file {} belongs to",
+            path.display()
         ))?;
 
-        Ok(Rc::new(Self { surround }))
+        let mut loaded = Vec::new();
+        for pw_config in self.get_configs() {
+            let keys = pw_config.borrow_mut().load(&file_name, sections);
+            if !keys.is_empty() {
+                loaded.push((pw_config.borrow().config_file, keys));
+            }
+        }
+
+        let mut skipped = Vec::new();
+        for (section_name, values) in sections {
+            let is_taken = |key: &str| {
+                loaded.iter().any(|(config_file, keys)| {
+                    let (_file, section, _subsection) =
+                        PwConfig::get_config_file_and_sections(config_file);
+                    section == section_name && keys.iter().any(|taken| taken == key)
+                })
+            };
+
+            match values.as_object() {
+                Some(values) => skipped.extend(
+                    values
+                        .keys()
+                        .filter(|key| !is_taken(key))
+                        .map(|key| format!("{}: {}", section_name, key)),
+                ),
+                // E.g. the modules and rules arrays
+                None => skipped.push(section_name.to_owned()),
+            }
+        }
+
+        if loaded.is_empty() {
+            bail!(
+                "{} has no settings for {} that can be edited here",
+                path.display(),
+                file_name
+            );
+        }
+
+        Ok(PwLoaded {
+            file_name,
+            loaded,
+            skipped,
+        })
+    }
+
+    /// Applies every config with pending values, on failure the applied ones are rolled back.
+    pub fn apply_pending(&self) -> Result<PwAppliedConfigs> {
+        let mut applied_configs: PwAppliedConfigs = Vec::new();
+        for pw_config in self.get_configs() {
+            if pw_config.borrow().new.is_empty() {
+                continue;
+            }
+
+            let applied = pw_config.borrow_mut().apply();
+            match applied {
+                Ok(applied) => applied_configs.push((pw_config.clone(), applied)),
+                Err(error) => {
+                    Self::rollback(applied_configs);
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(applied_configs)
+    }
+
+    /// Replaces the drop-ins of the configs the preset has values for.
+    /// Other configs and those that already match are left alone, on failure the written ones are restored.
+    pub fn activate_preset(&self, preset: &PwPreset) -> Result<PwAppliedConfigs> {
+        let configs = self.get_configs();
+        let config_files: Vec<_> = configs
+            .iter()
+            .map(|pw_config| pw_config.borrow().config_file)
+            .collect();
+
+        let unused_keys = preset.get_unused_keys(&config_files);
+        if !unused_keys.is_empty() {
+            bail!(
+                "Preset {} has settings that can not be applied: {}",
+                preset.name,
+                unused_keys.join(", ")
+            );
+        }
+
+        let mut applied_configs: PwAppliedConfigs = Vec::new();
+        for pw_config in configs {
+            let values = preset.get_values(&pw_config.borrow().config_file);
+            if values.is_empty() {
+                continue;
+            }
+            let activated = pw_config.borrow_mut().activate(values);
+
+            match activated {
+                Ok(Some(applied)) => applied_configs.push((pw_config.clone(), applied)),
+                Ok(None) => {}
+                Err(error) => {
+                    Self::rollback(applied_configs);
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(applied_configs)
+    }
+
+    fn rollback(applied_configs: PwAppliedConfigs) {
+        for (pw_config, applied) in applied_configs {
+            if let Err(error) = pw_config.borrow_mut().rollback(applied) {
+                error!(target: Self::LOG_TARGET, "Rolling back failed:\n{:?}", error);
+            }
+        }
     }
 }
@@ -0,0 +1,585 @@
+use super::{
+    config::{PwConfig, PwConfigFile},
+    dropin::PwDropIn,
+};
+use crate::config;
+use anyhow::{Context, Result, bail};
+use log::{debug, info};
+use serde_json::{Map, Value, json};
+use std::{fs, path::PathBuf};
+
+/// Which state of the configs a preset is created from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PwPresetSource {
+    Pending,
+    Applied,
+}
+
+/// Named values spanning config files, e.g. "Studio low latency".
+#[derive(Clone, PartialEq, Debug)]
+pub struct PwPreset {
+    pub name: String,
+    /// File name to section name to values, e.g. `pipewire.conf` > `context.properties`
+    pub files: Map<String, Value>,
+}
+impl PwPreset {
+    pub fn from_configs(name: &str, configs: &[&PwConfig], source: PwPresetSource) -> Result<Self> {
+        let mut files = Map::new();
+
+        for pw_config in configs {
+            let (file_name, section_name, _subsection_name) =
+                PwConfig::get_config_file_and_sections(&pw_config.config_file);
+            let values = match source {
+                PwPresetSource::Pending => pw_config.new.clone(),
+                PwPresetSource::Applied => pw_config.get_applied()?,
+            };
+            if values.is_empty() {
+                continue;
+            }
+
+            let sections = files
+                .entry(file_name)
+                .or_insert_with(|| Value::Object(Map::new()));
+            let section = sections
+                .as_object_mut()
+                .unwrap()
+                .entry(section_name)
+                .or_insert_with(|| Value::Object(Map::new()));
+            section.as_object_mut().unwrap().extend(values);
+        }
+
+        if files.is_empty() {
+            bail!(
+                "There are no {} settings to save",
+                match source {
+                    PwPresetSource::Pending => "pending",
+                    PwPresetSource::Applied => "applied",
+                }
+            );
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            files,
+        })
+    }
+
+    /// Values of this preset for one config, only the keys of its subsection.
+    pub fn get_values(&self, config_file: &PwConfigFile) -> Map<String, Value> {
+        let (file_name, section_name, subsection_name) =
+            PwConfig::get_config_file_and_sections(config_file);
+        let Some(Value::Object(values)) = self
+            .files
+            .get(file_name)
+            .and_then(|sections| sections.get(section_name))
+        else {
+            return Map::new();
+        };
+
+        let mut values = values.to_owned();
+        if let Some(subsection) = subsection_name {
+            values.retain(|key, _value| key.starts_with(&format!("{}.", subsection)));
+        }
+
+        values
+    }
+
+    /// Keys of this preset that none of the configs take.
+    pub fn get_unused_keys(&self, config_files: &[PwConfigFile]) -> Vec<String> {
+        let mut unused = Vec::new();
+
+        for (file_name, sections) in &self.files {
+            let Some(sections) = sections.as_object() else {
+                continue;
+            };
+            for (section_name, values) in sections {
+                let Some(values) = values.as_object() else {
+                    continue;
+                };
+                for key in values.keys() {
+                    let is_used = config_files.iter().any(|config_file| {
+                        let (file, section, _subsection) =
+                            PwConfig::get_config_file_and_sections(config_file);
+                        file == file_name
+                            && section == section_name
+                            && self.get_values(config_file).contains_key(key)
+                    });
+                    if !is_used {
+                        unused.push(format!("{} {}: {}", file_name, section_name, key));
+                    }
+                }
+            }
+        }
+
+        unused
+    }
+
+    /// Short description of the values, e.g. for a row subtitle.
+    pub fn get_summary(&self) -> String {
+        let mut summaries = Vec::new();
+
+        for (file_name, sections) in &self.files {
+            for (section_name, values) in sections.as_object().into_iter().flatten() {
+                let values: Vec<String> = values
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(|(key, value)| format!("{} = {}", key, value))
+                    .collect();
+                summaries.push(format!(
+                    "{} {}: {}",
+                    file_name,
+                    section_name,
+                    values.join(", ")
+                ));
+            }
+        }
+
+        summaries.join("; ")
+    }
+
+    /// Values that differ from the current values of the configs.
+    pub fn get_changes(&self, configs: &[&PwConfig]) -> Vec<PwPresetChange> {
+        let mut changes = Vec::new();
+
+        for pw_config in configs {
+            let (file_name, _section_name, _subsection_name) =
+                PwConfig::get_config_file_and_sections(&pw_config.config_file);
+
+            for (key, value) in self.get_values(&pw_config.config_file) {
+                let current = pw_config.current.get(&key).cloned();
+                if current.as_ref() == Some(&value) {
+                    continue;
+                }
+
+                changes.push(PwPresetChange {
+                    file: file_name.to_string(),
+                    key,
+                    current,
+                    new: value,
+                });
+            }
+        }
+
+        changes
+    }
+
+    /// Splits off the keys the configs on this machine do not know, e.g. from another version.
+    pub fn split_unknown(&self, configs: &[&PwConfig]) -> (Self, Vec<String>) {
+        let mut known = Self {
+            name: self.name.clone(),
+            files: Map::new(),
+        };
+        let config_files: Vec<_> = configs
+            .iter()
+            .map(|pw_config| pw_config.config_file)
+            .collect();
+        let mut unknown = self.get_unused_keys(&config_files);
+
+        for pw_config in configs {
+            let (file_name, section_name, _subsection_name) =
+                PwConfig::get_config_file_and_sections(&pw_config.config_file);
+
+            for (key, value) in self.get_values(&pw_config.config_file) {
+                if !pw_config.default.contains_key(&key) && !pw_config.current.contains_key(&key) {
+                    unknown.push(format!("{} {}: {}", file_name, section_name, key));
+                    continue;
+                }
+
+                let sections = known
+                    .files
+                    .entry(file_name)
+                    .or_insert_with(|| Value::Object(Map::new()));
+                let section = sections
+                    .as_object_mut()
+                    .unwrap()
+                    .entry(section_name)
+                    .or_insert_with(|| Value::Object(Map::new()));
+                section.as_object_mut().unwrap().insert(key, value);
+            }
+        }
+
+        (known, unknown)
+    }
+
+    /// File name of the preset, e.g. `studio-low-latency.json`.
+    pub fn get_file_name(name: &str) -> String {
+        let file_name: String = name
+            .trim()
+            .chars()
+            .map(|char| match char.is_alphanumeric() {
+                true => char.to_ascii_lowercase(),
+                false => '-',
+            })
+            .collect();
+
+        format!("{}.json", file_name)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "files": self.files,
+        })
+    }
+
+    pub fn from_json(json: &Value) -> Result<Self> {
+        let name = json["name"].as_str().context("Preset has no name")?;
+        let files = json["files"]
+            .as_object()
+            .context("Preset has no files")?
+            .to_owned();
+
+        Ok(Self {
+            name: name.to_string(),
+            files,
+        })
+    }
+}
+
+/// Local store of the user's presets, one json file per preset.
+pub struct PwPresets {
+    dir: PathBuf,
+}
+impl PwPresets {
+    const LOG_TARGET: &str = "PwPresets";
+
+    pub fn new() -> Self {
+        Self::with_dir(
+            PwDropIn::get_data_home()
+                .join(config::APP_ID)
+                .join("presets"),
+        )
+    }
+
+    pub fn with_dir(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Presets sorted by name.
+    pub fn list(&self) -> Result<Vec<PwPreset>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut presets = Vec::new();
+        let entries =
+            fs::read_dir(&self.dir).context(format!("Reading presets {}", self.dir.display()))?;
+
+        for entry in entries {
+            let path = entry?.path();
+            let preset = fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Ok(serde_json::from_str(&contents)?))
+                .and_then(|json| PwPreset::from_json(&json));
+            match preset {
+                Ok(preset) => presets.push(preset),
+                Err(error) => {
+                    debug!(target: Self::LOG_TARGET, "Skipping {}: {:?}", path.display(), error)
+                }
+            }
+        }
+        presets.sort_by_key(|preset| preset.name.to_lowercase());
+
+        Ok(presets)
+    }
+
+    pub fn get(&self, name: &str) -> Result<PwPreset> {
+        self.list()?
+            .into_iter()
+            .find(|preset| preset.name == name)
+            .context(format!("Preset {} does not exist", name))
+    }
+
+    /// Saves the preset, replacing one with the same name.
+    /// Fails when another preset has the same file name, e.g. "Studio!" and "Studio?".
+    pub fn save(&self, preset: &PwPreset) -> Result<()> {
+        if preset.name.trim().is_empty() {
+            bail!("A preset needs a name");
+        }
+
+        let path = self.get_path(&preset.name);
+        if path.exists() {
+            let existing = fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Ok(serde_json::from_str(&contents)?))
+                .and_then(|json| PwPreset::from_json(&json))
+                .context(format!("Reading preset {}", path.display()))?;
+            if existing.name != preset.name {
+                bail!(
+                    "The name {} is too close to the preset {}, choose another name",
+                    preset.name,
+                    existing.name
+                );
+            }
+        }
+
+        let contents = serde_json::to_string_pretty(&preset.to_json())?;
+        PwDropIn::write_atomic(&path, &contents)?;
+
+        info!(target: Self::LOG_TARGET, "Saved preset {}", preset.name);
+
+        Ok(())
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let path = self.get_path(name);
+        fs::remove_file(&path).context(format!("Removing preset {}", path.display()))?;
+
+        info!(target: Self::LOG_TARGET, "Deleted preset {}", name);
+
+        Ok(())
+    }
+
+    fn get_path(&self, name: &str) -> PathBuf {
+        self.dir.join(PwPreset::get_file_name(name))
+    }
+}
+
+/// A value the import of a preset would change on this machine.
+#[derive(Debug, PartialEq)]
+pub struct PwPresetChange {
+    pub file: String,
+    pub key: String,
+    pub current: Option<Value>,
+    pub new: Value,
+}
+
+/// A preset as a portable file, to share between machines:
+///
+/// ```json
+/// {
+///   "format": "pipewire-config-gnome-preset",
+///   "version": 1,
+///   "name": "Studio low latency",
+///   "author": "Jane Doe",
+///   "pipewire_version": "1.2.7",
+///   "target_files": ["pipewire.conf"],
+///   "files": {
+///     "pipewire.conf": { "context.properties": { "default.clock.quantum": 64 } }
+///   }
+/// }
+/// ```
+///
+/// `files` holds the values per config file and section like a drop-in would,
+/// `target_files` lists its keys so the file can be checked before importing.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PwPresetBundle {
+    pub preset: PwPreset,
+    pub author: String,
+    /// Version of the PipeWire the preset was exported from
+    pub pipewire_version: String,
+}
+impl PwPresetBundle {
+    const FORMAT: &str = "pipewire-config-gnome-preset";
+    const VERSION: u64 = 1;
+
+    pub fn to_string(&self) -> Result<String> {
+        let bundle = json!({
+            "format": Self::FORMAT,
+            "version": Self::VERSION,
+            "name": self.preset.name,
+            "author": self.author,
+            "pipewire_version": self.pipewire_version,
+            "target_files": self.preset.files.keys().collect::<Vec<_>>(),
+            "files": self.preset.files,
+        });
+
+        Ok(serde_json::to_string_pretty(&bundle)?)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        let json: Value = serde_json::from_str(contents).context("Preset file is not json")?;
+
+        if json["format"].as_str() != Some(Self::FORMAT) {
+            bail!("Not a preset file, format should be {}", Self::FORMAT);
+        }
+        match json["version"].as_u64() {
+            Some(version) if version <= Self::VERSION => {}
+            version => bail!("Unsupported preset file version {:?}", version),
+        }
+
+        let preset = PwPreset::from_json(&json)?;
+        for (file_name, sections) in &preset.files {
+            let is_valid = sections
+                .as_object()
+                .is_some_and(|sections| sections.values().all(|values| values.is_object()));
+            if !is_valid {
+                bail!("{} should map sections to values", file_name);
+            }
+        }
+
+        let mut target_files: Vec<&str> = json["target_files"]
+            .as_array()
+            .context("Preset file has no target files")?
+            .iter()
+            .filter_map(|file| file.as_str())
+            .collect();
+        let mut files: Vec<&str> = preset.files.keys().map(|file| file.as_str()).collect();
+        target_files.sort();
+        files.sort();
+        if target_files != files {
+            bail!(
+                "Target files {:?} do not match the files in the preset {:?}",
+                target_files,
+                files
+            );
+        }
+
+        Ok(Self {
+            preset,
+            author: json["author"].as_str().unwrap_or_default().to_string(),
+            pipewire_version: json["pipewire_version"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PwPreset, PwPresetBundle, PwPresetChange, PwPresetSource, PwPresets};
+    use crate::application::pipewire::config::{
+        PwConfig, PwConfigFile, PwPipewireSection, PwPipewireSectionSub, PwPulseSection,
+        PwPulseSectionSub,
+    };
+    use anyhow::Result;
+    use serde_json::json;
+
+    fn build_config(config_file: PwConfigFile, new: serde_json::Value) -> PwConfig {
+        PwConfig {
+            new: new.as_object().unwrap().to_owned(),
+            ..PwConfig::for_test(config_file)
+        }
+    }
+
+    #[test]
+    fn it_should_create_from_pending_and_split_per_config() -> Result<()> {
+        let clock_file = PwConfigFile::Pipewire(PwPipewireSection::ContextProperties(
+            PwPipewireSectionSub::Clock,
+        ));
+        let pulse_file =
+            PwConfigFile::PipewirePulse(PwPulseSection::StreamProperties(PwPulseSectionSub::None));
+        let clock = build_config(clock_file, json!({ "default.clock.quantum": 64 }));
+        let pulse = build_config(pulse_file, json!({ "channelmix.upmix": true }));
+
+        let preset = PwPreset::from_configs("Studio", &[&clock, &pulse], PwPresetSource::Pending)?;
+
+        assert_eq!(
+            preset.get_values(&clock_file),
+            json!({ "default.clock.quantum": 64 })
+                .as_object()
+                .unwrap()
+                .to_owned()
+        );
+        assert!(preset.get_unused_keys(&[clock_file, pulse_file]).is_empty());
+        assert_eq!(
+            preset.get_unused_keys(&[clock_file]),
+            ["pipewire-pulse.conf stream.properties: channelmix.upmix"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_save_list_and_delete() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let presets = PwPresets::with_dir(tmp.path().to_owned());
+        let preset = PwPreset::from_json(&json!({
+            "name": "Battery saver",
+            "files": { "pipewire.conf": { "context.properties": { "default.clock.quantum": 2048 } } },
+        }))?;
+
+        presets.save(&preset)?;
+        assert_eq!(presets.list()?.len(), 1);
+        assert_eq!(presets.get("Battery saver")?, preset);
+
+        presets.delete("Battery saver")?;
+        assert!(presets.list()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_not_overwrite_a_preset_with_the_same_file_name() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let presets = PwPresets::with_dir(tmp.path().to_owned());
+        let files =
+            json!({ "pipewire.conf": { "context.properties": { "default.clock.quantum": 64 } } });
+        let studio = PwPreset::from_json(&json!({ "name": "Studio!", "files": files }))?;
+        let other = PwPreset::from_json(&json!({ "name": "Studio?", "files": files }))?;
+
+        presets.save(&studio)?;
+        presets.save(&studio)?;
+        assert!(presets.save(&other).is_err());
+        assert_eq!(presets.list()?, [studio]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_export_and_import_a_bundle() -> Result<()> {
+        let bundle = PwPresetBundle {
+            preset: PwPreset::from_json(&json!({
+                "name": "Studio",
+                "files": { "pipewire.conf": { "context.properties": { "default.clock.quantum": 64 } } },
+            }))?,
+            author: "Jane Doe".into(),
+            pipewire_version: "1.2.7".into(),
+        };
+
+        let parsed = PwPresetBundle::parse(&bundle.to_string()?)?;
+        assert_eq!(parsed, bundle);
+
+        assert!(PwPresetBundle::parse(r#"{ "name": "Studio", "files": {} }"#).is_err());
+        assert!(
+            PwPresetBundle::parse(
+                r#"{ "format": "pipewire-config-gnome-preset", "version": 1, "name": "Studio",
+                     "target_files": ["pipewire-pulse.conf"],
+                     "files": { "pipewire.conf": { "context.properties": {} } } }"#
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_split_unknown_keys_and_list_changes() -> Result<()> {
+        let clock_file = PwConfigFile::Pipewire(PwPipewireSection::ContextProperties(
+            PwPipewireSectionSub::Clock,
+        ));
+        let mut clock = build_config(clock_file, json!({}));
+        clock.current = json!({ "default.clock.quantum": 1024, "default.clock.rate": 48000 })
+            .as_object()
+            .unwrap()
+            .to_owned();
+        let preset = PwPreset::from_json(&json!({
+            "name": "Studio",
+            "files": {
+                "pipewire.conf": { "context.properties": {
+                    "default.clock.quantum": 64,
+                    "default.clock.rate": 48000,
+                    "default.clock.unknown": 1,
+                } },
+                "client.conf": { "stream.properties": { "node.latency": "64/48000" } },
+            },
+        }))?;
+
+        let (known, unknown) = preset.split_unknown(&[&clock]);
+        assert_eq!(
+            unknown,
+            [
+                "client.conf stream.properties: node.latency",
+                "pipewire.conf context.properties: default.clock.unknown",
+            ]
+        );
+        assert_eq!(
+            known.get_changes(&[&clock]),
+            [PwPresetChange {
+                file: "pipewire.conf".into(),
+                key: "default.clock.quantum".into(),
+                current: Some(json!(1024)),
+                new: json!(64),
+            }]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,364 @@
+use super::version::PwVersion;
+use crate::i18n::{gettext, gettext_f};
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, sync::OnceLock};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PwPropertyType {
+    Bool,
+    Int,
+    Float,
+    String,
+    Enum(Vec<String>),
+    /// Items of this type, the range and unit are for the items
+    Array(Box<PwPropertyType>),
+}
+
+/// What values a property takes, from the bundled `schema.json`.
+#[derive(Clone, Debug)]
+pub struct PwProperty {
+    pub kind: PwPropertyType,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub step: f64,
+    pub unit: Option<String>,
+    /// Row title in English, the msgid of its translation
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// First version of the program reading the config that has the property
+    pub since: Option<PwVersion>,
+    /// Only shown when advanced settings are turned on in the preferences
+    pub advanced: bool,
+    pattern: Option<Regex>,
+}
+impl PwProperty {
+    /// A property of which only the type is known, e.g. guessed from a default value.
+    pub fn from_kind(kind: PwPropertyType) -> Self {
+        let step = match kind {
+            PwPropertyType::Float => 0.1,
+            _ => 1.0,
+        };
+
+        Self {
+            kind,
+            min: None,
+            max: None,
+            step,
+            unit: None,
+            title: None,
+            description: None,
+            since: None,
+            advanced: false,
+            pattern: None,
+        }
+    }
+
+    /// The property of an array's items.
+    pub fn get_item_property(&self) -> Option<Self> {
+        match &self.kind {
+            PwPropertyType::Array(kind) => Some(Self {
+                kind: kind.as_ref().clone(),
+                ..self.clone()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Decimals shown for the step, e.g. 2 for `0.01` and 0 for ints.
+    pub fn get_digits(&self) -> u32 {
+        if self.kind != PwPropertyType::Float {
+            return 0;
+        }
+
+        let mut digits = 0;
+        let mut step = self.step;
+        while step.fract().abs() > 1e-9 && digits < 6 {
+            step *= 10.0;
+            digits += 1;
+        }
+
+        digits
+    }
+
+    /// Why the value does not fit this property.
+    pub fn validate(&self, value: &Value) -> Result<(), String> {
+        match &self.kind {
+            PwPropertyType::Bool => match value.is_boolean() {
+                true => Ok(()),
+                false => Err(gettext("should be true or false")),
+            },
+            PwPropertyType::Int => match value.as_f64() {
+                Some(number) if number.fract() == 0.0 => self.validate_range(number),
+                _ => Err(gettext("should be a whole number")),
+            },
+            PwPropertyType::Float => match value.as_f64() {
+                Some(number) => self.validate_range(number),
+                None => Err(gettext("should be a number")),
+            },
+            PwPropertyType::String => match (value.as_str(), &self.pattern) {
+                (None, _) => Err(gettext("should be text")),
+                (Some(text), Some(pattern)) if !pattern.is_match(text) => {
+                    Err(gettext_f("should match {}", &[pattern.as_str()]))
+                }
+                (Some(_), _) => Ok(()),
+            },
+            PwPropertyType::Enum(values) => match value.as_str() {
+                Some(text) if values.iter().any(|value| value == text) => Ok(()),
+                _ => Err(gettext_f("should be one of: {}", &[&values.join(", ")])),
+            },
+            PwPropertyType::Array(_kind) => {
+                let (Some(items), Some(item_property)) =
+                    (value.as_array(), self.get_item_property())
+                else {
+                    return Err(gettext("should be a list"));
+                };
+
+                for (index, item) in items.iter().enumerate() {
+                    item_property.validate(item).map_err(|reason| {
+                        gettext_f("item {} {}", &[&(index + 1).to_string(), &reason])
+                    })?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn validate_range(&self, number: f64) -> Result<(), String> {
+        let unit = self
+            .unit
+            .as_ref()
+            .map_or(String::new(), |unit| format!(" {}", unit));
+
+        if let Some(min) = self.min.filter(|min| number < *min) {
+            return Err(gettext_f(
+                "should be at least {}{}",
+                &[&min.to_string(), &unit],
+            ));
+        }
+        if let Some(max) = self.max.filter(|max| number > *max) {
+            return Err(gettext_f(
+                "should be at most {}{}",
+                &[&max.to_string(), &unit],
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn parse_kind(
+        key: &str,
+        kind: Option<&str>,
+        json: &Map<String, Value>,
+    ) -> Result<PwPropertyType> {
+        let kind = match kind {
+            Some("bool") => PwPropertyType::Bool,
+            Some("int") => PwPropertyType::Int,
+            Some("float") => PwPropertyType::Float,
+            Some("string") => PwPropertyType::String,
+            Some("enum") => {
+                let values = json
+                    .get("values")
+                    .and_then(|values| values.as_array())
+                    .context(format!("{} should list its values", key))?
+                    .iter()
+                    .filter_map(|value| value.as_str().map(|value| value.to_string()))
+                    .collect();
+                PwPropertyType::Enum(values)
+            }
+            kind => bail!("{} has an unknown type: {:?}", key, kind),
+        };
+
+        Ok(kind)
+    }
+
+    fn from_json(key: &str, json: &Value) -> Result<Self> {
+        let json = json
+            .as_object()
+            .context(format!("{} should be an object", key))?;
+        let get_number = |name: &str| json.get(name).and_then(|value| value.as_f64());
+        let get_string = |name: &str| {
+            json.get(name)
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string())
+        };
+
+        let kind = match get_string("type").as_deref() {
+            Some("array") => {
+                let items = get_string("items");
+                PwPropertyType::Array(Box::new(Self::parse_kind(key, items.as_deref(), json)?))
+            }
+            kind => Self::parse_kind(key, kind, json)?,
+        };
+        let default_step = match kind {
+            PwPropertyType::Float => 0.1,
+            PwPropertyType::Array(ref kind) if **kind == PwPropertyType::Float => 0.1,
+            _ => 1.0,
+        };
+        let pattern = match get_string("pattern") {
+            Some(pattern) => Some(Regex::new(&pattern).context(format!("{} pattern", key))?),
+            None => None,
+        };
+
+        Ok(Self {
+            kind,
+            min: get_number("min"),
+            max: get_number("max"),
+            step: get_number("step").unwrap_or(default_step),
+            unit: get_string("unit"),
+            title: get_string("title"),
+            description: get_string("description"),
+            since: get_string("since").and_then(|since| PwVersion::parse(&since)),
+            advanced: json
+                .get("advanced")
+                .and_then(|advanced| advanced.as_bool())
+                .unwrap_or_default(),
+            pattern,
+        })
+    }
+}
+
+/// Types, ranges and units of known properties, so rows and pending values can be checked.
+/// Properties that are not in the schema are accepted as they are.
+pub struct PwSchema {
+    properties: HashMap<String, PwProperty>,
+}
+impl PwSchema {
+    const BUNDLED: &str = include_str!("schema.json");
+
+    pub fn bundled() -> &'static Self {
+        static SCHEMA: OnceLock<PwSchema> = OnceLock::new();
+
+        SCHEMA.get_or_init(|| Self::parse(Self::BUNDLED).expect("Bundled schema should be valid"))
+    }
+
+    pub fn parse(json: &str) -> Result<Self> {
+        let json: Value = serde_json::from_str(json).context("Parsing schema")?;
+        let properties = json
+            .as_object()
+            .context("Schema should be an object")?
+            .iter()
+            .map(|(key, property)| Ok((key.to_owned(), PwProperty::from_json(key, property)?)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self { properties })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&PwProperty> {
+        self.properties.get(key)
+    }
+
+    pub fn validate(&self, key: &str, value: &Value) -> Result<(), String> {
+        match self.get(key) {
+            Some(property) => property.validate(value),
+            None => Ok(()),
+        }
+    }
+
+    /// `key: reason` for every value that does not fit its property.
+    pub fn get_invalid(&self, values: &Map<String, Value>) -> Vec<String> {
+        let mut invalid: Vec<String> = values
+            .iter()
+            .filter_map(|(key, value)| {
+                self.validate(key, value)
+                    .err()
+                    .map(|reason| format!("{}: {}", key, reason))
+            })
+            .collect();
+        invalid.sort();
+
+        invalid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PwPropertyType, PwSchema};
+    use crate::application::pipewire::version::PwVersion;
+    use serde_json::json;
+
+    #[test]
+    fn it_should_parse_bundled_schema() {
+        let schema = PwSchema::bundled();
+
+        let quality = schema.get("resample.quality").unwrap();
+        assert_eq!(quality.kind, PwPropertyType::Int);
+        assert_eq!((quality.min, quality.max), (Some(0.0), Some(14.0)));
+        assert_eq!(quality.get_digits(), 0);
+        assert_eq!(quality.since, None);
+        assert!(!quality.advanced);
+        assert_eq!(quality.title.as_deref(), Some("Resampling quality"));
+        assert!(
+            schema
+                .properties
+                .values()
+                .all(|property| property.title.is_some())
+        );
+        assert!(schema.get("log.level").unwrap().advanced);
+        assert_eq!(
+            schema.get("default.clock.quantum-floor").unwrap().since,
+            Some(PwVersion::new(1, 0, 0))
+        );
+
+        let lfe_cutoff = schema.get("channelmix.lfe-cutoff").unwrap();
+        assert_eq!(lfe_cutoff.unit.as_deref(), Some("Hz"));
+        assert_eq!(lfe_cutoff.get_digits(), 0);
+        assert_eq!(
+            schema.get("channelmix.stereo-widen").unwrap().get_digits(),
+            2
+        );
+    }
+
+    #[test]
+    fn it_should_validate_values() {
+        let schema = PwSchema::bundled();
+
+        assert!(schema.validate("resample.quality", &json!(4)).is_ok());
+        assert!(schema.validate("resample.quality", &json!(4.0)).is_ok());
+        assert!(schema.validate("resample.quality", &json!(-3.7)).is_err());
+        assert!(schema.validate("resample.quality", &json!(15)).is_err());
+        assert!(schema.validate("channelmix.upmix", &json!("yes")).is_err());
+        assert!(
+            schema
+                .validate("channelmix.upmix-method", &json!("psd"))
+                .is_ok()
+        );
+        assert!(
+            schema
+                .validate("channelmix.upmix-method", &json!("fancy"))
+                .is_err()
+        );
+        assert!(
+            schema
+                .validate("node.latency", &json!("1024/48000"))
+                .is_ok()
+        );
+        assert!(schema.validate("node.latency", &json!("1024")).is_err());
+        assert!(schema.validate("unknown.property", &json!(-3.7)).is_ok());
+        assert!(
+            schema
+                .validate("default.clock.allowed-rates", &json!([44100, 48000]))
+                .is_ok()
+        );
+        assert!(
+            schema
+                .validate("default.clock.allowed-rates", &json!([48000, 100]))
+                .is_err()
+        );
+        assert!(
+            schema
+                .validate("audio.position", &json!(["FL", "FR"]))
+                .is_ok()
+        );
+        assert!(schema.validate("audio.position", &json!("FL,FR")).is_err());
+
+        let invalid = schema.get_invalid(
+            json!({ "resample.quality": 20, "channelmix.normalize": true })
+                .as_object()
+                .unwrap(),
+        );
+        assert_eq!(invalid, ["resample.quality: should be at most 14"]);
+    }
+}
@@ -0,0 +1,415 @@
+use super::{
+    config::{PwConfig, PwConfigFile},
+    host::PwHost,
+};
+use anyhow::{Context, Result, bail};
+use log::{debug, info};
+use serde_json::Value;
+use std::{env, os::unix::net::UnixStream, path::PathBuf};
+use zbus::{Connection, proxy, proxy::CacheProperties, zvariant::OwnedObjectPath};
+
+#[proxy(
+    interface = "org.freedesktop.systemd1.Manager",
+    default_service = "org.freedesktop.systemd1",
+    default_path = "/org/freedesktop/systemd1"
+)]
+trait SystemdManager {
+    fn get_unit(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
+
+    fn restart_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.systemd1.Unit",
+    default_service = "org.freedesktop.systemd1"
+)]
+trait SystemdUnit {
+    #[zbus(property)]
+    fn active_state(&self) -> zbus::Result<String>;
+}
+
+/// A client connected to PipeWire, as listed by `pw-dump`.
+#[derive(Debug, PartialEq)]
+pub struct PwClient {
+    pub id: u64,
+    pub name: String,
+    pub api: Option<String>,
+}
+
+/// Restarts the PipeWire services through the systemd user manager.
+pub struct PwServices {
+    connection: Connection,
+}
+impl PwServices {
+    const LOG_TARGET: &str = "PwServices";
+    const PIPEWIRE: &str = "pipewire.service";
+    const PIPEWIRE_PULSE: &str = "pipewire-pulse.service";
+    const WIREPLUMBER: &str = "wireplumber.service";
+    /// The units of a PipeWire session, in start order
+    pub const UNITS: [&str; 3] = [Self::PIPEWIRE, Self::WIREPLUMBER, Self::PIPEWIRE_PULSE];
+    /// Clients that are the services themselves
+    const SERVICE_BINARIES: [&str; 5] = [
+        "pipewire",
+        "pipewire-pulse",
+        "wireplumber",
+        "pw-dump",
+        "pipewire-config-gui-gnome",
+    ];
+
+    /// Connects to the systemd user manager on the session bus.
+    pub async fn connect() -> Result<Self> {
+        let connection = Connection::session()
+            .await
+            .context("Connecting to the session bus")?;
+
+        Ok(Self::with_connection(connection))
+    }
+
+    pub fn with_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+
+    /// Units that have to restart for changes in these config files, in restart order.
+    pub fn get_affected_units(files: &[PwConfigFile]) -> Vec<&'static str> {
+        let mut units = Vec::new();
+
+        for file in files {
+            let file_units: &[&str] = match PwConfig::get_config_file_and_sections(file).0 {
+                // Everything else is a client of the pipewire daemon
+                "pipewire.conf" => &[Self::PIPEWIRE, Self::WIREPLUMBER, Self::PIPEWIRE_PULSE],
+                "pipewire-pulse.conf" => &[Self::PIPEWIRE_PULSE],
+                _ => &[Self::WIREPLUMBER],
+            };
+
+            for unit in file_units {
+                if !units.contains(unit) {
+                    units.push(*unit);
+                }
+            }
+        }
+
+        units.sort_by_key(|unit| Self::UNITS.iter().position(|ordered| ordered == unit));
+
+        units
+    }
+
+    /// Clients that lose their connection when these units restart.
+    pub fn get_interrupted_clients(units: &[&str]) -> Result<Vec<PwClient>> {
+        let output = PwHost::run(PwHost::command("pw-dump").arg("-N"))
+            .context("Listing clients with pw-dump")?;
+
+        let clients = Self::parse_clients(&output)?;
+
+        Ok(clients
+            .into_iter()
+            .filter(|client| {
+                units.contains(&Self::PIPEWIRE)
+                    || (units.contains(&Self::PIPEWIRE_PULSE)
+                        && client.api.as_deref() == Some("pipewire-pulse"))
+            })
+            .collect())
+    }
+
+    /// `ActiveState` of a unit, `inactive` when it is not loaded.
+    pub async fn get_unit_state(&self, unit: &str) -> Result<String> {
+        let manager = SystemdManagerProxy::new(&self.connection).await?;
+
+        let unit_path = match manager.get_unit(unit).await {
+            Ok(unit_path) => unit_path,
+            Err(zbus::Error::MethodError(name, _, _))
+                if name.as_str() == "org.freedesktop.systemd1.NoSuchUnit" =>
+            {
+                return Ok(String::from("inactive"));
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let unit_proxy = SystemdUnitProxy::builder(&self.connection)
+            .path(unit_path)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+
+        Ok(unit_proxy.active_state().await?)
+    }
+
+    /// Restarts the units that are running. Returns the restarted units.
+    pub async fn restart(&self, units: &[&str]) -> Result<Vec<String>> {
+        let manager = SystemdManagerProxy::new(&self.connection).await?;
+        // Checked before any restart, restarting pipewire also bounces the units bound to it
+        let mut running = Vec::new();
+        for unit in units {
+            let state = self.get_unit_state(unit).await?;
+            match state.as_str() {
+                "active" => running.push(*unit),
+                _ => debug!(target: Self::LOG_TARGET, "Not restarting {}, it is {}", unit, state),
+            }
+        }
+
+        let mut restarted = Vec::new();
+        for unit in running {
+            manager
+                .restart_unit(unit, "replace")
+                .await
+                .context(format!("Restarting {}", unit))?;
+            restarted.push(unit.to_string());
+
+            info!(target: Self::LOG_TARGET, "Queued restart of {}", unit);
+        }
+
+        Ok(restarted)
+    }
+
+    /// `Ok(true)` when the units are running again, `Ok(false)` while they are still (re)starting.
+    pub async fn check_health(&self, units: &[String]) -> Result<bool> {
+        for unit in units {
+            match self.get_unit_state(unit).await?.as_str() {
+                "active" => {}
+                "activating" | "deactivating" | "reloading" => return Ok(false),
+                state => bail!("{} is {} after restarting", unit, state),
+            }
+        }
+
+        if units.iter().any(|unit| unit == Self::PIPEWIRE_PULSE) {
+            Self::check_pulse_socket()?;
+        }
+
+        Ok(true)
+    }
+
+    fn check_pulse_socket() -> Result<()> {
+        let runtime_path = match env::var_os("PULSE_RUNTIME_PATH") {
+            Some(runtime_path) => PathBuf::from(runtime_path),
+            None => PathBuf::from(env::var_os("XDG_RUNTIME_DIR").context("No XDG_RUNTIME_DIR")?)
+                .join("pulse"),
+        };
+        let socket = runtime_path.join("native");
+
+        UnixStream::connect(&socket).context(format!(
+            "pipewire-pulse is not answering on {}",
+            socket.display()
+        ))?;
+
+        Ok(())
+    }
+
+    fn parse_clients(pw_dump: &str) -> Result<Vec<PwClient>> {
+        let objects: Value = serde_json::from_str(pw_dump).context("Parsing pw-dump output")?;
+        let mut clients = Vec::new();
+
+        for object in objects
+            .as_array()
+            .context("pw-dump output should be an array")?
+        {
+            if object["type"] != "PipeWire:Interface:Client" {
+                continue;
+            }
+
+            let props = &object["info"]["props"];
+            let binary = props["application.process.binary"].as_str().unwrap_or("");
+            if Self::SERVICE_BINARIES.contains(&binary) {
+                continue;
+            }
+
+            let name = props["application.name"]
+                .as_str()
+                .or(props["application.process.binary"].as_str())
+                .unwrap_or("Unknown client");
+
+            clients.push(PwClient {
+                id: object["id"].as_u64().unwrap_or_default(),
+                name: name.to_string(),
+                api: props["client.api"].as_str().map(|api| api.to_string()),
+            });
+        }
+
+        Ok(clients)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PwServices;
+    use crate::application::pipewire::config::{
+        PwConfigFile, PwPipewireSection, PwPipewireSectionSub, PwPulseSection, PwPulseSectionSub,
+    };
+    use anyhow::Result;
+    use std::{
+        collections::HashMap,
+        os::unix::net::UnixStream,
+        sync::{Arc, Mutex},
+    };
+    use zbus::{
+        DBusError, Guid, connection, interface,
+        zvariant::{ObjectPath, OwnedObjectPath},
+    };
+
+    #[derive(DBusError, Debug)]
+    #[zbus(prefix = "org.freedesktop.systemd1")]
+    enum FakeError {
+        #[zbus(error)]
+        ZBus(zbus::Error),
+        NoSuchUnit(String),
+    }
+
+    /// Stand-in for the systemd user manager
+    struct FakeManager {
+        units: HashMap<String, String>,
+        restarted: Arc<Mutex<Vec<String>>>,
+    }
+    #[interface(name = "org.freedesktop.systemd1.Manager")]
+    impl FakeManager {
+        fn get_unit(&self, name: &str) -> Result<OwnedObjectPath, FakeError> {
+            match self.units.contains_key(name) {
+                true => Ok(FakeUnit::path(name)),
+                false => Err(FakeError::NoSuchUnit(name.to_string())),
+            }
+        }
+
+        fn restart_unit(&self, name: &str, _mode: &str) -> OwnedObjectPath {
+            self.restarted.lock().unwrap().push(name.to_string());
+            ObjectPath::from_static_str_unchecked("/org/freedesktop/systemd1/job/1").into()
+        }
+    }
+
+    struct FakeUnit {
+        state: String,
+    }
+    impl FakeUnit {
+        fn path(name: &str) -> OwnedObjectPath {
+            let escaped = name.replace('.', "_2e").replace('-', "_2d");
+            ObjectPath::try_from(format!("/org/freedesktop/systemd1/unit/{}", escaped))
+                .unwrap()
+                .into()
+        }
+    }
+    #[interface(name = "org.freedesktop.systemd1.Unit")]
+    impl FakeUnit {
+        #[zbus(property)]
+        fn active_state(&self) -> String {
+            self.state.clone()
+        }
+    }
+
+    async fn connect_fake_systemd(
+        units: &[(&str, &str)],
+    ) -> Result<(PwServices, Arc<Mutex<Vec<String>>>, zbus::Connection)> {
+        let (server_stream, client_stream) = UnixStream::pair()?;
+        let restarted = Arc::new(Mutex::new(Vec::new()));
+        let manager = FakeManager {
+            units: units
+                .iter()
+                .map(|(unit, state)| (unit.to_string(), state.to_string()))
+                .collect(),
+            restarted: restarted.clone(),
+        };
+
+        let mut server = connection::Builder::unix_stream(server_stream)
+            .server(Guid::generate())?
+            .p2p()
+            .serve_at("/org/freedesktop/systemd1", manager)?;
+        for (unit, state) in units {
+            let unit_object = FakeUnit {
+                state: state.to_string(),
+            };
+            server = server.serve_at(FakeUnit::path(unit), unit_object)?;
+        }
+
+        let client = connection::Builder::unix_stream(client_stream)
+            .p2p()
+            .build();
+        let (server, client) = futures_util::try_join!(server.build(), client)?;
+
+        Ok((PwServices::with_connection(client), restarted, server))
+    }
+
+    #[test]
+    fn it_should_get_affected_units_in_order() {
+        let units = PwServices::get_affected_units(&[
+            PwConfigFile::PipewirePulse(PwPulseSection::StreamProperties(PwPulseSectionSub::None)),
+            PwConfigFile::Pipewire(PwPipewireSection::ContextProperties(
+                PwPipewireSectionSub::Clock,
+            )),
+        ]);
+
+        assert_eq!(
+            units,
+            [
+                "pipewire.service",
+                "wireplumber.service",
+                "pipewire-pulse.service"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_restart_only_running_units() -> Result<()> {
+        zbus::block_on(async {
+            let (services, restarted, _server) = connect_fake_systemd(&[
+                ("pipewire.service", "active"),
+                ("pipewire-pulse.service", "failed"),
+            ])
+            .await?;
+
+            assert_eq!(
+                services.get_unit_state("wireplumber.service").await?,
+                "inactive"
+            );
+
+            let result = services
+                .restart(&[
+                    "pipewire.service",
+                    "wireplumber.service",
+                    "pipewire-pulse.service",
+                ])
+                .await?;
+
+            assert_eq!(result, ["pipewire.service"]);
+            assert_eq!(*restarted.lock().unwrap(), ["pipewire.service"]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn it_should_fail_health_check_for_failed_units() -> Result<()> {
+        zbus::block_on(async {
+            let (services, _restarted, _server) = connect_fake_systemd(&[
+                ("pipewire.service", "active"),
+                ("wireplumber.service", "activating"),
+                ("pipewire-pulse.service", "failed"),
+            ])
+            .await?;
+
+            assert!(services.check_health(&["pipewire.service".into()]).await?);
+            assert!(
+                !services
+                    .check_health(&["wireplumber.service".into()])
+                    .await?
+            );
+            assert!(
+                services
+                    .check_health(&["pipewire-pulse.service".into()])
+                    .await
+                    .is_err()
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn it_should_parse_interrupted_clients() -> Result<()> {
+        let pw_dump = r#"[
+            { "id": 31, "type": "PipeWire:Interface:Client",
+              "info": { "props": { "application.name": "WirePlumber", "application.process.binary": "wireplumber" } } },
+            { "id": 70, "type": "PipeWire:Interface:Client",
+              "info": { "props": { "application.name": "Firefox", "application.process.binary": "firefox", "client.api": "pipewire-pulse" } } },
+            { "id": 80, "type": "PipeWire:Interface:Node", "info": { "props": {} } }
+        ]"#;
+        let clients = PwServices::parse_clients(pw_dump)?;
+
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients[0].name, "Firefox");
+        assert_eq!(clients[0].api.as_deref(), Some("pipewire-pulse"));
+        Ok(())
+    }
+}
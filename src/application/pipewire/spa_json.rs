@@ -0,0 +1,300 @@
+use anyhow::{Result, bail};
+use serde_json::{Map, Number, Value};
+use std::{iter::Peekable, str::Chars};
+
+/// Reader and writer for the relaxed JSON dialect used by PipeWire config files.
+/// Keys and strings may be unquoted, `=` or `:` separate keys from values,
+/// commas are optional and `#` starts a comment.
+pub struct SpaJson;
+impl SpaJson {
+    const INDENT: &str = "    ";
+
+    /// Parses a config file. The top level can be a bare list of key-values.
+    pub fn parse(text: &str) -> Result<Value> {
+        let mut chars = text.chars().peekable();
+
+        Self::skip_whitespace(&mut chars);
+        let value = match chars.peek() {
+            Some('{') | Some('[') => Self::parse_value(&mut chars)?,
+            _ => Value::Object(Self::parse_object_body(&mut chars, None)?),
+        };
+
+        Self::skip_whitespace(&mut chars);
+        if let Some(char) = chars.next() {
+            bail!("Unexpected '{}' after end of spa-json", char);
+        }
+
+        Ok(value)
+    }
+
+    /// Writes key-values as a top-level config file body.
+    pub fn to_string(map: &Map<String, Value>) -> String {
+        let mut spa_json = String::new();
+
+        for (key, value) in map {
+            spa_json.push_str(&format!("{} = ", Self::string_to_string(key)));
+            Self::write_value(&mut spa_json, value, 0);
+            spa_json.push('\n');
+        }
+
+        spa_json
+    }
+
+    /// Writes a single value, e.g. `[ FL FR ]`.
+    pub fn value_to_string(value: &Value) -> String {
+        let mut spa_json = String::new();
+        Self::write_value(&mut spa_json, value, 0);
+
+        spa_json
+    }
+
+    /// Whole floats are written as integers, spin rows only give floats.
+    pub fn number_to_string(number: &Number) -> String {
+        match number.as_f64() {
+            Some(float) if !number.is_i64() && !number.is_u64() && float.fract() == 0.0 => {
+                (float as i64).to_string()
+            }
+            _ => number.to_string(),
+        }
+    }
+
+    fn string_to_string(string: &str) -> String {
+        let is_bare = !string.is_empty()
+            && string.parse::<f64>().is_err()
+            && !matches!(string, "true" | "false" | "null")
+            && string.chars().all(|char| !Self::is_delimiter(char));
+
+        match is_bare {
+            true => string.to_string(),
+            false => Value::String(string.to_string()).to_string(),
+        }
+    }
+
+    fn write_value(spa_json: &mut String, value: &Value, depth: usize) {
+        match value {
+            Value::Null => spa_json.push_str("null"),
+            Value::Bool(bool) => spa_json.push_str(&bool.to_string()),
+            Value::Number(number) => spa_json.push_str(&Self::number_to_string(number)),
+            Value::String(string) => spa_json.push_str(&Self::string_to_string(string)),
+            Value::Array(array) => {
+                spa_json.push_str("[ ");
+                for item in array {
+                    Self::write_value(spa_json, item, depth);
+                    spa_json.push(' ');
+                }
+                spa_json.push(']');
+            }
+            Value::Object(map) => {
+                let indent = Self::INDENT.repeat(depth + 1);
+
+                spa_json.push_str("{\n");
+                for (key, value) in map {
+                    spa_json.push_str(&format!("{}{} = ", indent, Self::string_to_string(key)));
+                    Self::write_value(spa_json, value, depth + 1);
+                    spa_json.push('\n');
+                }
+                spa_json.push_str(&Self::INDENT.repeat(depth));
+                spa_json.push('}');
+            }
+        }
+    }
+
+    fn is_delimiter(char: char) -> bool {
+        char.is_whitespace() || matches!(char, '{' | '}' | '[' | ']' | ':' | '=' | ',' | '#' | '"')
+    }
+
+    fn skip_whitespace(chars: &mut Peekable<Chars>) {
+        while let Some(char) = chars.peek() {
+            match char {
+                '#' => {
+                    for char in chars.by_ref() {
+                        if char == '\n' {
+                            break;
+                        }
+                    }
+                }
+                char if char.is_whitespace() || *char == ',' => {
+                    chars.next();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn parse_value(chars: &mut Peekable<Chars>) -> Result<Value> {
+        Self::skip_whitespace(chars);
+
+        match chars.peek() {
+            Some('{') => {
+                chars.next();
+                Ok(Value::Object(Self::parse_object_body(chars, Some('}'))?))
+            }
+            Some('[') => {
+                chars.next();
+                let mut array = Vec::new();
+                loop {
+                    Self::skip_whitespace(chars);
+                    match chars.peek() {
+                        Some(']') => {
+                            chars.next();
+                            break;
+                        }
+                        Some(_) => array.push(Self::parse_value(chars)?),
+                        None => bail!("Unterminated array in spa-json"),
+                    }
+                }
+                Ok(Value::Array(array))
+            }
+            Some('"') => Ok(Value::String(Self::parse_string(chars)?)),
+            Some(_) => Ok(Self::bare_to_value(Self::parse_bare(chars)?)),
+            None => bail!("Expected a value but reached the end of spa-json"),
+        }
+    }
+
+    fn parse_object_body(
+        chars: &mut Peekable<Chars>,
+        end: Option<char>,
+    ) -> Result<Map<String, Value>> {
+        let mut map = Map::new();
+
+        loop {
+            Self::skip_whitespace(chars);
+            let key = match (chars.peek(), end) {
+                (None, None) => break,
+                (None, Some(end)) => bail!("Unterminated object in spa-json, expected '{}'", end),
+                (Some(char), Some(end)) if *char == end => {
+                    chars.next();
+                    break;
+                }
+                (Some('"'), _) => Self::parse_string(chars)?,
+                (Some(_), _) => Self::parse_bare(chars)?,
+            };
+
+            Self::skip_whitespace(chars);
+            if let Some(':') | Some('=') = chars.peek() {
+                chars.next();
+            }
+
+            let value = Self::parse_value(chars)?;
+            map.insert(key, value);
+        }
+
+        Ok(map)
+    }
+
+    fn parse_string(chars: &mut Peekable<Chars>) -> Result<String> {
+        // Opening quote
+        chars.next();
+        let mut raw = String::from('"');
+
+        loop {
+            match chars.next() {
+                Some('\\') => {
+                    raw.push('\\');
+                    if let Some(char) = chars.next() {
+                        raw.push(char);
+                    }
+                }
+                Some('"') => break,
+                Some(char) => raw.push(char),
+                None => bail!("Unterminated string in spa-json: {}", raw),
+            }
+        }
+        raw.push('"');
+
+        // Reuse JSON string unescaping
+        Ok(serde_json::from_str(&raw)?)
+    }
+
+    fn parse_bare(chars: &mut Peekable<Chars>) -> Result<String> {
+        let mut bare = String::new();
+
+        while let Some(char) = chars.peek() {
+            if Self::is_delimiter(*char) {
+                break;
+            }
+            bare.push(*char);
+            chars.next();
+        }
+
+        if bare.is_empty() {
+            bail!("Unexpected '{}' in spa-json", chars.peek().unwrap());
+        }
+
+        Ok(bare)
+    }
+
+    fn bare_to_value(bare: String) -> Value {
+        match bare.as_str() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "null" => Value::Null,
+            _ => match serde_json::from_str::<Number>(&bare) {
+                Ok(number) => Value::Number(number),
+                Err(_) => Value::String(bare),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpaJson;
+    use anyhow::Result;
+    use serde_json::json;
+
+    #[test]
+    fn it_should_parse_spa_json() -> Result<()> {
+        let spa_json = r#"
+            # Comment
+            stream.properties = {
+                node.latency = 1024/48000   # a comment, with a comma
+                channelmix.upmix = true
+                "quoted.key": "quoted value",
+                channelmix.lfe-cutoff = 150
+                channelmix.rear-delay = 12.5
+                audio.position = [ FL FR ]
+            }
+            context.modules = [
+                { name = libpipewire-module-rt args = { nice.level = -11 } }
+            ]
+        "#;
+        let expected = json!({
+            "stream.properties": {
+                "node.latency": "1024/48000",
+                "channelmix.upmix": true,
+                "quoted.key": "quoted value",
+                "channelmix.lfe-cutoff": 150,
+                "channelmix.rear-delay": 12.5,
+                "audio.position": ["FL", "FR"],
+            },
+            "context.modules": [
+                { "name": "libpipewire-module-rt", "args": { "nice.level": -11 } }
+            ]
+        });
+
+        assert_eq!(SpaJson::parse(spa_json)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_write_spa_json_that_parses_back() -> Result<()> {
+        let value = json!({
+            "stream.properties": {
+                "channelmix.upmix-method": "psd",
+                "channelmix.lfe-cutoff": 150.0,
+                "node.name": "with space",
+                "audio.position": ["FL", "FR"],
+            }
+        });
+        let spa_json = SpaJson::to_string(value.as_object().unwrap());
+
+        assert!(spa_json.contains("channelmix.lfe-cutoff = 150\n"));
+        assert!(spa_json.contains("channelmix.upmix-method = psd\n"));
+        assert_eq!(
+            SpaJson::parse(&spa_json)?["stream.properties"]["node.name"],
+            "with space"
+        );
+        Ok(())
+    }
+}
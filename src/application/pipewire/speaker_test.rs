@@ -0,0 +1,334 @@
+use super::{channel_map::PwChannelMap, error::PwError, host::PwHost};
+use anyhow::{Context, Result};
+use log::{debug, info};
+use serde_json::Value;
+use std::{
+    f64::consts::TAU,
+    io::{self, Read, Write},
+    process::Stdio,
+    thread,
+};
+
+/// The sound a speaker is tested with.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PwTestSignal {
+    PinkNoise,
+    Sine,
+}
+impl PwTestSignal {
+    pub const ALL: [PwTestSignal; 2] = [Self::PinkNoise, Self::Sine];
+
+    pub fn get_title(&self) -> &'static str {
+        match self {
+            Self::PinkNoise => "Pink noise",
+            Self::Sine => "Sine tone",
+        }
+    }
+}
+
+/// A sink streams can be played on.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PwSink {
+    pub name: String,
+    pub description: String,
+}
+impl PwSink {
+    /// The audio sinks of the running PipeWire.
+    pub fn list() -> Result<Vec<Self>> {
+        let output = PwHost::run(&mut PwHost::command("pw-dump")).context("Listing sinks")?;
+
+        Self::parse_dump(&output)
+    }
+
+    /// Sinks in the output of `pw-dump`, a list of all objects.
+    pub fn parse_dump(dump: &str) -> Result<Vec<Self>> {
+        let objects: Vec<Value> = serde_json::from_str(dump).map_err(|error| PwError::Parse {
+            what: String::from("output of pw-dump"),
+            reason: error.to_string(),
+            text: dump.to_string(),
+        })?;
+
+        let sinks = objects
+            .iter()
+            .filter(|object| object["type"] == "PipeWire:Interface:Node")
+            .map(|object| &object["info"]["props"])
+            .filter(|props| props["media.class"] == "Audio/Sink")
+            .filter_map(|props| {
+                let name = props["node.name"].as_str()?;
+                let description = props["node.description"].as_str().unwrap_or(name);
+
+                Some(Self {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                })
+            })
+            .collect();
+
+        Ok(sinks)
+    }
+}
+
+/// A short signal on one channel of a channel map, the other channels are silent.
+/// Generated in memory and piped to `pw-cat`.
+#[derive(Clone, Debug)]
+pub struct PwSpeakerTest {
+    pub signal: PwTestSignal,
+    pub map: PwChannelMap,
+    pub channel: usize,
+}
+impl PwSpeakerTest {
+    const LOG_TARGET: &str = "PwSpeakerTest";
+    pub const SAMPLE_RATE: u32 = 48000;
+    const SECONDS: f64 = 1.5;
+    /// Faded in and out, cut off signals click
+    const FADE_SECONDS: f64 = 0.05;
+    /// -12 dBFS, loud enough to hear without hurting
+    const AMPLITUDE: f64 = 0.25;
+    const SINE_FREQUENCY: f64 = 440.0;
+    /// Subwoofers only play low frequencies
+    const LOW_SINE_FREQUENCY: f64 = 60.0;
+
+    pub fn new(signal: PwTestSignal, map: &PwChannelMap, channel: usize) -> Self {
+        Self {
+            signal,
+            map: map.clone(),
+            channel,
+        }
+    }
+
+    /// Interleaved samples of all channels.
+    pub fn generate(&self) -> Vec<f32> {
+        let channels = self.map.positions.len();
+        let signal = self.generate_channel();
+        let mut samples = vec![0.0; signal.len() * channels];
+
+        for (frame, sample) in signal.into_iter().enumerate() {
+            if let Some(channel_sample) = samples.get_mut(frame * channels + self.channel) {
+                *channel_sample = sample;
+            }
+        }
+
+        samples
+    }
+
+    fn generate_channel(&self) -> Vec<f32> {
+        let rate = f64::from(Self::SAMPLE_RATE);
+        let frames = (Self::SECONDS * rate) as usize;
+        let fade_frames = Self::FADE_SECONDS * rate;
+
+        let signal = match self.signal {
+            PwTestSignal::PinkNoise => Self::generate_pink_noise(frames),
+            PwTestSignal::Sine => {
+                let frequency = self.get_sine_frequency();
+                (0..frames)
+                    .map(|frame| (TAU * frequency * frame as f64 / rate).sin())
+                    .collect()
+            }
+        };
+
+        signal
+            .into_iter()
+            .enumerate()
+            .map(|(frame, sample)| {
+                let fade = (frame.min(frames - 1 - frame) as f64 / fade_frames).min(1.0);
+                (sample * fade * Self::AMPLITUDE) as f32
+            })
+            .collect()
+    }
+
+    fn get_sine_frequency(&self) -> f64 {
+        let position = self.map.positions.get(self.channel).map(String::as_str);
+
+        match position {
+            Some("LFE" | "LFE2" | "LLFE" | "RLFE") => Self::LOW_SINE_FREQUENCY,
+            _ => Self::SINE_FREQUENCY,
+        }
+    }
+
+    /// Noise with equal energy per octave, from Paul Kellet's economy filter.
+    /// Seeded the same every time, so tests and plays are repeatable.
+    fn generate_pink_noise(frames: usize) -> Vec<f64> {
+        let mut state: u32 = 0x2545_f491;
+        let mut filter = [0.0; 3];
+
+        let noise: Vec<f64> = (0..frames)
+            .map(|_frame| {
+                // xorshift32
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let white = f64::from(state) / f64::from(u32::MAX) * 2.0 - 1.0;
+
+                filter[0] = 0.99765 * filter[0] + white * 0.0990460;
+                filter[1] = 0.96300 * filter[1] + white * 0.2965164;
+                filter[2] = 0.57000 * filter[2] + white * 1.0526913;
+                filter[0] + filter[1] + filter[2] + white * 0.1848
+            })
+            .collect();
+
+        let peak = noise
+            .iter()
+            .fold(0.0_f64, |peak, sample| peak.max(sample.abs()));
+        match peak > 0.0 {
+            true => noise.into_iter().map(|sample| sample / peak).collect(),
+            false => noise,
+        }
+    }
+
+    /// Plays the signal on a sink, or the default sink, and waits until it was played.
+    pub fn play(&self, sink: Option<&str>) -> Result<()> {
+        let mut command = PwHost::command("pw-cat");
+        command
+            .arg("--playback")
+            .arg("--raw")
+            .arg("--format=f32")
+            .arg(format!("--rate={}", Self::SAMPLE_RATE))
+            .arg(format!("--channels={}", self.map.positions.len()))
+            .arg(format!("--channel-map={}", self.map.positions.join(",")));
+        if let Some(sink) = sink {
+            command.arg(format!("--target={}", sink));
+        }
+        command
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        debug!(target: Self::LOG_TARGET, "{:?}", command);
+
+        let mut child = command.spawn().map_err(|error| match error.kind() {
+            io::ErrorKind::NotFound => PwError::MissingBinary {
+                program: String::from("pw-cat"),
+            },
+            _ => PwError::Failed {
+                command: String::from("pw-cat"),
+                status: None,
+                stderr: error.to_string(),
+            },
+        })?;
+
+        let bytes: Vec<u8> = self
+            .generate()
+            .into_iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        // Read while writing, pw-cat blocks when nobody reads its full stderr pipe
+        let stderr = child.stderr.take().map(|mut stderr| {
+            thread::spawn(move || {
+                let mut output = String::new();
+                let _ = stderr.read_to_string(&mut output);
+                output
+            })
+        });
+        // pw-cat reads until the end of the input, closing it ends the playback
+        let written = match child.stdin.take() {
+            Some(mut stdin) => stdin.write_all(&bytes),
+            None => Ok(()),
+        };
+
+        // Also after a failed write, e.g. a broken pipe, its stderr tells why it quit
+        let status = child.wait().context("Waiting for pw-cat")?;
+        let stderr = stderr
+            .and_then(|stderr| stderr.join().ok())
+            .unwrap_or_default();
+        if let Err(error) = written {
+            return Err(PwError::Failed {
+                command: String::from("pw-cat"),
+                status: status.code(),
+                stderr,
+            })
+            .context(format!(
+                "Writing the test signal to pw-cat failed: {}",
+                error
+            ));
+        }
+        if !status.success() {
+            return Err(PwError::Failed {
+                command: String::from("pw-cat"),
+                status: status.code(),
+                stderr,
+            }
+            .into());
+        }
+        info!(target: Self::LOG_TARGET, "Played channel {} on {:?}", self.channel + 1, sink);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PwSink, PwSpeakerTest, PwTestSignal};
+    use crate::application::pipewire::channel_map::{PwChannelLayout, PwChannelMap};
+
+    #[test]
+    fn it_should_only_play_on_the_tested_channel() {
+        let map = PwChannelMap::from_layout(PwChannelLayout::Surround51);
+        let samples = PwSpeakerTest::new(PwTestSignal::PinkNoise, &map, 2).generate();
+        let frames = (PwSpeakerTest::SECONDS * f64::from(PwSpeakerTest::SAMPLE_RATE)) as usize;
+        assert_eq!(samples.len(), frames * 6);
+
+        for (index, sample) in samples.iter().enumerate() {
+            match index % 6 {
+                2 => assert!(sample.abs() <= PwSpeakerTest::AMPLITUDE as f32),
+                _ => assert_eq!(*sample, 0.0),
+            }
+        }
+        // Faded in and out
+        assert_eq!(samples[2], 0.0);
+        assert_eq!(samples[samples.len() - 4], 0.0);
+        assert!(samples.iter().any(|sample| sample.abs() > 0.1));
+    }
+
+    #[test]
+    fn it_should_generate_pink_noise_and_sines() {
+        let map = PwChannelMap::from_layout(PwChannelLayout::Stereo21);
+        let noise = PwSpeakerTest::new(PwTestSignal::PinkNoise, &map, 0).generate_channel();
+        assert_eq!(
+            noise,
+            PwSpeakerTest::new(PwTestSignal::PinkNoise, &map, 0).generate_channel()
+        );
+        // Pink noise is mostly low frequencies, the differences of white noise have twice its energy
+        let energy: f32 = noise.iter().map(|sample| sample * sample).sum();
+        let difference_energy: f32 = noise
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]) * (pair[1] - pair[0]))
+            .sum();
+        assert!(difference_energy < energy);
+
+        // Zero crossings of a sine are twice its frequency per second
+        let count_crossings = |samples: Vec<f32>| {
+            samples
+                .windows(2)
+                .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+                .count()
+        };
+        let sine = PwSpeakerTest::new(PwTestSignal::Sine, &map, 0).generate_channel();
+        assert!(count_crossings(sine).abs_diff(660) <= 2);
+        let subwoofer = PwSpeakerTest::new(PwTestSignal::Sine, &map, 2).generate_channel();
+        assert!(count_crossings(subwoofer).abs_diff(90) <= 2);
+    }
+
+    #[test]
+    fn it_should_parse_sinks_from_pw_dump() {
+        let dump = r#"[
+            { "id": 0, "type": "PipeWire:Interface:Core", "info": { "props": {} } },
+            { "id": 40, "type": "PipeWire:Interface:Node", "info": { "props": {
+                "media.class": "Audio/Sink",
+                "node.name": "alsa_output.pci-0000_00_1f.3.analog-surround-51",
+                "node.description": "Built-in Audio Analog Surround 5.1"
+            } } },
+            { "id": 41, "type": "PipeWire:Interface:Node", "info": { "props": {
+                "media.class": "Audio/Source", "node.name": "alsa_input.mic"
+            } } },
+            { "id": 42, "type": "PipeWire:Interface:Node", "info": { "props": {
+                "media.class": "Audio/Sink", "node.name": "speaker-layout"
+            } } }
+        ]"#;
+
+        let sinks = PwSink::parse_dump(dump).unwrap();
+        assert_eq!(sinks.len(), 2);
+        assert_eq!(sinks[0].description, "Built-in Audio Analog Surround 5.1");
+        assert_eq!(sinks[1].description, "speaker-layout");
+        assert!(PwSink::parse_dump("not json").is_err());
+    }
+}
@@ -0,0 +1,242 @@
+use super::{error::PwError, host::PwHost};
+use anyhow::{Context, Result};
+use log::{info, warn};
+use regex::Regex;
+use std::fmt;
+
+/// A version like `1.2.7`, parts that are missing or not a number count as 0.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct PwVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub micro: u32,
+}
+impl PwVersion {
+    pub const fn new(major: u32, minor: u32, micro: u32) -> Self {
+        Self {
+            major,
+            minor,
+            micro,
+        }
+    }
+
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        if version.is_empty() {
+            return None;
+        }
+        let mut parts = version
+            .split('.')
+            .map(|part| part.parse::<u32>().unwrap_or_default());
+
+        Some(Self {
+            major: parts.next().unwrap_or_default(),
+            minor: parts.next().unwrap_or_default(),
+            micro: parts.next().unwrap_or_default(),
+        })
+    }
+
+    /// The library version in the output of `--version`, e.g. `1.2.7` from
+    /// `Linked with libpipewire 1.2.7`. Linked is printed after compiled and wins.
+    pub fn from_output(output: &str) -> Option<Self> {
+        let regex_version = Regex::new(r"(?:Linked|Compiled) with lib\S+ (\S+)").unwrap();
+
+        regex_version
+            .captures_iter(output)
+            .last()
+            .and_then(|version| Self::parse(&version[1]))
+    }
+}
+impl fmt::Display for PwVersion {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}.{}.{}", self.major, self.minor, self.micro)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PwProgram {
+    Pipewire,
+    Wireplumber,
+}
+impl PwProgram {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Pipewire => "PipeWire",
+            Self::Wireplumber => "WirePlumber",
+        }
+    }
+
+    /// The program reading a config file, e.g. `pipewire-pulse.conf` is read by PipeWire.
+    pub fn from_file(file: &str) -> Self {
+        match file.starts_with("wireplumber") {
+            true => Self::Wireplumber,
+            false => Self::Pipewire,
+        }
+    }
+}
+
+/// Something that only newer versions have.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PwCapability {
+    /// `pw-config` to read the merged configs
+    PwConfig,
+    /// `wireplumber.conf` with a `wireplumber.settings` section, 0.4 was configured with Lua
+    WireplumberSettings,
+}
+impl PwCapability {
+    /// The first version of the program that has the capability.
+    const TABLE: [(PwCapability, PwProgram, PwVersion); 2] = [
+        (
+            PwCapability::PwConfig,
+            PwProgram::Pipewire,
+            PwVersion::new(0, 3, 68),
+        ),
+        (
+            PwCapability::WireplumberSettings,
+            PwProgram::Wireplumber,
+            PwVersion::new(0, 5, 0),
+        ),
+    ];
+
+    pub fn get_requirement(&self) -> (PwProgram, PwVersion) {
+        Self::TABLE
+            .iter()
+            .find(|(capability, _program, _version)| capability == self)
+            .map(|(_capability, program, version)| (*program, *version))
+            .expect("Every capability should be in the table")
+    }
+}
+
+/// The installed versions, they decide which settings can be used and whether WirePlumber is configured.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PwVersions {
+    pub pipewire: PwVersion,
+    /// `None` when WirePlumber is not installed
+    pub wireplumber: Option<PwVersion>,
+}
+impl PwVersions {
+    const LOG_TARGET: &str = "PwVersions";
+
+    /// Reads the versions, fails when the PipeWire version can not be read
+    /// or is too old for `pw-config`.
+    pub fn detect() -> Result<Self> {
+        let output = PwHost::run(PwHost::command("pw-config").arg("--version"))
+            .context("Reading version of pw-config")?;
+        let pipewire = PwVersion::from_output(&output).ok_or_else(|| PwError::Parse {
+            what: String::from("version of pw-config"),
+            reason: String::from("it has no libpipewire version"),
+            text: output.clone(),
+        })?;
+
+        let wireplumber = match PwHost::run(PwHost::command("wireplumber").arg("--version")) {
+            Ok(output) => PwVersion::from_output(&output),
+            Err(error) => {
+                warn!(target: Self::LOG_TARGET, "Reading WirePlumber version failed: {}", error);
+                None
+            }
+        };
+
+        let versions = Self {
+            pipewire,
+            wireplumber,
+        };
+        let (_program, minimum) = PwCapability::PwConfig.get_requirement();
+        if !versions.has(PwCapability::PwConfig) {
+            return Err(PwError::UnsupportedVersion {
+                version: pipewire.to_string(),
+                minimum: minimum.to_string(),
+            }
+            .into());
+        }
+
+        info!(target: Self::LOG_TARGET, "PipeWire {}, WirePlumber {:?}", pipewire, wireplumber);
+
+        Ok(versions)
+    }
+
+    pub fn get(&self, program: PwProgram) -> Option<PwVersion> {
+        match program {
+            PwProgram::Pipewire => Some(self.pipewire),
+            PwProgram::Wireplumber => self.wireplumber,
+        }
+    }
+
+    pub fn has(&self, capability: PwCapability) -> bool {
+        let (program, minimum) = capability.get_requirement();
+
+        self.get(program).is_some_and(|version| version >= minimum)
+    }
+
+    /// Why a setting that needs this version of the program can not be used, e.g.
+    /// `Needs PipeWire 1.0.0 or newer`.
+    pub fn get_unsupported_reason(&self, program: PwProgram, since: PwVersion) -> Option<String> {
+        match self.get(program) {
+            Some(version) if version >= since => None,
+            Some(version) => Some(format!(
+                "Needs {} {} or newer, {} is installed",
+                program.get_name(),
+                since,
+                version
+            )),
+            None => Some(format!("Needs {} {} or newer", program.get_name(), since)),
+        }
+    }
+}
+/// Assumes the newest versions, e.g. for configs that are not read from the system.
+impl Default for PwVersions {
+    fn default() -> Self {
+        Self {
+            pipewire: PwVersion::new(u32::MAX, 0, 0),
+            wireplumber: Some(PwVersion::new(u32::MAX, 0, 0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PwCapability, PwProgram, PwVersion, PwVersions};
+
+    #[test]
+    fn it_should_parse_versions() {
+        let output = "pw-config\nCompiled with libpipewire 1.0.5\nLinked with libpipewire 1.0.7\n";
+        assert_eq!(
+            PwVersion::from_output(output),
+            Some(PwVersion::new(1, 0, 7))
+        );
+        let output = "wireplumber\nCompiled with libwireplumber 0.4.17\n";
+        assert_eq!(
+            PwVersion::from_output(output),
+            Some(PwVersion::new(0, 4, 17))
+        );
+        assert_eq!(PwVersion::from_output("command not found"), None);
+
+        assert_eq!(PwVersion::parse("1.2"), Some(PwVersion::new(1, 2, 0)));
+        assert_eq!(PwVersion::parse(""), None);
+        assert!(PwVersion::new(0, 3, 85) < PwVersion::new(1, 0, 0));
+        assert_eq!(PwVersion::new(1, 2, 7).to_string(), "1.2.7");
+    }
+
+    #[test]
+    fn it_should_gate_capabilities() {
+        let versions = PwVersions {
+            pipewire: PwVersion::new(0, 3, 80),
+            wireplumber: Some(PwVersion::new(0, 4, 17)),
+        };
+        assert!(versions.has(PwCapability::PwConfig));
+        assert!(!versions.has(PwCapability::WireplumberSettings));
+        assert_eq!(
+            versions
+                .get_unsupported_reason(PwProgram::Pipewire, PwVersion::new(1, 0, 0))
+                .as_deref(),
+            Some("Needs PipeWire 1.0.0 or newer, 0.3.80 is installed")
+        );
+
+        let versions = PwVersions {
+            pipewire: PwVersion::new(1, 2, 7),
+            wireplumber: None,
+        };
+        assert!(versions.has(PwCapability::PwConfig));
+        assert!(!versions.has(PwCapability::WireplumberSettings));
+        assert!(PwVersions::default().has(PwCapability::WireplumberSettings));
+    }
+}
@@ -0,0 +1,263 @@
+use super::pipewire::dropin::PwDropInScope;
+use crate::config;
+use libadwaita::{
+    gio::{self, SettingsSchemaSource, prelude::SettingsExt},
+    glib::{self, KeyFile, KeyFileFlags},
+};
+use log::{error, info};
+use std::{fs, path::PathBuf};
+
+/// Where the preferences are kept.
+enum PreferencesStore {
+    Settings(gio::Settings),
+    /// Used when the schema is not installed, e.g. when running from the build dir
+    KeyFile(KeyFile, PathBuf),
+}
+
+/// Window state and app preferences, kept between runs.
+pub struct Preferences {
+    store: PreferencesStore,
+}
+impl Preferences {
+    const LOG_TARGET: &str = "preferences";
+    const KEY_FILE_GROUP: &str = "preferences";
+    const WINDOW_WIDTH: (&str, i32) = ("window-width", 800);
+    const WINDOW_HEIGHT: (&str, i32) = ("window-height", 600);
+    const WINDOW_MAXIMIZED: (&str, bool) = ("window-maximized", false);
+    const LAST_PAGE: (&str, &str) = ("last-page", "");
+    const SHOW_ADVANCED: (&str, bool) = ("show-advanced", false);
+    const CONFIRM_APPLY: (&str, bool) = ("confirm-apply", false);
+    const DROPIN_SCOPE: (&str, &str) = ("dropin-scope", "user");
+    const KEYS: [&str; 7] = [
+        Self::WINDOW_WIDTH.0,
+        Self::WINDOW_HEIGHT.0,
+        Self::WINDOW_MAXIMIZED.0,
+        Self::LAST_PAGE.0,
+        Self::SHOW_ADVANCED.0,
+        Self::CONFIRM_APPLY.0,
+        Self::DROPIN_SCOPE.0,
+    ];
+
+    /// Uses GSettings when the app's schema with all keys is installed,
+    /// otherwise `~/.config/<app id>/preferences.ini`.
+    pub fn new() -> Self {
+        let schema = SettingsSchemaSource::default()
+            .and_then(|source| source.lookup(config::APP_ID, true))
+            .filter(|schema| Self::KEYS.iter().all(|key| schema.has_key(key)));
+        if schema.is_some() {
+            return Self {
+                store: PreferencesStore::Settings(gio::Settings::new(config::APP_ID)),
+            };
+        }
+
+        info!(target: Self::LOG_TARGET, "Schema {} is not installed, using a keyfile", config::APP_ID);
+        Self::load(
+            glib::user_config_dir()
+                .join(config::APP_ID)
+                .join("preferences.ini"),
+        )
+    }
+
+    /// Reads the keyfile at `path`, defaults are used when it doesn't exist yet.
+    fn load(path: PathBuf) -> Self {
+        let key_file = KeyFile::new();
+        if path.exists()
+            && let Err(error) = key_file.load_from_file(&path, KeyFileFlags::NONE)
+        {
+            error!(target: Self::LOG_TARGET, "Reading {} failed: {}", path.display(), error);
+        }
+
+        Self {
+            store: PreferencesStore::KeyFile(key_file, path),
+        }
+    }
+
+    /// The size without maximizing, and if the window was maximized.
+    pub fn get_window_state(&self) -> (i32, i32, bool) {
+        (
+            self.get_int(Self::WINDOW_WIDTH),
+            self.get_int(Self::WINDOW_HEIGHT),
+            self.get_bool(Self::WINDOW_MAXIMIZED),
+        )
+    }
+
+    pub fn set_window_state(&self, width: i32, height: i32, is_maximized: bool) {
+        self.set_int(Self::WINDOW_WIDTH.0, width);
+        self.set_int(Self::WINDOW_HEIGHT.0, height);
+        self.set_bool(Self::WINDOW_MAXIMIZED.0, is_maximized);
+    }
+
+    /// The page shown last, `None` before the first navigation.
+    pub fn get_last_page(&self) -> Option<String> {
+        Some(self.get_string(Self::LAST_PAGE)).filter(|page_id| !page_id.is_empty())
+    }
+
+    pub fn set_last_page(&self, page_id: &str) {
+        self.set_string(Self::LAST_PAGE.0, page_id);
+    }
+
+    /// Rows of settings the schema marks as advanced are hidden unless this is on.
+    pub fn get_show_advanced(&self) -> bool {
+        self.get_bool(Self::SHOW_ADVANCED)
+    }
+
+    pub fn set_show_advanced(&self, show_advanced: bool) {
+        self.set_bool(Self::SHOW_ADVANCED.0, show_advanced);
+    }
+
+    /// Asks with the list of pending settings before a page applies them.
+    pub fn get_confirm_apply(&self) -> bool {
+        self.get_bool(Self::CONFIRM_APPLY)
+    }
+
+    pub fn set_confirm_apply(&self, confirm_apply: bool) {
+        self.set_bool(Self::CONFIRM_APPLY.0, confirm_apply);
+    }
+
+    /// Where drop-ins are written by default.
+    pub fn get_dropin_scope(&self) -> PwDropInScope {
+        PwDropInScope::from_name(&self.get_string(Self::DROPIN_SCOPE)).unwrap_or_default()
+    }
+
+    pub fn set_dropin_scope(&self, scope: PwDropInScope) {
+        self.set_string(Self::DROPIN_SCOPE.0, scope.get_name());
+    }
+
+    fn get_bool(&self, (key, default): (&str, bool)) -> bool {
+        match &self.store {
+            PreferencesStore::Settings(settings) => settings.boolean(key),
+            PreferencesStore::KeyFile(key_file, _path) => key_file
+                .boolean(Self::KEY_FILE_GROUP, key)
+                .unwrap_or(default),
+        }
+    }
+
+    fn get_int(&self, (key, default): (&str, i32)) -> i32 {
+        match &self.store {
+            PreferencesStore::Settings(settings) => settings.int(key),
+            PreferencesStore::KeyFile(key_file, _path) => key_file
+                .integer(Self::KEY_FILE_GROUP, key)
+                .unwrap_or(default),
+        }
+    }
+
+    fn get_string(&self, (key, default): (&str, &str)) -> String {
+        match &self.store {
+            PreferencesStore::Settings(settings) => settings.string(key).to_string(),
+            PreferencesStore::KeyFile(key_file, _path) => key_file
+                .string(Self::KEY_FILE_GROUP, key)
+                .map_or(default.to_string(), |value| value.to_string()),
+        }
+    }
+
+    fn set_bool(&self, key: &str, value: bool) {
+        match &self.store {
+            PreferencesStore::Settings(settings) => {
+                if let Err(error) = settings.set_boolean(key, value) {
+                    error!(target: Self::LOG_TARGET, "Setting {} failed: {}", key, error);
+                }
+            }
+            PreferencesStore::KeyFile(key_file, _path) => {
+                key_file.set_boolean(Self::KEY_FILE_GROUP, key, value);
+                self.save_key_file();
+            }
+        }
+    }
+
+    fn set_int(&self, key: &str, value: i32) {
+        match &self.store {
+            PreferencesStore::Settings(settings) => {
+                if let Err(error) = settings.set_int(key, value) {
+                    error!(target: Self::LOG_TARGET, "Setting {} failed: {}", key, error);
+                }
+            }
+            PreferencesStore::KeyFile(key_file, _path) => {
+                key_file.set_integer(Self::KEY_FILE_GROUP, key, value);
+                self.save_key_file();
+            }
+        }
+    }
+
+    fn set_string(&self, key: &str, value: &str) {
+        match &self.store {
+            PreferencesStore::Settings(settings) => {
+                if let Err(error) = settings.set_string(key, value) {
+                    error!(target: Self::LOG_TARGET, "Setting {} failed: {}", key, error);
+                }
+            }
+            PreferencesStore::KeyFile(key_file, _path) => {
+                key_file.set_string(Self::KEY_FILE_GROUP, key, value);
+                self.save_key_file();
+            }
+        }
+    }
+
+    fn save_key_file(&self) {
+        let PreferencesStore::KeyFile(key_file, path) = &self.store else {
+            return;
+        };
+
+        if let Some(dir) = path.parent()
+            && let Err(error) = fs::create_dir_all(dir)
+        {
+            error!(target: Self::LOG_TARGET, "Creating {} failed: {}", dir.display(), error);
+            return;
+        }
+        if let Err(error) = key_file.save_to_file(path) {
+            error!(target: Self::LOG_TARGET, "Writing {} failed: {}", path.display(), error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_path(dir: &tempfile::TempDir) -> PathBuf {
+        dir.path().join("app").join("preferences.ini")
+    }
+
+    #[test]
+    fn it_should_use_defaults_without_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let preferences = Preferences::load(get_path(&dir));
+
+        assert_eq!(preferences.get_window_state(), (800, 600, false));
+        assert_eq!(preferences.get_last_page(), None);
+        assert!(!preferences.get_show_advanced());
+        assert!(!preferences.get_confirm_apply());
+        assert_eq!(preferences.get_dropin_scope(), PwDropInScope::User);
+    }
+
+    #[test]
+    fn it_should_round_trip_window_state() {
+        let dir = tempfile::tempdir().unwrap();
+        Preferences::load(get_path(&dir)).set_window_state(1024, 768, true);
+
+        let preferences = Preferences::load(get_path(&dir));
+        assert_eq!(preferences.get_window_state(), (1024, 768, true));
+    }
+
+    #[test]
+    fn it_should_round_trip_last_page() {
+        let dir = tempfile::tempdir().unwrap();
+        Preferences::load(get_path(&dir)).set_last_page("clock");
+
+        let preferences = Preferences::load(get_path(&dir));
+        assert_eq!(preferences.get_last_page().as_deref(), Some("clock"));
+    }
+
+    #[test]
+    fn it_should_round_trip_app_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let preferences = Preferences::load(get_path(&dir));
+        preferences.set_show_advanced(true);
+        preferences.set_confirm_apply(true);
+        preferences.set_dropin_scope(PwDropInScope::User);
+
+        let preferences = Preferences::load(get_path(&dir));
+        assert!(preferences.get_show_advanced());
+        assert!(preferences.get_confirm_apply());
+        assert_eq!(preferences.get_dropin_scope(), PwDropInScope::User);
+    }
+}
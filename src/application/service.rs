@@ -0,0 +1,478 @@
+use super::{
+    pipewire::{
+        pipewire::{Pipewire, PwAppliedConfigs},
+        schema::PwSchema,
+    },
+    watcher::{ConfigWatcher, WatcherPause},
+};
+use anyhow::{Context, Result, anyhow};
+use libadwaita::{
+    gio::{
+        DBusConnection, DBusError, DBusNodeInfo, RegistrationId,
+        prelude::{ApplicationExt, DBusMethodCall},
+    },
+    glib::{self, Variant, variant::ToVariant},
+};
+use log::{error, info};
+use serde_json::Value;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// Methods of the exported interface, values are JSON text, e.g. `true` or `"psd"`.
+#[derive(Debug)]
+enum DbusCall {
+    GetValue(String),
+    SetValue(String, String),
+    ListPending,
+    Apply,
+    ActivatePreset(String),
+}
+impl DBusMethodCall for DbusCall {
+    fn parse_call(
+        _obj_path: &str,
+        _interface: Option<&str>,
+        method: &str,
+        params: Variant,
+    ) -> Result<Self, glib::Error> {
+        let call = match method {
+            "GetValue" => params.get::<(String,)>().map(|(key,)| Self::GetValue(key)),
+            "SetValue" => params
+                .get::<(String, String)>()
+                .map(|(key, value)| Self::SetValue(key, value)),
+            "ListPending" => Some(Self::ListPending),
+            "Apply" => Some(Self::Apply),
+            "ActivatePreset" => params
+                .get::<(String,)>()
+                .map(|(name,)| Self::ActivatePreset(name)),
+            _ => None,
+        };
+
+        call.ok_or_else(|| {
+            glib::Error::new(
+                DBusError::InvalidArgs,
+                &format!("Invalid call of {}", method),
+            )
+        })
+    }
+}
+
+type OnApplied = Box<dyn Fn(PwAppliedConfigs)>;
+
+/// D-Bus interface on the app's name for scripts and GNOME extensions.
+/// Backed by the same configs as the pages, so pending values show up in the rows.
+pub struct DbusService {
+    pipewire: RefCell<Option<Rc<Pipewire>>>,
+    watcher: RefCell<Option<Rc<ConfigWatcher>>>,
+    registered: RefCell<Option<(DBusConnection, String, RegistrationId)>>,
+    on_pending: RefCell<Option<Box<dyn Fn()>>>,
+    on_applied: RefCell<Option<OnApplied>>,
+}
+impl DbusService {
+    const LOG_TARGET: &str = "DbusService";
+    pub const INTERFACE: &str = "org.pvermeer.PipewireConfigGnome";
+    const XML: &str = r#"<node>
+  <interface name="org.pvermeer.PipewireConfigGnome">
+    <method name="GetValue">
+      <arg name="key" type="s" direction="in"/>
+      <arg name="value" type="s" direction="out"/>
+    </method>
+    <method name="SetValue">
+      <arg name="key" type="s" direction="in"/>
+      <arg name="value" type="s" direction="in"/>
+    </method>
+    <method name="ListPending">
+      <arg name="pending" type="a{ss}" direction="out"/>
+    </method>
+    <method name="Apply">
+      <arg name="applied" type="as" direction="out"/>
+    </method>
+    <method name="ActivatePreset">
+      <arg name="name" type="s" direction="in"/>
+      <arg name="applied" type="as" direction="out"/>
+    </method>
+    <signal name="Changed">
+      <arg name="key" type="s"/>
+      <arg name="value" type="s"/>
+    </signal>
+  </interface>
+</node>"#;
+
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self {
+            pipewire: RefCell::new(None),
+            watcher: RefCell::new(None),
+            registered: RefCell::new(None),
+            on_pending: RefCell::new(None),
+            on_applied: RefCell::new(None),
+        })
+    }
+
+    /// Exports the interface at the app's object path, once on startup.
+    pub fn export(self: &Rc<Self>, adw_application: &libadwaita::Application) {
+        let (Some(connection), Some(object_path)) = (
+            adw_application.dbus_connection(),
+            adw_application.dbus_object_path(),
+        ) else {
+            return;
+        };
+
+        if let Err(error) = self.register(&connection, &object_path) {
+            error!(target: Self::LOG_TARGET, "Exporting D-Bus interface failed:\n{:?}", error);
+        }
+    }
+
+    /// Exports the interface, e.g. on the app's connection and object path.
+    pub fn register(self: &Rc<Self>, connection: &DBusConnection, object_path: &str) -> Result<()> {
+        let node = DBusNodeInfo::for_xml(Self::XML)?;
+        let interface = node
+            .lookup_interface(Self::INTERFACE)
+            .context("Interface missing from its introspection")?;
+
+        let service = Rc::downgrade(self);
+        let registration = connection
+            .register_object(object_path, &interface)
+            .typed_method_call::<DbusCall>()
+            .invoke_and_return(move |_connection, _sender, call| {
+                let service = service
+                    .upgrade()
+                    .ok_or_else(|| glib::Error::new(DBusError::Failed, "The app is closing"))?;
+
+                service.handle(call).map_err(|error| {
+                    error!(target: Self::LOG_TARGET, "{:?}", error);
+                    match error.downcast::<glib::Error>() {
+                        Ok(error) => error,
+                        Err(error) => glib::Error::new(DBusError::Failed, &format!("{:#}", error)),
+                    }
+                })
+            })
+            .build()?;
+
+        info!(target: Self::LOG_TARGET, "Exported {} at {}", Self::INTERFACE, object_path);
+        self.registered.replace(Some((
+            connection.clone(),
+            object_path.to_string(),
+            registration,
+        )));
+
+        Ok(())
+    }
+
+    /// The configs calls work on, calls fail until the app has started.
+    pub fn set_pipewire(&self, pipewire: Rc<Pipewire>) {
+        self.pipewire.replace(Some(pipewire));
+    }
+
+    /// Paused while calls write drop-ins, so they are not reloaded as outside changes.
+    pub fn set_watcher(&self, watcher: Rc<ConfigWatcher>) {
+        self.watcher.replace(Some(watcher));
+    }
+
+    /// Called after pending values were set, e.g. to show them in the rows.
+    pub fn connect_pending(&self, on_pending: impl Fn() + 'static) {
+        self.on_pending.replace(Some(Box::new(on_pending)));
+    }
+
+    /// Called after an apply or preset activation, e.g. to restart the services.
+    pub fn connect_applied(&self, on_applied: impl Fn(PwAppliedConfigs) + 'static) {
+        self.on_applied.replace(Some(Box::new(on_applied)));
+    }
+
+    /// Emits `Changed` for a pending value, does nothing when the interface is not exported.
+    pub fn emit_changed(&self, key: &str, value: &Value) {
+        let registered = self.registered.borrow();
+        let Some((connection, object_path, _registration)) = registered.as_ref() else {
+            return;
+        };
+
+        let parameters = (key, value.to_string()).to_variant();
+        if let Err(error) = connection.emit_signal(
+            None,
+            object_path,
+            Self::INTERFACE,
+            "Changed",
+            Some(&parameters),
+        ) {
+            error!(target: Self::LOG_TARGET, "Emitting Changed failed:\n{:?}", error);
+        }
+    }
+
+    fn handle(&self, call: DbusCall) -> Result<Option<Variant>> {
+        let pipewire = self.get_pipewire()?;
+        match call {
+            DbusCall::GetValue(key) => {
+                let value = self.get_value(&key)?;
+                Ok(Some((value.to_string(),).to_variant()))
+            }
+            DbusCall::SetValue(key, value) => {
+                let value: Value = serde_json::from_str(&value).context(format!(
+                    "{} should be JSON, e.g. \"psd\" with quotes",
+                    value
+                ))?;
+                self.set_value(&key, value)?;
+                Ok(None)
+            }
+            DbusCall::ListPending => {
+                let mut pending = HashMap::new();
+                for pw_config in pipewire.get_configs() {
+                    for (key, value) in &pw_config.borrow().new {
+                        pending.insert(key.to_owned(), value.to_string());
+                    }
+                }
+                Ok(Some((pending,).to_variant()))
+            }
+            DbusCall::Apply => {
+                let invalid: Vec<String> = pipewire
+                    .get_configs()
+                    .iter()
+                    .flat_map(|pw_config| pw_config.borrow().get_invalid())
+                    .collect();
+                if !invalid.is_empty() {
+                    return Err(glib::Error::new(
+                        DBusError::InvalidArgs,
+                        &format!("Invalid settings:\n{}", invalid.join("\n")),
+                    )
+                    .into());
+                }
+
+                self.snapshot_initial(&pipewire);
+                let _pause = self.pause_watcher();
+                let applied_configs = pipewire.apply_pending()?;
+                let summaries: Vec<String> = applied_configs
+                    .iter()
+                    .map(|(_pw_config, applied)| applied.get_summary())
+                    .collect();
+                self.finish(&pipewire, applied_configs, &summaries.join("\n"));
+                Ok(Some((summaries,).to_variant()))
+            }
+            DbusCall::ActivatePreset(name) => {
+                let preset = pipewire.presets.get(&name)?;
+                self.snapshot_initial(&pipewire);
+                let _pause = self.pause_watcher();
+                let applied_configs = pipewire.activate_preset(&preset)?;
+                let summaries = match applied_configs.is_empty() {
+                    true => Vec::new(),
+                    false => vec![format!("Activated preset {}", name)],
+                };
+                self.finish(&pipewire, applied_configs, &summaries.join("\n"));
+                Ok(Some((summaries,).to_variant()))
+            }
+        }
+    }
+
+    fn get_pipewire(&self) -> Result<Rc<Pipewire>> {
+        self.pipewire
+            .borrow()
+            .clone()
+            .context("The app has not started yet")
+    }
+
+    /// The pending value, else the current one or the upstream default.
+    fn get_value(&self, key: &str) -> Result<Value> {
+        for pw_config in self.get_pipewire()?.get_configs() {
+            let pw_config = pw_config.borrow();
+            let default = pw_config.default.get(key).map(|(value, _options)| value);
+            let value = pw_config
+                .new
+                .get(key)
+                .or(pw_config.current.get(key))
+                .or(default);
+
+            if let Some(value) = value {
+                return Ok(value.to_owned());
+            }
+        }
+
+        Err(anyhow!("Unknown setting {}", key))
+    }
+
+    fn set_value(&self, key: &str, value: Value) -> Result<()> {
+        let pipewire = self.get_pipewire()?;
+        let pw_config = pipewire
+            .get_configs()
+            .into_iter()
+            .find(|pw_config| pw_config.borrow().default.contains_key(key))
+            .context(format!("Unknown setting {}", key))?;
+        PwSchema::bundled()
+            .validate(key, &value)
+            .map_err(|reason| anyhow!("{} {}", key, reason))?;
+
+        pw_config
+            .borrow_mut()
+            .new
+            .insert(key.to_owned(), value.to_owned());
+        self.emit_changed(key, &value);
+
+        if let Some(on_pending) = self.on_pending.borrow().as_ref() {
+            on_pending();
+        }
+
+        Ok(())
+    }
+
+    fn pause_watcher(&self) -> Option<WatcherPause> {
+        self.watcher
+            .borrow()
+            .as_ref()
+            .map(|watcher| watcher.pause())
+    }
+
+    fn snapshot_initial(&self, pipewire: &Pipewire) {
+        if let Err(error) = pipewire.history.snapshot_initial() {
+            error!(target: Self::LOG_TARGET, "Initial snapshot failed:\n{:?}", error);
+        }
+    }
+
+    fn finish(&self, pipewire: &Pipewire, applied_configs: PwAppliedConfigs, summary: &str) {
+        if applied_configs.is_empty() {
+            return;
+        }
+
+        if let Err(error) = pipewire.history.snapshot(summary) {
+            error!(target: Self::LOG_TARGET, "Snapshot failed:\n{:?}", error);
+        }
+        if let Some(on_applied) = self.on_applied.borrow().as_ref() {
+            on_applied(applied_configs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DbusService;
+    use crate::application::pipewire::{
+        config::{
+            PwConfig, PwConfigFile, PwPipewireSection, PwPipewireSectionSub, PwPulseSection,
+            PwPulseSectionSub,
+        },
+        history::PwHistory,
+        pipewire::Pipewire,
+        preset::PwPresets,
+        version::PwVersions,
+    };
+    use anyhow::Result;
+    use libadwaita::{
+        gio::{
+            DBusCallFlags, DBusConnection, DBusConnectionFlags, DBusError, TestDBus, TestDBusFlags,
+        },
+        glib::{self, Variant, VariantTy, variant::ToVariant},
+    };
+    use serde_json::json;
+    use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+    const OBJECT_PATH: &str = "/org/pvermeer/PipewireConfigGnome";
+
+    fn build_pipewire(dir: &std::path::Path) -> Rc<Pipewire> {
+        let surround = PwConfig {
+            default: HashMap::from([("channelmix.upmix".to_string(), (json!(false), None))]),
+            ..PwConfig::for_test(PwConfigFile::PipewirePulse(
+                PwPulseSection::StreamProperties(PwPulseSectionSub::None),
+            ))
+        };
+        let clock = PwConfig::for_test(PwConfigFile::Pipewire(
+            PwPipewireSection::ContextProperties(PwPipewireSectionSub::Clock),
+        ));
+
+        Rc::new(Pipewire {
+            configs: vec![
+                Rc::new(RefCell::new(surround)),
+                Rc::new(RefCell::new(clock)),
+            ],
+            errors: Vec::new(),
+            history: PwHistory::with_store_dir(dir.join("history"), Vec::new()),
+            presets: PwPresets::with_dir(dir.join("presets")),
+            versions: PwVersions::default(),
+        })
+    }
+
+    fn call(
+        connection: &DBusConnection,
+        method: &str,
+        parameters: Option<&Variant>,
+    ) -> Result<Variant, glib::Error> {
+        glib::MainContext::default().block_on(connection.call_future(
+            None,
+            OBJECT_PATH,
+            DbusService::INTERFACE,
+            method,
+            parameters,
+            None,
+            DBusCallFlags::NONE,
+            1000,
+        ))
+    }
+
+    #[test]
+    fn it_should_set_pending_values_on_a_private_bus() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let test_bus = TestDBus::new(TestDBusFlags::NONE);
+        test_bus.up();
+        let address = test_bus.bus_address().unwrap();
+        let connect = || {
+            glib::MainContext::default().block_on(DBusConnection::for_address_future(
+                &address,
+                DBusConnectionFlags::AUTHENTICATION_CLIENT
+                    | DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+                None,
+            ))
+        };
+        let service_connection = connect()?;
+        let client_connection = connect()?;
+
+        let pipewire = build_pipewire(dir.path());
+        let service = DbusService::new();
+        service.set_pipewire(pipewire.clone());
+        let pending_calls = Rc::new(RefCell::new(0));
+        let counter = pending_calls.clone();
+        service.connect_pending(move || *counter.borrow_mut() += 1);
+        service.register(&service_connection, OBJECT_PATH)?;
+
+        let value = call(
+            &client_connection,
+            "GetValue",
+            Some(&("channelmix.upmix",).to_variant()),
+        )?;
+        assert_eq!(value.get::<(String,)>(), Some(("false".to_string(),)));
+
+        call(
+            &client_connection,
+            "SetValue",
+            Some(&("channelmix.upmix", "true").to_variant()),
+        )?;
+        assert_eq!(
+            pipewire.configs[0].borrow().new.get("channelmix.upmix"),
+            Some(&json!(true))
+        );
+        assert_eq!(*pending_calls.borrow(), 1);
+
+        let pending = call(&client_connection, "ListPending", None)?;
+        assert_eq!(pending.type_(), VariantTy::new("(a{ss})").unwrap());
+        assert_eq!(
+            pending.get::<(HashMap<String, String>,)>().unwrap().0,
+            HashMap::from([("channelmix.upmix".to_string(), "true".to_string())])
+        );
+
+        let invalid = call(
+            &client_connection,
+            "SetValue",
+            Some(&("channelmix.upmix", "\"yes\"").to_variant()),
+        );
+        assert!(invalid.is_err());
+        let unknown = call(
+            &client_connection,
+            "GetValue",
+            Some(&("unknown.property",).to_variant()),
+        );
+        assert!(unknown.is_err());
+
+        pipewire.configs[1]
+            .borrow_mut()
+            .new
+            .insert("default.clock.quantum".to_string(), json!("fast"));
+        let error = call(&client_connection, "Apply", None).unwrap_err();
+        assert!(error.matches(DBusError::InvalidArgs));
+        assert!(error.message().contains("default.clock.quantum"));
+
+        test_bus.down();
+
+        Ok(())
+    }
+}
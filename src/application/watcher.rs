@@ -0,0 +1,217 @@
+use super::{
+    Application,
+    pages::Pages,
+    pipewire::{config::PwConflict, spa_json::SpaJson},
+    window::apply_dialog::ApplyDialog,
+};
+use crate::i18n::{gettext, gettext_f};
+use anyhow::anyhow;
+use libadwaita::{
+    MessageDialog, ResponseAppearance,
+    gio::{
+        self, FileMonitor, FileMonitorEvent, FileMonitorFlags,
+        prelude::{FileExt, FileMonitorExt},
+    },
+    glib::{self, SourceId},
+    prelude::{MessageDialogExt, MessageDialogExtManual},
+};
+use log::{debug, error, info};
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+    time::Duration,
+};
+
+/// Reloads the configs when their files change outside of the app,
+/// e.g. a drop-in edited in a text editor.
+pub struct ConfigWatcher {
+    monitors: RefCell<Vec<FileMonitor>>,
+    /// Held pauses, changes are ignored while there is one
+    pauses: Cell<usize>,
+    /// Editors write in steps, the reload waits until it is quiet
+    scheduled: RefCell<Option<SourceId>>,
+}
+impl ConfigWatcher {
+    const LOG_TARGET: &str = "watcher";
+    const QUIET_TIME: Duration = Duration::from_millis(500);
+    /// Events of a write can arrive after it finished, e.g. the changes done hint
+    const RESUME_DELAY: Duration = Duration::from_secs(2);
+    const RESPONSE_MINE: &str = "mine";
+    const RESPONSE_THEIRS: &str = "theirs";
+
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self {
+            monitors: RefCell::new(Vec::new()),
+            pauses: Cell::new(0),
+            scheduled: RefCell::new(None),
+        })
+    }
+
+    /// Watches the dirs of the config files and drop-ins.
+    pub fn watch(self: &Rc<Self>, application: &Rc<Application>) {
+        let mut monitors = self.monitors.borrow_mut();
+        monitors.clear();
+
+        for dir in application.pipewire.get_watched_dirs() {
+            let monitor = match gio::File::for_path(&dir)
+                .monitor_directory(FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+            {
+                Ok(monitor) => monitor,
+                Err(error) => {
+                    error!(target: Self::LOG_TARGET, "Watching {} failed: {}", dir.display(), error);
+                    continue;
+                }
+            };
+
+            let watcher = Rc::downgrade(self);
+            let weak_application = Rc::downgrade(application);
+            monitor.connect_changed(move |_monitor, file, other_file, event| {
+                if !Self::is_config_change(file, other_file, event) {
+                    return;
+                }
+                let Some(watcher) = watcher.upgrade() else {
+                    return;
+                };
+                if watcher.pauses.get() > 0 {
+                    debug!(target: Self::LOG_TARGET, "Ignored {:?} {:?}", event, file.path());
+                    return;
+                }
+                debug!(target: Self::LOG_TARGET, "{:?} {:?}", event, file.path());
+
+                watcher.schedule_reload(weak_application.clone());
+            });
+
+            debug!(target: Self::LOG_TARGET, "Watching {}", dir.display());
+            monitors.push(monitor);
+        }
+    }
+
+    /// Ignores changes until the pause is dropped, e.g. while the app writes drop-ins itself.
+    pub fn pause(self: &Rc<Self>) -> WatcherPause {
+        self.pauses.set(self.pauses.get() + 1);
+
+        WatcherPause {
+            watcher: Rc::downgrade(self),
+        }
+    }
+
+    /// Only finished writes of config files, not the temporary files of an atomic write.
+    /// A rename has the new name in the other file.
+    fn is_config_change(
+        file: &gio::File,
+        other_file: Option<&gio::File>,
+        event: FileMonitorEvent,
+    ) -> bool {
+        let is_conf = |file: &gio::File| {
+            file.basename().is_some_and(|name| {
+                name.extension()
+                    .is_some_and(|extension| extension == "conf")
+            })
+        };
+
+        (is_conf(file) || other_file.is_some_and(is_conf))
+            && matches!(
+                event,
+                FileMonitorEvent::ChangesDoneHint
+                    | FileMonitorEvent::Created
+                    | FileMonitorEvent::Deleted
+                    | FileMonitorEvent::MovedIn
+                    | FileMonitorEvent::MovedOut
+                    | FileMonitorEvent::Renamed
+            )
+    }
+
+    fn schedule_reload(self: &Rc<Self>, application: Weak<Application>) {
+        if let Some(source_id) = self.scheduled.take() {
+            source_id.remove();
+        }
+
+        let watcher = Rc::downgrade(self);
+        let source_id = glib::timeout_add_local_once(Self::QUIET_TIME, move || {
+            let Some(watcher) = watcher.upgrade() else {
+                return;
+            };
+            watcher.scheduled.take();
+
+            if let Some(application) = application.upgrade() {
+                glib::spawn_future_local(Self::reload(application));
+            }
+        });
+        self.scheduled.replace(Some(source_id));
+    }
+
+    async fn reload(application: Rc<Application>) {
+        let (conflicts, errors) = application.pipewire.reload_external();
+        info!(target: Self::LOG_TARGET, "Reloaded configs, {} conflicts", conflicts.len());
+        Self::show_values(&application);
+
+        if !errors.is_empty() {
+            let reasons: Vec<String> = errors.iter().map(|error| format!("{:#}", error)).collect();
+            ApplyDialog::show_error(
+                &application.window.window,
+                &gettext("Could not reload the changed configs"),
+                &anyhow!(reasons.join("\n")),
+            )
+            .await;
+        }
+
+        for (pw_config, conflict) in conflicts {
+            if !Self::ask_keep_mine(&application, &conflict).await {
+                pw_config.borrow_mut().new.remove(&conflict.key);
+            }
+        }
+        Self::show_values(&application);
+    }
+
+    fn show_values(application: &Rc<Application>) {
+        application
+            .pages
+            .borrow_mut()
+            .show_values(application.clone());
+        Pages::show_enabled(application);
+    }
+
+    /// Asks which value of a setting that was also changed outside of the app is kept.
+    async fn ask_keep_mine(application: &Application, conflict: &PwConflict) -> bool {
+        let pending = SpaJson::value_to_string(&conflict.pending);
+        let body = match &conflict.external {
+            Some(external) => gettext_f(
+                "{} was changed to {} outside of the app. Your unapplied value is {}.",
+                &[&conflict.key, &SpaJson::value_to_string(external), &pending],
+            ),
+            None => gettext_f(
+                "{} was removed outside of the app. Your unapplied value is {}.",
+                &[&conflict.key, &pending],
+            ),
+        };
+        let dialog = MessageDialog::new(
+            Some(&application.window.window),
+            Some(&gettext("Setting Changed Elsewhere")),
+            Some(&body),
+        );
+        dialog.add_responses(&[
+            (Self::RESPONSE_THEIRS, &gettext("Use Theirs")),
+            (Self::RESPONSE_MINE, &gettext("Keep Mine")),
+        ]);
+        dialog.set_response_appearance(Self::RESPONSE_MINE, ResponseAppearance::Suggested);
+        dialog.set_default_response(Some(Self::RESPONSE_MINE));
+        dialog.set_close_response(Self::RESPONSE_MINE);
+
+        dialog.choose_future().await == Self::RESPONSE_MINE
+    }
+}
+
+/// Keeps the watcher paused, it resumes a bit after this is dropped.
+pub struct WatcherPause {
+    watcher: Weak<ConfigWatcher>,
+}
+impl Drop for WatcherPause {
+    fn drop(&mut self) {
+        let watcher = self.watcher.clone();
+        glib::timeout_add_local_once(ConfigWatcher::RESUME_DELAY, move || {
+            if let Some(watcher) = watcher.upgrade() {
+                watcher.pauses.set(watcher.pauses.get().saturating_sub(1));
+            }
+        });
+    }
+}
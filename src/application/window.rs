@@ -1,9 +1,14 @@
+pub mod apply_dialog;
+pub mod countdown;
+pub mod error_page;
+pub mod file_chooser;
+pub mod preferences_window;
 pub mod view;
 
-use super::Application;
-use crate::config;
+use super::{Application, preferences::Preferences};
+use crate::{config, i18n::gettext};
 use libadwaita::{
-    AboutWindow,
+    AboutWindow, glib,
     gtk::{
         self,
         prelude::{GtkWindowExt, WidgetExt},
@@ -18,14 +23,17 @@ pub struct ApplicationWindow {
     pub view: View,
 }
 impl ApplicationWindow {
-    pub fn new(adw_application: &libadwaita::Application) -> Self {
+    /// Opens with the size and maximized state of the last run.
+    pub fn new(adw_application: &libadwaita::Application, preferences: &Preferences) -> Self {
         let title = config::APP_NAME.to_string();
         let view = View::new();
+        let (width, height, is_maximized) = preferences.get_window_state();
         let window = libadwaita::ApplicationWindow::builder()
             .application(adw_application)
             .title(&title)
-            .default_height(600)
-            .default_width(800)
+            .default_height(height)
+            .default_width(width)
+            .maximized(is_maximized)
             .content(&view.split_view)
             .build();
 
@@ -40,6 +48,14 @@ impl ApplicationWindow {
             .insert_action_group(View::ACTION_LABEL, Some(&self.view.actions));
         self.window.add_breakpoint(self.view.breakpoint.clone());
 
+        // The default size is the size without maximizing
+        let preferences = application.preferences.clone();
+        self.window.connect_close_request(move |window| {
+            let (width, height) = window.default_size();
+            preferences.set_window_state(width, height, window.is_maximized());
+            glib::Propagation::Proceed
+        });
+
         self.window.present();
     }
 
@@ -48,7 +64,12 @@ impl ApplicationWindow {
         about.set_application_name(config::APP_NAME);
         about.set_version(config::VERSION);
         about.set_developer_name("Me");
-        about.add_credit_section(Some("Code by"), &["Me"]);
+        about.add_credit_section(Some(&gettext("Code by")), &["Me"]);
+        // Translators: your names, one per line, shown in the about window
+        let translator_credits = gettext("translator-credits");
+        if translator_credits != "translator-credits" {
+            about.set_translator_credits(&translator_credits);
+        }
         about.add_acknowledgement_section(None, &["Also me"]);
         about.add_legal_section("Some title", None, gtk::License::Gpl30, None);
         about.show();
@@ -0,0 +1,362 @@
+use super::countdown::Countdown;
+use crate::{
+    application::{
+        Application,
+        pipewire::{
+            config::PwConfig, pipewire::PwAppliedConfigs, preset::PwPreset, services::PwServices,
+            spa_json::SpaJson,
+        },
+    },
+    i18n::{gettext, gettext_f},
+};
+use anyhow::{Result, anyhow, bail};
+use libadwaita::{
+    MessageDialog, ResponseAppearance, gio, glib,
+    prelude::{MessageDialogExt, MessageDialogExtManual},
+};
+use log::{error, info};
+use serde_json::{Map, Value};
+use std::{cell::RefCell, rc::Rc};
+
+/// Dialogs for after drop-ins are written.
+pub struct ApplyDialog;
+impl ApplyDialog {
+    const LOG_TARGET: &str = "apply-dialog";
+    const RESPONSE_LATER: &str = "later";
+    const RESPONSE_RESTART: &str = "restart";
+    const RESPONSE_KEEP: &str = "keep";
+    const RESPONSE_REVERT: &str = "revert";
+    const RESPONSE_CLOSE: &str = "close";
+    const RESPONSE_CANCEL: &str = "cancel";
+    const RESPONSE_APPLY: &str = "apply";
+    const KEEP_SECONDS: u32 = 20;
+    const SETTLE_SECONDS: u32 = 10;
+
+    /// Writes the pending values of a page to its drop-in, then continues with the restart.
+    /// Asks first when the preferences want that.
+    pub async fn apply(application: Rc<Application>, pw_config: Rc<RefCell<PwConfig>>) {
+        let window = &application.window.window;
+        let history = &application.pipewire.history;
+        let pending = pw_config.borrow().new.clone();
+        if application.preferences.get_confirm_apply()
+            && !Self::confirm_apply(window, &pending).await
+        {
+            info!(target: Self::LOG_TARGET, "Apply not confirmed");
+            return;
+        }
+
+        if let Err(error) = history.snapshot_initial() {
+            error!(target: Self::LOG_TARGET, "Initial snapshot failed:\n{:?}", error);
+        }
+
+        let applied = {
+            let _pause = application.watcher.pause();
+            pw_config.borrow_mut().apply()
+        };
+        match applied {
+            Ok(applied) => {
+                info!(target: Self::LOG_TARGET, "Applied to {}", applied.path.display());
+
+                if let Err(error) = history.snapshot(&applied.get_summary()) {
+                    error!(target: Self::LOG_TARGET, "Snapshot failed:\n{:?}", error);
+                }
+
+                let applied = vec![(pw_config.clone(), applied)];
+                Self::restart_affected(application.clone(), applied).await;
+            }
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Apply failed:\n{:?}", error);
+                Self::show_error(window, &gettext("Could not apply settings"), &error).await;
+            }
+        };
+    }
+
+    /// Lists the pending values before they are written, `true` when they should be.
+    pub async fn confirm_apply(
+        window: &libadwaita::ApplicationWindow,
+        pending: &Map<String, Value>,
+    ) -> bool {
+        let changes: Vec<String> = pending
+            .iter()
+            .map(|(key, value)| format!("{} = {}", key, SpaJson::value_to_string(value)))
+            .collect();
+        let dialog = MessageDialog::new(
+            Some(window),
+            Some(&gettext("Apply these settings?")),
+            Some(&changes.join("\n")),
+        );
+        dialog.add_responses(&[
+            (Self::RESPONSE_CANCEL, &gettext("Cancel")),
+            (Self::RESPONSE_APPLY, &gettext("Apply")),
+        ]);
+        dialog.set_response_appearance(Self::RESPONSE_APPLY, ResponseAppearance::Suggested);
+        dialog.set_default_response(Some(Self::RESPONSE_APPLY));
+        dialog.set_close_response(Self::RESPONSE_CANCEL);
+
+        dialog.choose_future().await == Self::RESPONSE_APPLY
+    }
+
+    /// Asks to restart the services affected by the applied configs and restarts them.
+    /// The previous drop-ins are restored if the restart or the services fail, or the settings are not kept.
+    pub async fn restart_affected(application: Rc<Application>, applied: PwAppliedConfigs) {
+        let window = &application.window.window;
+        let files: Vec<_> = applied
+            .iter()
+            .map(|(_pw_config, applied)| applied.config_file)
+            .collect();
+        let units = PwServices::get_affected_units(&files);
+
+        if !Self::ask_restart(window, &units).await {
+            info!(target: Self::LOG_TARGET, "Restart postponed for {:?}", units);
+            return;
+        }
+
+        let services = match PwServices::connect().await {
+            Ok(services) => services,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Connecting to systemd failed:\n{:?}", error);
+                Self::rollback(&application, None, &units, applied).await;
+                Self::show_error(window, &gettext("Settings were reverted"), &error).await;
+                return;
+            }
+        };
+        let restarted = match services.restart(&units).await {
+            Ok(restarted) => restarted,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Restarting services failed:\n{:?}", error);
+                Self::rollback(&application, Some(&services), &units, applied).await;
+                Self::show_error(window, &gettext("Settings were reverted"), &error).await;
+                return;
+            }
+        };
+        // Nothing to check or revert, the settings are used when the services start
+        if restarted.is_empty() {
+            info!(target: Self::LOG_TARGET, "None of {:?} is running, skipped health check", units);
+            return;
+        }
+
+        match Self::wait_for_services(&services, &restarted).await {
+            Ok(()) => {
+                if Self::ask_keep(window).await {
+                    info!(target: Self::LOG_TARGET, "Kept applied settings");
+                    return;
+                }
+                Self::rollback(&application, Some(&services), &units, applied).await;
+            }
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Services did not come back:\n{:?}", error);
+                Self::rollback(&application, Some(&services), &units, applied).await;
+                Self::show_error(window, &gettext("Settings were reverted"), &error).await;
+            }
+        }
+    }
+
+    /// Writes the drop-ins of a preset, then continues like an apply.
+    pub async fn activate_preset(application: Rc<Application>, preset: PwPreset) {
+        let window = &application.window.window;
+        let history = &application.pipewire.history;
+        if let Err(error) = history.snapshot_initial() {
+            error!(target: Self::LOG_TARGET, "Initial snapshot failed:\n{:?}", error);
+        }
+
+        let activated = {
+            let _pause = application.watcher.pause();
+            application.pipewire.activate_preset(&preset)
+        };
+        let applied = match activated {
+            Ok(applied) => applied,
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Activating preset failed:\n{:?}", error);
+                Self::show_error(window, &gettext("Could not activate preset"), &error).await;
+                return;
+            }
+        };
+        if applied.is_empty() {
+            info!(target: Self::LOG_TARGET, "Preset {} is already active", preset.name);
+            return;
+        }
+
+        info!(target: Self::LOG_TARGET, "Activated preset {}", preset.name);
+        if let Err(error) = history.snapshot(&format!("Activated preset {}", preset.name)) {
+            error!(target: Self::LOG_TARGET, "Snapshot failed:\n{:?}", error);
+        }
+
+        Self::restart_affected(application.clone(), applied).await;
+    }
+
+    /// Asks to restart the units and restarts them, without keeping track of what changed.
+    pub async fn restart_units(window: &libadwaita::ApplicationWindow, units: &[&str]) {
+        if !Self::ask_restart(window, units).await {
+            info!(target: Self::LOG_TARGET, "Restart postponed for {:?}", units);
+            return;
+        }
+
+        let restarted = match PwServices::connect().await {
+            Ok(services) => services.restart(units).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = restarted {
+            error!(target: Self::LOG_TARGET, "Restarting services failed:\n{:?}", error);
+            Self::show_error(window, &gettext("Could not restart audio services"), &error).await;
+        }
+    }
+
+    pub async fn show_error(
+        window: &libadwaita::ApplicationWindow,
+        heading: &str,
+        error: &anyhow::Error,
+    ) {
+        let dialog = MessageDialog::new(Some(window), Some(heading), Some(&format!("{:#}", error)));
+        dialog.add_response(Self::RESPONSE_CLOSE, &gettext("Close"));
+        dialog.choose_future().await;
+    }
+
+    async fn ask_restart(window: &libadwaita::ApplicationWindow, units: &[&str]) -> bool {
+        // pw-dump blocks, so it runs off the main loop
+        let owned_units: Vec<String> = units.iter().map(|unit| unit.to_string()).collect();
+        let clients = gio::spawn_blocking(move || {
+            let units: Vec<&str> = owned_units.iter().map(String::as_str).collect();
+            PwServices::get_interrupted_clients(&units)
+        })
+        .await
+        .unwrap_or_else(|_panic| Err(anyhow!("Listing clients panicked")))
+        .unwrap_or_else(|error| {
+            error!(target: Self::LOG_TARGET, "Listing clients failed:\n{:?}", error);
+            Vec::new()
+        });
+
+        let mut body = gettext_f(
+            "The settings take effect after restarting {}.",
+            &[&units.join(", ")],
+        );
+        if !clients.is_empty() {
+            body.push_str("\n\n");
+            body.push_str(&gettext("These applications will be interrupted:"));
+            for client in &clients {
+                body.push_str(&format!("\n• {}", client.name));
+            }
+        }
+
+        let dialog = MessageDialog::new(
+            Some(window),
+            Some(&gettext("Restart audio services?")),
+            Some(&body),
+        );
+        dialog.add_responses(&[
+            (Self::RESPONSE_LATER, &gettext("Later")),
+            (Self::RESPONSE_RESTART, &gettext("Restart")),
+        ]);
+        dialog.set_response_appearance(
+            Self::RESPONSE_RESTART,
+            match clients.is_empty() {
+                true => ResponseAppearance::Suggested,
+                false => ResponseAppearance::Destructive,
+            },
+        );
+        dialog.set_default_response(Some(Self::RESPONSE_RESTART));
+        dialog.set_close_response(Self::RESPONSE_LATER);
+
+        dialog.choose_future().await == Self::RESPONSE_RESTART
+    }
+
+    /// Waits until the restarted units are running again or one of them failed.
+    async fn wait_for_services(services: &PwServices, restarted: &[String]) -> Result<()> {
+        for _second in 0..Self::SETTLE_SECONDS {
+            glib::timeout_future_seconds(1).await;
+
+            if services.check_health(restarted).await? {
+                return Ok(());
+            }
+        }
+
+        bail!(
+            "{} did not start within {} seconds",
+            restarted.join(", "),
+            Self::SETTLE_SECONDS
+        );
+    }
+
+    /// Like display settings, the settings are reverted unless confirmed in time.
+    async fn ask_keep(window: &libadwaita::ApplicationWindow) -> bool {
+        let dialog = MessageDialog::new(Some(window), Some(&gettext("Keep these settings?")), None);
+        dialog.add_responses(&[
+            (Self::RESPONSE_REVERT, &gettext("Revert")),
+            (Self::RESPONSE_KEEP, &gettext("Keep Settings")),
+        ]);
+        dialog.set_response_appearance(Self::RESPONSE_KEEP, ResponseAppearance::Suggested);
+        dialog.set_default_response(Some(Self::RESPONSE_KEEP));
+        dialog.set_close_response(Self::RESPONSE_REVERT);
+
+        let dialog_tick = dialog.clone();
+        let dialog_done = dialog.clone();
+        let countdown = Countdown::start(
+            Self::KEEP_SECONDS,
+            move |remaining| {
+                dialog_tick.set_body(&gettext_f(
+                    "The previous settings are restored in {} seconds.",
+                    &[&remaining.to_string()],
+                ));
+            },
+            move || dialog_done.response(Self::RESPONSE_REVERT),
+        );
+
+        let response = dialog.choose_future().await;
+        countdown.stop();
+
+        response == Self::RESPONSE_KEEP
+    }
+
+    async fn rollback(
+        application: &Application,
+        services: Option<&PwServices>,
+        units: &[&str],
+        applied: PwAppliedConfigs,
+    ) {
+        let window = &application.window.window;
+        let summaries: Vec<String> = applied
+            .iter()
+            .map(|(_pw_config, applied)| applied.get_summary())
+            .collect();
+
+        // Every config is rolled back, one that fails does not keep the others on the new drop-ins
+        let mut errors = Vec::new();
+        {
+            let _pause = application.watcher.pause();
+            for (pw_config, applied) in applied {
+                let rolled_back = pw_config.borrow_mut().rollback(applied);
+                if let Err(error) = rolled_back {
+                    error!(target: Self::LOG_TARGET, "Rolling back failed:\n{:?}", error);
+                    errors.push(format!("{:#}", error));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            Self::show_error(
+                window,
+                &gettext("Could not restore the previous settings"),
+                &anyhow!(errors.join("\n")),
+            )
+            .await;
+        }
+
+        let summary = format!("Reverted {}", summaries.join("; "));
+        if let Err(error) = application.pipewire.history.snapshot(&summary) {
+            error!(target: Self::LOG_TARGET, "Snapshot after rollback failed:\n{:?}", error);
+        }
+
+        // Without a connection the restored drop-ins are used when the services start
+        let Some(services) = services else {
+            return;
+        };
+        match services.restart(units).await {
+            Ok(restarted) => {
+                info!(target: Self::LOG_TARGET, "Rolled back and restarted {:?}", restarted)
+            }
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Restarting after rollback failed:\n{:?}", error);
+                Self::show_error(window, &gettext("Could not restart audio services"), &error)
+                    .await;
+            }
+        }
+    }
+}
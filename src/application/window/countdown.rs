@@ -0,0 +1,45 @@
+use libadwaita::glib::{self, ControlFlow, SourceId};
+use std::{cell::RefCell, rc::Rc};
+
+/// Ticks every second until zero, can be stopped before it runs out.
+pub struct Countdown {
+    source: Rc<RefCell<Option<SourceId>>>,
+}
+impl Countdown {
+    /// Calls `on_tick` with the remaining seconds and `on_done` when it reaches zero.
+    pub fn start(
+        seconds: u32,
+        on_tick: impl Fn(u32) + 'static,
+        on_done: impl FnOnce() + 'static,
+    ) -> Self {
+        let source: Rc<RefCell<Option<SourceId>>> = Rc::new(RefCell::new(None));
+        let source_done = source.clone();
+        let mut remaining = seconds;
+        let mut on_done = Some(on_done);
+        on_tick(remaining);
+
+        let source_id = glib::timeout_add_seconds_local(1, move || {
+            remaining = remaining.saturating_sub(1);
+            if remaining > 0 {
+                on_tick(remaining);
+                return ControlFlow::Continue;
+            }
+
+            // The source is removed by returning Break
+            source_done.borrow_mut().take();
+            if let Some(on_done) = on_done.take() {
+                on_done();
+            }
+            ControlFlow::Break
+        });
+        source.replace(Some(source_id));
+
+        Self { source }
+    }
+
+    pub fn stop(&self) {
+        if let Some(source_id) = self.source.borrow_mut().take() {
+            source_id.remove();
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    application::pipewire::error::PwError,
+    config,
+    i18n::{gettext, gettext_f},
+};
+use libadwaita::{
+    HeaderBar, StatusPage, ToolbarView, glib,
+    gtk::{
+        self, Button, Expander, Label, Orientation, ScrolledWindow,
+        prelude::{BoxExt, ButtonExt, GtkWindowExt, WidgetExt},
+    },
+};
+
+/// Window shown instead of the app when it can not start.
+pub struct ErrorPage;
+impl ErrorPage {
+    /// Shows why the app can not start, with a command that fixes it to copy.
+    pub fn show(
+        adw_application: &libadwaita::Application,
+        title: &str,
+        description: &str,
+        command: Option<&str>,
+    ) {
+        let status_page = StatusPage::builder()
+            .icon_name("dialog-warning-symbolic")
+            .title(title)
+            .description(description)
+            .build();
+
+        if let Some(command) = command {
+            let content_box = gtk::Box::builder()
+                .orientation(Orientation::Vertical)
+                .spacing(12)
+                .halign(gtk::Align::Center)
+                .build();
+            let command_label = Label::builder()
+                .label(command)
+                .selectable(true)
+                .wrap(true)
+                .css_classes(["monospace"])
+                .build();
+            let copy_button = Button::builder()
+                .label(gettext("Copy Command"))
+                .halign(gtk::Align::Center)
+                .css_classes(["pill", "suggested-action"])
+                .build();
+
+            let command = command.to_string();
+            copy_button.connect_clicked(move |button| button.clipboard().set_text(&command));
+
+            content_box.append(&command_label);
+            content_box.append(&copy_button);
+            status_page.set_child(Some(&content_box));
+        }
+
+        Self::present(adw_application, &status_page);
+    }
+
+    /// Shows why PipeWire configs could not be read, with the details to copy.
+    /// With `on_continue` the app can start with the pages whose config was read.
+    pub fn show_startup_error(
+        adw_application: &libadwaita::Application,
+        errors: &[anyhow::Error],
+        on_retry: impl Fn() + 'static,
+        on_continue: Option<Box<dyn Fn()>>,
+    ) {
+        let pw_error = errors
+            .iter()
+            .find_map(|error| error.downcast_ref::<PwError>());
+        let (title, hint) = match (pw_error, on_continue.is_some()) {
+            (_, true) => (
+                gettext("Some Settings Are Unavailable"),
+                gettext("Not every PipeWire config could be read, the pages of the others work."),
+            ),
+            (Some(pw_error), false) => {
+                let (hint, args) = pw_error.get_hint();
+                (gettext(pw_error.get_title()), gettext_f(hint, &args))
+            }
+            (None, false) => (
+                gettext("PipeWire Config Could Not Be Read"),
+                gettext("Check that PipeWire is installed and running, then retry."),
+            ),
+        };
+        let details: Vec<String> = errors.iter().map(|error| format!("{:?}", error)).collect();
+        let details = details.join("\n\n");
+
+        let status_page = StatusPage::builder()
+            .icon_name("dialog-error-symbolic")
+            .title(title)
+            .description(glib::markup_escape_text(&hint))
+            .build();
+
+        let details_label = Label::builder()
+            .label(&details)
+            .selectable(true)
+            .wrap(true)
+            .xalign(0.0)
+            .css_classes(["monospace"])
+            .build();
+        let details_expander = Expander::builder()
+            .label(gettext("Details"))
+            .child(
+                &ScrolledWindow::builder()
+                    .child(&details_label)
+                    .max_content_height(200)
+                    .propagate_natural_height(true)
+                    .build(),
+            )
+            .build();
+
+        let button_box = gtk::Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(12)
+            .halign(gtk::Align::Center)
+            .build();
+        let retry_button = Self::build_button(&gettext("Retry"), on_continue.is_none());
+        let copy_button = Self::build_button(&gettext("Copy Details"), false);
+        copy_button.connect_clicked(move |button| button.clipboard().set_text(&details));
+        button_box.append(&retry_button);
+        button_box.append(&copy_button);
+
+        let content_box = gtk::Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(12)
+            .build();
+        content_box.append(&button_box);
+        content_box.append(&details_expander);
+        status_page.set_child(Some(&content_box));
+
+        let window = Self::present(adw_application, &status_page);
+
+        if let Some(on_continue) = on_continue {
+            let continue_button = Self::build_button(&gettext("Continue Anyway"), true);
+            let continue_window = window.clone();
+            continue_button.connect_clicked(move |_button| {
+                on_continue();
+                continue_window.close();
+            });
+            button_box.append(&continue_button);
+        }
+        // The app is started again before this window closes, so it keeps running
+        retry_button.connect_clicked(move |_button| {
+            on_retry();
+            window.close();
+        });
+    }
+
+    fn build_button(label: &str, is_suggested: bool) -> Button {
+        let button = Button::builder().label(label).css_classes(["pill"]).build();
+        if is_suggested {
+            button.add_css_class("suggested-action");
+        }
+
+        button
+    }
+
+    fn present(
+        adw_application: &libadwaita::Application,
+        status_page: &StatusPage,
+    ) -> libadwaita::ApplicationWindow {
+        let toolbar = ToolbarView::new();
+        toolbar.add_top_bar(&HeaderBar::new());
+        toolbar.set_content(Some(status_page));
+
+        let window = libadwaita::ApplicationWindow::builder()
+            .application(adw_application)
+            .title(config::APP_NAME)
+            .default_height(600)
+            .default_width(800)
+            .content(&toolbar)
+            .build();
+        window.present();
+
+        window
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use ashpd::{
+    WindowIdentifier,
+    desktop::{
+        ResponseError,
+        file_chooser::{FileFilter, SelectedFiles},
+    },
+};
+use std::path::PathBuf;
+
+/// File dialogs through the FileChooser portal, so they also work in a sandbox.
+/// They are modal for the window they are opened from.
+pub struct FileChooser;
+impl FileChooser {
+    /// `None` when the user cancelled.
+    pub async fn open(
+        window: &libadwaita::ApplicationWindow,
+        title: &str,
+        filter: FileFilter,
+    ) -> Result<Option<PathBuf>> {
+        let selected = SelectedFiles::open_file()
+            .identifier(WindowIdentifier::from_native(window).await)
+            .title(title)
+            .modal(true)
+            .multiple(false)
+            .filter(filter)
+            .send()
+            .await
+            .and_then(|request| request.response());
+
+        Self::get_path(selected)
+    }
+
+    /// `None` when the user cancelled.
+    pub async fn save(
+        window: &libadwaita::ApplicationWindow,
+        title: &str,
+        current_name: &str,
+        filter: FileFilter,
+    ) -> Result<Option<PathBuf>> {
+        let selected = SelectedFiles::save_file()
+            .identifier(WindowIdentifier::from_native(window).await)
+            .title(title)
+            .modal(true)
+            .current_name(current_name)
+            .filter(filter)
+            .send()
+            .await
+            .and_then(|request| request.response());
+
+        Self::get_path(selected)
+    }
+
+    fn get_path(selected: ashpd::Result<SelectedFiles>) -> Result<Option<PathBuf>> {
+        let selected = match selected {
+            Ok(selected) => selected,
+            Err(ashpd::Error::Response(ResponseError::Cancelled)) => return Ok(None),
+            Err(error) => return Err(error).context("Choosing a file"),
+        };
+
+        let uri = selected.uris().first().context("No file was chosen")?;
+        let path = uri
+            .to_file_path()
+            .ok()
+            .context(format!("{} is not a local file", uri))?;
+
+        Ok(Some(path))
+    }
+}
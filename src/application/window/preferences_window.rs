@@ -0,0 +1,83 @@
+use crate::{
+    application::{Application, pipewire::dropin::PwDropInScope},
+    i18n::gettext,
+};
+use libadwaita::{
+    ComboRow, PreferencesGroup, PreferencesPage, SwitchRow,
+    gtk::{StringList, prelude::GtkWindowExt},
+    prelude::{ComboRowExt, PreferencesGroupExt, PreferencesPageExt, PreferencesWindowExt},
+};
+use std::rc::Rc;
+
+/// The app preferences, saved as soon as they change.
+pub struct PreferencesWindow;
+impl PreferencesWindow {
+    pub fn show(application: &Rc<Application>) {
+        let preferences = &application.preferences;
+
+        let advanced_row = SwitchRow::builder()
+            .title(gettext("Show advanced settings"))
+            .subtitle(gettext(
+                "Settings that are rarely changed, e.g. memory locking and log level",
+            ))
+            .active(preferences.get_show_advanced())
+            .build();
+        let advanced_application = application.clone();
+        advanced_row.connect_active_notify(move |advanced_row| {
+            let is_shown = advanced_row.is_active();
+            advanced_application.preferences.set_show_advanced(is_shown);
+            advanced_application.pages.borrow().show_advanced(is_shown);
+        });
+
+        let confirm_row = SwitchRow::builder()
+            .title(gettext("Confirm before apply"))
+            .subtitle(gettext("List the pending settings before they are written"))
+            .active(preferences.get_confirm_apply())
+            .build();
+        let confirm_application = application.clone();
+        confirm_row.connect_active_notify(move |confirm_row| {
+            confirm_application
+                .preferences
+                .set_confirm_apply(confirm_row.is_active());
+        });
+
+        let scope = preferences.get_dropin_scope();
+        let scope_row = ComboRow::builder()
+            .title(gettext("Drop-in location"))
+            .subtitle(gettext(
+                "Drop-ins for all users in /etc can not be written by the app",
+            ))
+            .model(&StringList::new(&[&gettext("Only for you, in ~/.config")]))
+            .selected(
+                PwDropInScope::ALL
+                    .iter()
+                    .position(|other| *other == scope)
+                    .unwrap_or_default() as u32,
+            )
+            .build();
+        let scope_application = application.clone();
+        scope_row.connect_selected_notify(move |scope_row| {
+            if let Some(scope) = PwDropInScope::ALL.get(scope_row.selected() as usize) {
+                scope_application.preferences.set_dropin_scope(*scope);
+            }
+        });
+
+        let settings_group = PreferencesGroup::builder()
+            .title(gettext("Settings"))
+            .build();
+        settings_group.add(&advanced_row);
+        settings_group.add(&confirm_row);
+        settings_group.add(&scope_row);
+
+        let page = PreferencesPage::new();
+        page.add(&settings_group);
+
+        let window = libadwaita::PreferencesWindow::builder()
+            .transient_for(&application.window.window)
+            .modal(true)
+            .search_enabled(false)
+            .build();
+        window.add(&page);
+        window.present();
+    }
+}
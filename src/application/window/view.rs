@@ -1,10 +1,7 @@
 pub mod app_menu;
 mod sidebar_page;
 
-use crate::application::{
-    Application,
-    pages::{NavPage, Page},
-};
+use crate::application::Application;
 use app_menu::AppMenu;
 use libadwaita::{
     Breakpoint, BreakpointCondition, NavigationSplitView,
@@ -14,6 +11,7 @@ use libadwaita::{
     },
     glib::{Value, Variant, VariantTy},
 };
+use log::warn;
 use sidebar_page::SidebarPage;
 use std::rc::Rc;
 
@@ -28,6 +26,7 @@ impl View {
     pub const ACTION_LABEL: &str = "view";
     pub const NAVIGATE_ACTION_LABEL: &str = "navigate";
     pub const VIEW_NAVIGATE_ACTION_LABEL: &str = "view.navigate";
+    const LOG_TARGET: &str = "view";
 
     pub fn new() -> Self {
         let sidebar = SidebarPage::new();
@@ -51,14 +50,17 @@ impl View {
     pub fn init(&self, application: &Rc<Application>) {
         let navigation_action = self.build_navigate_action(&self.split_view, application);
 
+        self.app_menu.init(application);
+        self.sidebar.init_search(application);
         self.sidebar.header.pack_end(&self.app_menu.button);
         self.breakpoint
             .add_setter(&self.split_view, "collapsed", Some(&Value::from(true)));
         self.actions.add_action_entries([navigation_action]);
     }
 
-    pub fn navigate(&self, page: Page) {
-        let action_target = Variant::from(page as i32);
+    /// Shows the page with this id from the registry.
+    pub fn navigate(&self, page_id: &str) {
+        let action_target = Variant::from(page_id);
         self.actions
             .activate_action(Self::NAVIGATE_ACTION_LABEL, Some(&action_target));
     }
@@ -73,21 +75,18 @@ impl View {
         let pages = application.pages.clone();
 
         let action = ActionEntry::builder(View::NAVIGATE_ACTION_LABEL)
-            .parameter_type(Some(VariantTy::INT32))
+            .parameter_type(Some(VariantTy::STRING))
             .activate(move |_, _, parameter| {
-                // Using an int as parameter to map back to the enum
-                let enum_page_index = parameter.unwrap().try_get::<i32>().unwrap();
-                let page_enum: Page = unsafe { ::std::mem::transmute(enum_page_index) };
-                let mut pages_mut = pages.borrow_mut();
+                let page_id = parameter.unwrap().try_get::<String>().unwrap();
+                let is_loaded =
+                    pages
+                        .borrow_mut()
+                        .load_page(&page_id, application.clone(), &split_view_ref);
 
-                match page_enum {
-                    Page::Main => pages_mut
-                        .main
-                        .load_page(application.clone(), &split_view_ref),
-                    Page::Surround => pages_mut
-                        .surround
-                        .load_page(application.clone(), &split_view_ref),
-                };
+                match is_loaded {
+                    true => application.preferences.set_last_page(&page_id),
+                    false => warn!(target: Self::LOG_TARGET, "No page with id: {}", page_id),
+                }
             })
             .build();
 
@@ -1,17 +1,36 @@
 use libadwaita::{
     gio::{ActionEntry, Menu, MenuItem, SimpleActionGroup, prelude::ActionMapExtManual},
+    glib::{self, VariantTy, variant::ToVariant},
     gtk::MenuButton,
 };
+use log::error;
+use std::rc::Rc;
 
-use crate::application::window::ApplicationWindow;
+use crate::{
+    application::{
+        Application,
+        pages::registry,
+        pipewire::preset::PwPreset,
+        window::{
+            ApplicationWindow, apply_dialog::ApplyDialog, preferences_window::PreferencesWindow,
+        },
+    },
+    i18n::gettext,
+};
 
 pub struct AppMenu {
     pub button: MenuButton,
     pub actions: SimpleActionGroup,
+    menu: Menu,
+    presets_menu: Menu,
 }
 impl AppMenu {
     pub const NAME: &str = "app-menu";
     pub const ACTION_LABEL: &str = "app-menu";
+    pub const ACTIVATE_PRESET_ACTION_LABEL: &str = "activate-preset";
+    pub const MANAGE_PRESETS_ACTION_LABEL: &str = "manage-presets";
+    pub const PREFERENCES_ACTION_LABEL: &str = "preferences";
+    const LOG_TARGET: &str = Self::NAME;
 
     pub fn new() -> Self {
         // GTK does not let a popovermenu to be created programmatically
@@ -21,18 +40,112 @@ impl AppMenu {
             .icon_name("open-menu-symbolic")
             .build();
         let menu = Menu::new();
+        let presets_menu = Menu::new();
         let actions = SimpleActionGroup::new();
 
         button.set_menu_model(Some(&menu));
 
         Self::add_about(&menu, &actions);
 
-        return Self { button, actions };
+        return Self {
+            button,
+            actions,
+            menu,
+            presets_menu,
+        };
+    }
+
+    pub fn init(&self, application: &Rc<Application>) {
+        let activate_application = application.clone();
+        let activate_action = ActionEntry::builder(Self::ACTIVATE_PRESET_ACTION_LABEL)
+            .parameter_type(Some(VariantTy::STRING))
+            .activate(move |_: &SimpleActionGroup, _, parameter| {
+                let name = parameter.unwrap().try_get::<String>().unwrap();
+                let application = activate_application.clone();
+
+                match application.pipewire.presets.get(&name) {
+                    Ok(preset) => {
+                        glib::spawn_future_local(ApplyDialog::activate_preset(application, preset));
+                    }
+                    Err(error) => error!(target: Self::LOG_TARGET, "{:?}", error),
+                }
+            })
+            .build();
+        let manage_application = application.clone();
+        let manage_action = ActionEntry::builder(Self::MANAGE_PRESETS_ACTION_LABEL)
+            .activate(move |_: &SimpleActionGroup, _, _| {
+                manage_application
+                    .window
+                    .view
+                    .navigate(registry::PRESETS_PAGE);
+            })
+            .build();
+        let preferences_application = application.clone();
+        let preferences_action = ActionEntry::builder(Self::PREFERENCES_ACTION_LABEL)
+            .activate(move |_: &SimpleActionGroup, _, _| {
+                PreferencesWindow::show(&preferences_application);
+            })
+            .build();
+        self.actions
+            .add_action_entries([activate_action, manage_action, preferences_action]);
+
+        self.menu
+            .prepend_submenu(Some(&gettext("Presets")), &self.presets_menu);
+        // Between the presets and About
+        self.menu.insert(
+            1,
+            Some(&gettext("Preferences")),
+            Some(&format!(
+                "{}.{}",
+                Self::ACTION_LABEL,
+                Self::PREFERENCES_ACTION_LABEL
+            )),
+        );
+
+        match application.pipewire.presets.list() {
+            Ok(presets) => self.set_presets(&presets),
+            Err(error) => {
+                error!(target: Self::LOG_TARGET, "Listing presets failed:\n{:?}", error);
+                self.set_presets(&[]);
+            }
+        }
+    }
+
+    /// Lists the presets to activate, call again when they changed.
+    pub fn set_presets(&self, presets: &[PwPreset]) {
+        self.presets_menu.remove_all();
+
+        let preset_items = Menu::new();
+        for preset in presets {
+            let item = MenuItem::new(Some(&preset.name), None);
+            item.set_action_and_target_value(
+                Some(&format!(
+                    "{}.{}",
+                    Self::ACTION_LABEL,
+                    Self::ACTIVATE_PRESET_ACTION_LABEL
+                )),
+                Some(&preset.name.to_variant()),
+            );
+            preset_items.append_item(&item);
+        }
+        self.presets_menu.append_section(None, &preset_items);
+
+        self.presets_menu.append(
+            Some(&gettext("Manage Presets")),
+            Some(&format!(
+                "{}.{}",
+                Self::ACTION_LABEL,
+                Self::MANAGE_PRESETS_ACTION_LABEL
+            )),
+        );
     }
 
     fn add_about(menu: &Menu, actions: &SimpleActionGroup) {
-        let item =
-            Self::build_menu_item("About", ("about", ApplicationWindow::show_about), actions);
+        let item = Self::build_menu_item(
+            &gettext("About"),
+            ("about", ApplicationWindow::show_about),
+            actions,
+        );
         menu.prepend_item(&item);
     }
 
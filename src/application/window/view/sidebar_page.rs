@@ -1,10 +1,22 @@
-use super::{NavPage, Page};
-use crate::application::{pages::PageState, window::view::View};
+use crate::{
+    application::{
+        Application,
+        pages::{NavPage, PageState, registry::PageDescriptor},
+        window::view::View,
+    },
+    i18n::gettext,
+};
 use libadwaita::{
-    ActionRow, HeaderBar, NavigationPage, ToolbarView,
-    glib::Variant,
-    gtk::{ListBox, SelectionMode},
+    ActionRow, HeaderBar, NavigationPage, StatusPage, ToolbarView,
+    glib::{self, Variant, object::ObjectExt},
+    gtk::{
+        Image, Label, ListBox, ListBoxRow, ScrolledWindow, SearchBar, SearchEntry, SelectionMode,
+        Stack, ToggleButton,
+        prelude::{EditableExt, WidgetExt},
+    },
+    prelude::ActionRowExt,
 };
+use std::rc::Rc;
 
 pub struct SidebarPage {
     pub nav_page: NavigationPage,
@@ -12,21 +24,66 @@ pub struct SidebarPage {
     state: PageState,
     title: String,
     list: ListBox,
+    search_entry: SearchEntry,
+    results: ListBox,
+    stack: Stack,
 }
 impl NavPage for SidebarPage {
     const LABEL: &str = "sidebar-page";
     const LOG_TARGET: &str = Self::LABEL;
 
-    fn new() -> Self {
-        let title = String::from("List");
+    fn is_init(&self) -> bool {
+        self.get_state().get_init()
+    }
+
+    fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    fn get_state(&self) -> &PageState {
+        &self.state
+    }
+
+    fn init(&mut self, _application: std::rc::Rc<crate::application::Application>) {
+        self.state.set_init(true);
+    }
+
+    fn get_navpage(&self) -> &NavigationPage {
+        &self.nav_page
+    }
+}
+impl SidebarPage {
+    pub fn new() -> Self {
+        let title = gettext("List");
         let list = ListBox::builder()
             .selection_mode(SelectionMode::Single)
             .css_classes(["navigation-sidebar"])
             .build();
         let header = HeaderBar::new();
+        let (search_button, search_bar, search_entry) = Self::build_search();
+        header.pack_start(&search_button);
+
+        let results = ListBox::builder()
+            .selection_mode(SelectionMode::None)
+            .css_classes(["navigation-sidebar"])
+            .build();
+        let no_results = StatusPage::builder()
+            .icon_name("edit-find-symbolic")
+            .title(gettext("No Results Found"))
+            .css_classes(["compact"])
+            .build();
+        let stack = Stack::new();
+        stack.add_named(&list, Some(Self::STACK_PAGES));
+        stack.add_named(
+            &ScrolledWindow::builder().child(&results).build(),
+            Some(Self::STACK_RESULTS),
+        );
+        stack.add_named(&no_results, Some(Self::STACK_NO_RESULTS));
+
         let toolbar = ToolbarView::new();
         toolbar.add_top_bar(&header);
-        toolbar.set_content(Some(&list));
+        toolbar.add_top_bar(&search_bar);
+        toolbar.set_content(Some(&stack));
 
         let nav_page = NavigationPage::builder()
             .title(&title)
@@ -42,39 +99,115 @@ impl NavPage for SidebarPage {
             list,
             state,
             title,
+            search_entry,
+            results,
+            stack,
         };
     }
 
-    fn is_init(&self) -> bool {
-        self.get_state().get_init()
-    }
+    const STACK_PAGES: &str = "pages";
+    const STACK_RESULTS: &str = "results";
+    const STACK_NO_RESULTS: &str = "no-results";
 
-    fn get_title(&self) -> &str {
-        &self.title
-    }
+    /// Searches the settings of all pages while typing in the search entry.
+    pub fn init_search(&self, application: &Rc<Application>) {
+        let application = application.clone();
+        let results = self.results.clone();
+        let stack = self.stack.clone();
 
-    fn get_state(&self) -> &PageState {
-        &self.state
+        self.search_entry
+            .connect_search_changed(move |search_entry| {
+                while let Some(child) = results.first_child() {
+                    results.remove(&child);
+                }
+
+                let query = search_entry.text();
+                if query.trim().is_empty() {
+                    stack.set_visible_child_name(Self::STACK_PAGES);
+                    return;
+                }
+
+                let found = application.pages.borrow().search(
+                    &application.pipewire,
+                    &query,
+                    application.preferences.get_show_advanced(),
+                );
+                if found.is_empty() {
+                    stack.set_visible_child_name(Self::STACK_NO_RESULTS);
+                    return;
+                }
+
+                let mut page_title = None;
+                for result in found {
+                    // Grouped by page under its title
+                    if page_title.as_ref() != Some(&result.page_title) {
+                        results.append(&Self::build_results_header(&result.page_title));
+                        page_title = Some(result.page_title.clone());
+                    }
+
+                    let row = ActionRow::builder()
+                        .activatable(true)
+                        .title(glib::markup_escape_text(&result.title))
+                        .subtitle(glib::markup_escape_text(&result.key))
+                        .build();
+                    let application = application.clone();
+                    row.connect_activated(move |_row| {
+                        application.window.view.navigate(result.page_id);
+                        application
+                            .pages
+                            .borrow()
+                            .show_setting(result.page_id, &result.key);
+                    });
+                    results.append(&row);
+                }
+                stack.set_visible_child_name(Self::STACK_RESULTS);
+            });
     }
 
-    fn init(&mut self, _application: std::rc::Rc<crate::application::Application>) {
-        self.state.set_init(true);
+    fn build_search() -> (ToggleButton, SearchBar, SearchEntry) {
+        let search_button = ToggleButton::builder()
+            .icon_name("system-search-symbolic")
+            .tooltip_text(gettext("Search Settings"))
+            .build();
+        let search_entry = SearchEntry::builder()
+            .placeholder_text(gettext("Search settings"))
+            .hexpand(true)
+            .build();
+        let search_bar = SearchBar::builder().child(&search_entry).build();
+        search_bar.connect_entry(&search_entry);
+
+        search_button
+            .bind_property("active", &search_bar, "search-mode-enabled")
+            .bidirectional()
+            .build();
+
+        (search_button, search_bar, search_entry)
     }
 
-    fn get_navpage(&self) -> &NavigationPage {
-        &self.nav_page
+    fn build_results_header(page_title: &str) -> ListBoxRow {
+        let label = Label::builder()
+            .label(page_title)
+            .xalign(0.0)
+            .css_classes(["heading"])
+            .build();
+
+        ListBoxRow::builder()
+            .child(&label)
+            .activatable(false)
+            .selectable(false)
+            .build()
     }
-}
-impl SidebarPage {
-    pub fn add_nav_row(&self, title: &str, page: Page) -> ActionRow {
-        let action_target = Variant::from(page as i32);
+
+    pub fn add_nav_row(&self, descriptor: &PageDescriptor) -> ActionRow {
+        let action_target = Variant::from(descriptor.id);
 
         let row = ActionRow::builder()
             .activatable(true)
             .action_name(View::VIEW_NAVIGATE_ACTION_LABEL)
             .action_target(&action_target)
-            .title(title)
+            .title(descriptor.get_title())
             .build();
+        row.add_prefix(&Image::from_icon_name(descriptor.icon_name));
 
         self.list.append(&row);
 
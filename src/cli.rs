@@ -0,0 +1,417 @@
+use crate::application::pipewire::{
+    config::PwConfig,
+    pending::PwPending,
+    pipewire::{Pipewire, PwAppliedConfigs},
+    schema::PwSchema,
+    services::PwServices,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use libadwaita::glib;
+use log::error;
+use serde_json::{Value, json};
+use std::{cell::RefCell, rc::Rc};
+
+#[derive(Debug, PartialEq)]
+enum CliCommand {
+    List,
+    Get(String),
+    Set(String, Value),
+    Reset(String),
+    Diff,
+    Apply,
+    ActivatePreset(String),
+    Help,
+}
+
+/// Subcommands that edit the same configs as the app, e.g. over SSH.
+/// Pending values are kept between runs until `apply`.
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+    command: CliCommand,
+    /// Prints JSON for scripts instead of text
+    json: bool,
+    /// Restarts the affected services after an apply
+    restart: bool,
+}
+impl Cli {
+    const LOG_TARGET: &str = "cli";
+    const USAGE: &str = "Usage: pipewire-config-gui-gnome [COMMAND] [--json]
+
+Without a command the app is started.
+
+Commands:
+  list                     Settings with their current and pending values
+  get KEY                  Current value of a setting
+  set KEY VALUE            Makes a value pending, VALUE is JSON or text
+  reset KEY                Removes a setting from the drop-in, back to the upstream default
+  diff                     Pending values next to the current ones
+  apply [--restart]        Writes the pending values to the drop-ins
+  preset activate NAME     Replaces the drop-ins with a preset
+
+Options:
+  --json                   Prints JSON instead of text
+  --restart                Restarts the affected services after apply, reset or preset activate";
+
+    /// `None` unless the first argument is a subcommand, the app is started then
+    /// and gets all arguments, e.g. GApplication's `--display :0`.
+    pub fn parse(args: &[String]) -> Result<Option<Self>> {
+        let Some(first) = args.first() else {
+            return Ok(None);
+        };
+
+        let json = args.iter().any(|arg| arg == "--json");
+        let restart = args.iter().any(|arg| arg == "--restart");
+        let positional: Vec<&str> = args
+            .iter()
+            .map(|arg| arg.as_str())
+            .filter(|arg| !arg.starts_with("--"))
+            .collect();
+
+        let get_arg = |index: usize, name: &str| {
+            positional
+                .get(index)
+                .map(|arg| arg.to_string())
+                .context(format!("Missing {}\n\n{}", name, Self::USAGE))
+        };
+
+        let command = match first.as_str() {
+            "help" => CliCommand::Help,
+            "list" => CliCommand::List,
+            "get" => CliCommand::Get(get_arg(1, "KEY")?),
+            "set" => {
+                let value = get_arg(2, "VALUE")?;
+                // Text that is not JSON, e.g. `psd`, is taken as a string
+                let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+                CliCommand::Set(get_arg(1, "KEY")?, value)
+            }
+            "reset" => CliCommand::Reset(get_arg(1, "KEY")?),
+            "diff" => CliCommand::Diff,
+            "apply" => CliCommand::Apply,
+            "preset" => match positional.get(1) {
+                Some(&"activate") => CliCommand::ActivatePreset(get_arg(2, "NAME")?),
+                _ => bail!("Unknown preset command\n\n{}", Self::USAGE),
+            },
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Self {
+            command,
+            json,
+            restart,
+        }))
+    }
+
+    pub fn run(&self) -> Result<()> {
+        if self.command == CliCommand::Help {
+            println!("{}", Self::USAGE);
+            return Ok(());
+        }
+
+        let pipewire = Pipewire::new()?;
+        let pending = PwPending::new();
+        for pw_config in pipewire.get_configs() {
+            pending.load(&mut pw_config.borrow_mut())?;
+        }
+
+        match &self.command {
+            CliCommand::List => self.list(&pipewire),
+            CliCommand::Get(key) => self.get(&pipewire, key),
+            CliCommand::Set(key, value) => self.set(&pipewire, &pending, key, value.to_owned()),
+            CliCommand::Reset(key) => self.reset(&pipewire, &pending, key),
+            CliCommand::Diff => self.diff(&pipewire),
+            CliCommand::Apply => self.apply(&pipewire, &pending),
+            CliCommand::ActivatePreset(name) => self.activate_preset(&pipewire, name),
+            CliCommand::Help => Ok(()),
+        }
+    }
+
+    fn list(&self, pipewire: &Pipewire) -> Result<()> {
+        let mut settings = Vec::new();
+        let mut text = Vec::new();
+
+        for pw_config in pipewire.get_configs() {
+            let pw_config = pw_config.borrow();
+            let mut keys: Vec<&String> = pw_config.default.keys().collect();
+            keys.sort();
+
+            text.push(pw_config.get_name());
+            for key in keys {
+                let setting = Self::get_setting(&pw_config, key);
+                let mut line = format!("  {} = {}", key, Self::to_text(&setting["value"]));
+                if let Some(pending) = pw_config.new.get(key) {
+                    line.push_str(&format!(" (pending: {})", Self::to_text(pending)));
+                }
+                text.push(line);
+                settings.push(setting);
+            }
+        }
+
+        self.print(json!(settings), &text.join("\n"));
+
+        Ok(())
+    }
+
+    fn get(&self, pipewire: &Pipewire, key: &str) -> Result<()> {
+        let pw_config = Self::find_config(pipewire, key)?;
+        let setting = Self::get_setting(&pw_config.borrow(), key);
+
+        self.print(setting.clone(), &Self::to_text(&setting["value"]));
+
+        Ok(())
+    }
+
+    fn set(&self, pipewire: &Pipewire, pending: &PwPending, key: &str, value: Value) -> Result<()> {
+        let pw_config = Self::find_config(pipewire, key)?;
+        PwSchema::bundled()
+            .validate(key, &value)
+            .map_err(|reason| anyhow!("{} {}", key, reason))?;
+
+        pw_config
+            .borrow_mut()
+            .new
+            .insert(key.to_owned(), value.to_owned());
+        Self::save_pending(pipewire, pending)?;
+
+        let setting = Self::get_setting(&pw_config.borrow(), key);
+        self.print(
+            setting,
+            &format!("{} = {} (pending)", key, Self::to_text(&value)),
+        );
+
+        Ok(())
+    }
+
+    /// Drops the key from the pending values and the drop-in, so the upstream default is used.
+    fn reset(&self, pipewire: &Pipewire, pending: &PwPending, key: &str) -> Result<()> {
+        let pw_config = Self::find_config(pipewire, key)?;
+        if pw_config.borrow_mut().new.remove(key).is_some() {
+            Self::save_pending(pipewire, pending)?;
+        }
+
+        let mut values = pw_config.borrow().get_applied()?;
+        if values.remove(key).is_none() {
+            self.print(
+                json!({ "applied": [], "restarted": [] }),
+                &format!("{} is not set in the drop-in", key),
+            );
+            return Ok(());
+        }
+
+        if let Err(error) = pipewire.history.snapshot_initial() {
+            error!(target: Self::LOG_TARGET, "Initial snapshot failed:\n{:?}", error);
+        }
+
+        let applied = pw_config
+            .borrow_mut()
+            .activate(values)?
+            .context(format!("{} is not set in the drop-in", key))?;
+        let summary = format!("Reset {}", key);
+        if let Err(error) = pipewire.history.snapshot(&summary) {
+            error!(target: Self::LOG_TARGET, "Snapshot failed:\n{:?}", error);
+        }
+
+        self.finish(vec![(pw_config.clone(), applied)], vec![summary])
+    }
+
+    fn diff(&self, pipewire: &Pipewire) -> Result<()> {
+        let mut changes = Vec::new();
+        let mut text = Vec::new();
+
+        for pw_config in pipewire.get_configs() {
+            let pw_config = pw_config.borrow();
+            for (key, value) in &pw_config.new {
+                let setting = Self::get_setting(&pw_config, key);
+                text.push(format!(
+                    "{}: {} -> {}",
+                    key,
+                    Self::to_text(&setting["value"]),
+                    Self::to_text(value)
+                ));
+                changes.push(setting);
+            }
+        }
+
+        match text.is_empty() {
+            true => self.print(json!(changes), "Nothing pending"),
+            false => self.print(json!(changes), &text.join("\n")),
+        }
+
+        Ok(())
+    }
+
+    fn apply(&self, pipewire: &Pipewire, pending: &PwPending) -> Result<()> {
+        let has_pending = pipewire
+            .get_configs()
+            .iter()
+            .any(|pw_config| !pw_config.borrow().new.is_empty());
+        if !has_pending {
+            self.print(json!({ "applied": [], "restarted": [] }), "Nothing pending");
+            return Ok(());
+        }
+
+        if let Err(error) = pipewire.history.snapshot_initial() {
+            error!(target: Self::LOG_TARGET, "Initial snapshot failed:\n{:?}", error);
+        }
+
+        let applied_configs = pipewire.apply_pending()?;
+        Self::save_pending(pipewire, pending)?;
+
+        let summaries: Vec<String> = applied_configs
+            .iter()
+            .map(|(_pw_config, applied)| applied.get_summary())
+            .collect();
+        if let Err(error) = pipewire.history.snapshot(&summaries.join("\n")) {
+            error!(target: Self::LOG_TARGET, "Snapshot failed:\n{:?}", error);
+        }
+
+        self.finish(applied_configs, summaries)
+    }
+
+    fn activate_preset(&self, pipewire: &Pipewire, name: &str) -> Result<()> {
+        let preset = pipewire.presets.get(name)?;
+        if let Err(error) = pipewire.history.snapshot_initial() {
+            error!(target: Self::LOG_TARGET, "Initial snapshot failed:\n{:?}", error);
+        }
+
+        let applied_configs = pipewire.activate_preset(&preset)?;
+        if applied_configs.is_empty() {
+            self.print(
+                json!({ "applied": [], "restarted": [] }),
+                &format!("Preset {} is already active", name),
+            );
+            return Ok(());
+        }
+
+        let summary = format!("Activated preset {}", name);
+        if let Err(error) = pipewire.history.snapshot(&summary) {
+            error!(target: Self::LOG_TARGET, "Snapshot failed:\n{:?}", error);
+        }
+
+        self.finish(applied_configs, vec![summary])
+    }
+
+    /// Restarts the affected services when asked, or else tells which ones to restart.
+    fn finish(&self, applied_configs: PwAppliedConfigs, summaries: Vec<String>) -> Result<()> {
+        let files: Vec<_> = applied_configs
+            .iter()
+            .map(|(_pw_config, applied)| applied.config_file)
+            .collect();
+        let units = PwServices::get_affected_units(&files);
+
+        let mut text = summaries.clone();
+        let restarted = match self.restart {
+            true => {
+                let restarted = glib::MainContext::default()
+                    .block_on(async { PwServices::connect().await?.restart(&units).await })?;
+                text.push(format!("Restarted {}", restarted.join(", ")));
+                restarted
+            }
+            false => {
+                text.push(format!(
+                    "Restart {} for the settings to take effect, or use --restart",
+                    units.join(", ")
+                ));
+                Vec::new()
+            }
+        };
+
+        self.print(
+            json!({ "applied": summaries, "units": units, "restarted": restarted }),
+            &text.join("\n"),
+        );
+
+        Ok(())
+    }
+
+    fn find_config<'a>(pipewire: &'a Pipewire, key: &str) -> Result<&'a Rc<RefCell<PwConfig>>> {
+        pipewire
+            .get_configs()
+            .into_iter()
+            .find(|pw_config| pw_config.borrow().default.contains_key(key))
+            .context(format!(
+                "Unknown setting {}, `list` shows all settings",
+                key
+            ))
+    }
+
+    fn save_pending(pipewire: &Pipewire, pending: &PwPending) -> Result<()> {
+        let configs: Vec<_> = pipewire
+            .get_configs()
+            .into_iter()
+            .map(|pw_config| pw_config.borrow())
+            .collect();
+        let configs: Vec<&PwConfig> = configs.iter().map(|pw_config| &**pw_config).collect();
+
+        pending.save(&configs)
+    }
+
+    /// The current value is the upstream default when no config file sets it.
+    fn get_setting(pw_config: &PwConfig, key: &str) -> Value {
+        let default = pw_config.default.get(key).map(|(value, _options)| value);
+        let value = pw_config.current.get(key).or(default);
+
+        json!({
+            "config": pw_config.get_name(),
+            "key": key,
+            "value": value,
+            "default": default,
+            "pending": pw_config.new.get(key),
+            "description": pw_config.get_description(key),
+        })
+    }
+
+    fn to_text(value: &Value) -> String {
+        match value {
+            Value::String(value) => value.to_owned(),
+            Value::Null => String::from("(unset)"),
+            value => value.to_string(),
+        }
+    }
+
+    fn print(&self, json: Value, text: &str) {
+        match self.json {
+            true => println!("{}", serde_json::to_string_pretty(&json).unwrap()),
+            false => println!("{}", text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cli, CliCommand};
+    use serde_json::json;
+
+    fn parse(args: &[&str]) -> Option<Cli> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Cli::parse(&args).unwrap()
+    }
+
+    #[test]
+    fn it_should_parse_subcommands() {
+        assert_eq!(parse(&[]), None);
+        assert_eq!(parse(&["--gapplication-service"]), None);
+        assert_eq!(parse(&["--display", ":0"]), None);
+        assert_eq!(parse(&["frobnicate"]), None);
+
+        let set = parse(&["set", "channelmix.upmix", "true", "--json"]).unwrap();
+        assert_eq!(
+            set.command,
+            CliCommand::Set("channelmix.upmix".to_string(), json!(true))
+        );
+        assert!(set.json);
+        assert_eq!(
+            parse(&["set", "channelmix.upmix-method", "psd"])
+                .unwrap()
+                .command,
+            CliCommand::Set("channelmix.upmix-method".to_string(), json!("psd"))
+        );
+        assert_eq!(
+            parse(&["preset", "activate", "Movies"]).unwrap().command,
+            CliCommand::ActivatePreset("Movies".to_string())
+        );
+        assert!(parse(&["apply", "--restart"]).unwrap().restart);
+
+        let args: Vec<String> = vec!["get".to_string()];
+        assert!(Cli::parse(&args).is_err());
+    }
+}
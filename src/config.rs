@@ -1,3 +1,13 @@
 pub const APP_ID: &str = "org.pvermeer.pipewire-config-gnome";
 pub const VERSION: &str = "0.0.0";
 pub const APP_NAME: &str = "Pipewire config gnome";
+pub const GETTEXT_PACKAGE: &str = "pipewire-config-gnome";
+/// Where the compiled catalogs are installed, set `LOCALE_DIR` when building a package.
+/// Without it the catalogs build.rs compiled from po/ are used.
+pub const LOCALE_DIR: &str = match option_env!("LOCALE_DIR") {
+    Some(locale_dir) => locale_dir,
+    None => match option_env!("BUILD_LOCALE_DIR") {
+        Some(locale_dir) => locale_dir,
+        None => "/usr/share/locale",
+    },
+};
@@ -0,0 +1,53 @@
+use crate::config;
+use libadwaita::glib;
+use log::warn;
+use std::ffi::{CString, c_char};
+
+const LOG_TARGET: &str = "i18n";
+
+unsafe extern "C" {
+    fn bindtextdomain(domain_name: *const c_char, dir_name: *const c_char) -> *mut c_char;
+    fn bind_textdomain_codeset(domain_name: *const c_char, codeset: *const c_char) -> *mut c_char;
+    fn textdomain(domain_name: *const c_char) -> *mut c_char;
+}
+
+/// Uses the user's locale and the app's catalogs in the locale dir, before any string is shown.
+pub fn init() {
+    let (Ok(domain), Ok(locale_dir)) = (
+        CString::new(config::GETTEXT_PACKAGE),
+        CString::new(config::LOCALE_DIR),
+    ) else {
+        warn!(target: LOG_TARGET, "Invalid gettext package or locale dir");
+        return;
+    };
+
+    // Safe, called from main before any other thread is started
+    unsafe {
+        if libc::setlocale(libc::LC_ALL, c"".as_ptr()).is_null() {
+            warn!(target: LOG_TARGET, "The locale is not supported, using English");
+        }
+        bindtextdomain(domain.as_ptr(), locale_dir.as_ptr());
+        // GTK wants UTF-8, whatever the locale's charset
+        bind_textdomain_codeset(domain.as_ptr(), c"UTF-8".as_ptr());
+        textdomain(domain.as_ptr());
+    }
+}
+
+/// The translation of a UI string, or the string itself when it has none.
+pub fn gettext(msgid: &str) -> String {
+    glib::dgettext(Some(config::GETTEXT_PACKAGE), msgid).into()
+}
+
+/// The translation with each `{}` replaced by the next argument, e.g. `gettext_f("Channel {}", &["3"])`.
+pub fn gettext_f(msgid: &str, args: &[&str]) -> String {
+    let translation = gettext(msgid);
+    let mut parts = translation.split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+
+    for (index, part) in parts.enumerate() {
+        text.push_str(args.get(index).copied().unwrap_or_default());
+        text.push_str(part);
+    }
+
+    text
+}
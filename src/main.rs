@@ -1,21 +1,39 @@
 mod application;
+mod cli;
 mod config;
+mod i18n;
 
 use anyhow::Result;
-use application::Application;
+use application::{Application, service::DbusService};
+use cli::Cli;
 use env_logger::Env;
 use libadwaita::gio::prelude::{ApplicationExt, ApplicationExtManual};
+use std::env;
 
 fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(cli) = Cli::parse(&args)? {
+        // Only warnings, the output is for scripts
+        env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+        return cli.run();
+    }
+
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    // The CLI's output is for scripts and stays English
+    i18n::init();
 
     let adw_application = libadwaita::Application::builder()
         .application_id(config::APP_ID)
         .build();
 
-    adw_application.connect_activate(|adw_application| {
-        let application = Application::new(adw_application);
-        Application::run_app(&application);
+    // Exported once, the app started on activate takes it over
+    let service = DbusService::new();
+    let startup_service = service.clone();
+    adw_application.connect_startup(move |adw_application| {
+        startup_service.export(adw_application);
+    });
+    adw_application.connect_activate(move |adw_application| {
+        Application::start(adw_application, service.clone());
     });
 
     adw_application.run();